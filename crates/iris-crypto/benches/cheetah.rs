@@ -0,0 +1,100 @@
+//! Scalar multiplication and signature benchmarks.
+//!
+//! `double_and_add` is the plain binary method that `ch_scal_big` used to be, kept here as the
+//! baseline: `cargo bench -p iris-crypto --bench cheetah`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use ibig::UBig;
+use iris_crypto::{verify_batch, PrivateKey};
+use iris_ztd::crypto::cheetah::{
+    ch_add, ch_double, ch_double_scal, ch_neg, ch_scal_ct, ch_scal_gen, ch_scal_gen_ct,
+    ch_scal_wnaf, CheetahPoint, A_GEN, A_ID, G_ORDER,
+};
+use iris_ztd::crypto::scalar::Scalar;
+use iris_ztd::{Belt, Digest};
+
+fn double_and_add(n: &UBig, p: &CheetahPoint) -> CheetahPoint {
+    let mut acc = A_ID;
+    let mut p = *p;
+    for i in 0..n.bit_len() {
+        if n.bit(i) {
+            acc = ch_add(&acc, &p).unwrap();
+        }
+        p = ch_double(p).unwrap();
+    }
+    acc
+}
+
+fn scalar() -> UBig {
+    &*G_ORDER - UBig::from(0x1234_5678_9abc_def0u64)
+}
+
+fn scalar_multiplication(c: &mut Criterion) {
+    let n = scalar();
+    let p = PrivateKey(Scalar::from_u64(987654321)).public_key().0;
+    let neg_p = ch_neg(&p);
+    let secret = Scalar::from_ubig(&n);
+    // Build the generator tables outside of the measurements.
+    ch_scal_gen(&n).unwrap();
+    ch_scal_gen_ct(&secret);
+
+    let mut group = c.benchmark_group("scalar multiplication");
+    group.bench_function("fixed base: double-and-add", |b| {
+        b.iter(|| double_and_add(black_box(&n), &A_GEN))
+    });
+    group.bench_function("fixed base: table", |b| {
+        b.iter(|| ch_scal_gen(black_box(&n)).unwrap())
+    });
+    group.bench_function("fixed base: constant time", |b| {
+        b.iter(|| ch_scal_gen_ct(black_box(&secret)))
+    });
+    group.bench_function("variable base: double-and-add", |b| {
+        b.iter(|| double_and_add(black_box(&n), &p))
+    });
+    group.bench_function("variable base: wNAF", |b| {
+        b.iter(|| ch_scal_wnaf(black_box(&n), &p).unwrap())
+    });
+    group.bench_function("variable base: constant time", |b| {
+        b.iter(|| ch_scal_ct(black_box(&secret), &p))
+    });
+    group.bench_function("s*G - c*P: double-and-add", |b| {
+        b.iter(|| {
+            let sg = double_and_add(black_box(&n), &A_GEN);
+            ch_add(&sg, &double_and_add(black_box(&n), &neg_p)).unwrap()
+        })
+    });
+    group.bench_function("s*G - c*P: simultaneous", |b| {
+        b.iter(|| ch_double_scal(black_box(&n), &A_GEN, black_box(&n), &neg_p).unwrap())
+    });
+    group.finish();
+}
+
+fn signatures(c: &mut Criterion) {
+    let items = (1..=32u64)
+        .map(|i| {
+            let key = PrivateKey(Scalar::from_u64(1000 + i));
+            let digest = Digest([Belt(i), Belt(2), Belt(3), Belt(4), Belt(5)]);
+            let sig = key.sign(&digest);
+            (key.public_key(), digest, sig)
+        })
+        .collect::<Vec<_>>();
+    let key = PrivateKey(Scalar::from_ubig(&scalar()));
+    let (public_key, digest, sig) = &items[0];
+
+    let mut group = c.benchmark_group("signatures");
+    group.bench_function("public_key", |b| b.iter(|| black_box(&key).public_key()));
+    group.bench_function("sign", |b| b.iter(|| black_box(&key).sign(digest)));
+    group.bench_function("verify", |b| {
+        b.iter(|| black_box(public_key).verify(digest, sig))
+    });
+    group.bench_function("verify x32", |b| {
+        b.iter(|| black_box(&items).iter().all(|(k, d, s)| k.verify(d, s)))
+    });
+    group.bench_function("verify_batch x32", |b| {
+        b.iter(|| verify_batch(black_box(&items)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, scalar_multiplication, signatures);
+criterion_main!(benches);
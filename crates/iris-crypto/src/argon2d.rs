@@ -0,0 +1,355 @@
+//! Argon2d (version 0x13) with a hook between segments, for progress reporting and cancellation.
+//!
+//! The `argon2` crate runs a hash to completion without any way to observe it. This follows the
+//! same block filling as the reference implementation, and is checked against the RFC 9106 test
+//! vector and against that crate in tests.
+
+use blake2::digest::{Digest, Update, VariableOutput};
+use blake2::{Blake2b512, Blake2bVar};
+use zeroize::Zeroize;
+
+const SYNC_POINTS: usize = 4;
+const BLOCK_WORDS: usize = 128;
+const BLOCK_BYTES: usize = BLOCK_WORDS * 8;
+const VERSION: u32 = 0x13;
+const ARGON2D: u32 = 0;
+
+type Block = [u64; BLOCK_WORDS];
+
+/// The hash was stopped by the segment hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Stopped;
+
+/// Hash `pwd` into `out`, calling `on_segment(completed, total)` after each segment of each lane.
+///
+/// Parameters must have been validated with `argon2::Params::new`, and `salt` must be at least 8
+/// bytes. Returning `false` from `on_segment` stops the hash.
+pub(crate) fn hash(
+    pwd: &[u8],
+    salt: &[u8],
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+    out: &mut [u8],
+    on_segment: impl FnMut(u32, u32) -> bool,
+) -> Result<(), Stopped> {
+    hash_keyed(pwd, salt, &[], &[], m_cost, t_cost, p_cost, out, on_segment)
+}
+
+/// [`hash`] with a secret key and associated data, as in the test vectors of RFC 9106.
+#[allow(clippy::too_many_arguments)]
+fn hash_keyed(
+    pwd: &[u8],
+    salt: &[u8],
+    secret: &[u8],
+    ad: &[u8],
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+    out: &mut [u8],
+    mut on_segment: impl FnMut(u32, u32) -> bool,
+) -> Result<(), Stopped> {
+    let lanes = p_cost as usize;
+    let segment_length = (m_cost as usize).max(2 * SYNC_POINTS * lanes) / (lanes * SYNC_POINTS);
+    let lane_length = segment_length * SYNC_POINTS;
+    let mut memory: Vec<Block> = vec![[0; BLOCK_WORDS]; lane_length * lanes];
+
+    let mut h0 = Blake2b512::new();
+    Digest::update(&mut h0, p_cost.to_le_bytes());
+    Digest::update(&mut h0, (out.len() as u32).to_le_bytes());
+    Digest::update(&mut h0, m_cost.to_le_bytes());
+    Digest::update(&mut h0, t_cost.to_le_bytes());
+    Digest::update(&mut h0, VERSION.to_le_bytes());
+    Digest::update(&mut h0, ARGON2D.to_le_bytes());
+    Digest::update(&mut h0, (pwd.len() as u32).to_le_bytes());
+    Digest::update(&mut h0, pwd);
+    Digest::update(&mut h0, (salt.len() as u32).to_le_bytes());
+    Digest::update(&mut h0, salt);
+    Digest::update(&mut h0, (secret.len() as u32).to_le_bytes());
+    Digest::update(&mut h0, secret);
+    Digest::update(&mut h0, (ad.len() as u32).to_le_bytes());
+    Digest::update(&mut h0, ad);
+    let mut h0 = h0.finalize();
+
+    let mut bytes = [0u8; BLOCK_BYTES];
+    for lane in 0..lanes {
+        for i in 0..2 {
+            blake2b_long(
+                &[&h0, &(i as u32).to_le_bytes(), &(lane as u32).to_le_bytes()],
+                &mut bytes,
+            );
+            load(&mut memory[lane * lane_length + i], &bytes);
+        }
+    }
+    h0.zeroize();
+
+    let total = t_cost * SYNC_POINTS as u32 * p_cost;
+    let mut completed = 0;
+    let result = (|| {
+        for pass in 0..t_cost as usize {
+            for slice in 0..SYNC_POINTS {
+                for lane in 0..lanes {
+                    fill_segment(&mut memory, pass, slice, lane, segment_length, lanes);
+                    completed += 1;
+                    if !on_segment(completed, total) {
+                        return Err(Stopped);
+                    }
+                }
+            }
+        }
+        Ok(())
+    })();
+
+    if result.is_ok() {
+        let mut last = memory[lane_length - 1];
+        for lane in 1..lanes {
+            xor(&mut last, &memory[lane * lane_length + lane_length - 1]);
+        }
+        for (chunk, word) in bytes.chunks_exact_mut(8).zip(last.iter()) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        blake2b_long(&[&bytes], out);
+        last.zeroize();
+    }
+    bytes.zeroize();
+    memory.zeroize();
+    result
+}
+
+fn fill_segment(
+    memory: &mut [Block],
+    pass: usize,
+    slice: usize,
+    lane: usize,
+    segment_length: usize,
+    lanes: usize,
+) {
+    let lane_length = segment_length * SYNC_POINTS;
+    // The first two blocks of each lane are already initialized.
+    let first_block = if pass == 0 && slice == 0 { 2 } else { 0 };
+
+    let segment_start = lane * lane_length + slice * segment_length;
+    let mut prev_index = if slice == 0 && first_block == 0 {
+        segment_start + lane_length - 1
+    } else {
+        segment_start + first_block - 1
+    };
+
+    for block in first_block..segment_length {
+        let cur_index = segment_start + block;
+        let rand = memory[prev_index][0];
+
+        let ref_lane = if pass == 0 && slice == 0 {
+            lane
+        } else {
+            (rand >> 32) as usize % lanes
+        };
+        let reference_area_size = if pass == 0 {
+            if slice == 0 {
+                block - 1
+            } else if ref_lane == lane {
+                slice * segment_length + block - 1
+            } else {
+                slice * segment_length - usize::from(block == 0)
+            }
+        } else if ref_lane == lane {
+            lane_length - segment_length + block - 1
+        } else {
+            lane_length - segment_length - usize::from(block == 0)
+        };
+
+        let map = ((rand & 0xFFFF_FFFF) * (rand & 0xFFFF_FFFF)) >> 32;
+        let relative_position =
+            reference_area_size - 1 - ((reference_area_size as u64 * map) >> 32) as usize;
+        let start_position = if pass != 0 && slice != SYNC_POINTS - 1 {
+            (slice + 1) * segment_length
+        } else {
+            0
+        };
+        let ref_index = ref_lane * lane_length + (start_position + relative_position) % lane_length;
+
+        let result = compress(&memory[prev_index], &memory[ref_index]);
+        if pass == 0 {
+            memory[cur_index] = result;
+        } else {
+            xor(&mut memory[cur_index], &result);
+        }
+
+        prev_index = cur_index;
+    }
+}
+
+fn load(block: &mut Block, bytes: &[u8; BLOCK_BYTES]) {
+    for (word, chunk) in block.iter_mut().zip(bytes.chunks_exact(8)) {
+        *word = u64::from_le_bytes(chunk.try_into().unwrap());
+    }
+}
+
+fn xor(block: &mut Block, other: &Block) {
+    for (a, b) in block.iter_mut().zip(other.iter()) {
+        *a ^= b;
+    }
+}
+
+#[inline(always)]
+fn blamka(x: u64, y: u64) -> u64 {
+    x.wrapping_add(y)
+        .wrapping_add(2u64.wrapping_mul((x & 0xFFFF_FFFF) * (y & 0xFFFF_FFFF)))
+}
+
+#[inline(always)]
+fn gb(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize) {
+    v[a] = blamka(v[a], v[b]);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = blamka(v[c], v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = blamka(v[a], v[b]);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = blamka(v[c], v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+#[inline(always)]
+fn permute(v: &mut [u64; 16]) {
+    gb(v, 0, 4, 8, 12);
+    gb(v, 1, 5, 9, 13);
+    gb(v, 2, 6, 10, 14);
+    gb(v, 3, 7, 11, 15);
+    gb(v, 0, 5, 10, 15);
+    gb(v, 1, 6, 11, 12);
+    gb(v, 2, 7, 8, 13);
+    gb(v, 3, 4, 9, 14);
+}
+
+/// The Argon2 compression function G.
+fn compress(x: &Block, y: &Block) -> Block {
+    let mut r = *x;
+    xor(&mut r, y);
+    let mut q = r;
+
+    for row in q.chunks_exact_mut(16) {
+        permute(row.try_into().unwrap());
+    }
+    for i in 0..8 {
+        let mut v = [0u64; 16];
+        for j in 0..8 {
+            v[2 * j] = q[2 * i + 16 * j];
+            v[2 * j + 1] = q[2 * i + 16 * j + 1];
+        }
+        permute(&mut v);
+        for j in 0..8 {
+            q[2 * i + 16 * j] = v[2 * j];
+            q[2 * i + 16 * j + 1] = v[2 * j + 1];
+        }
+    }
+
+    xor(&mut q, &r);
+    q
+}
+
+/// The variable-length hash H' from RFC 9106.
+fn blake2b_long(inputs: &[&[u8]], out: &mut [u8]) {
+    let len = (out.len() as u32).to_le_bytes();
+    if out.len() <= 64 {
+        let mut digest = Blake2bVar::new(out.len()).unwrap();
+        Update::update(&mut digest, &len);
+        for input in inputs {
+            Update::update(&mut digest, input);
+        }
+        digest.finalize_variable(out).unwrap();
+        return;
+    }
+
+    let mut digest = Blake2b512::new();
+    Digest::update(&mut digest, len);
+    for input in inputs {
+        Digest::update(&mut digest, input);
+    }
+    let mut last = digest.finalize();
+    out[..32].copy_from_slice(&last[..32]);
+
+    let mut written = 32;
+    while out.len() - written > 64 {
+        last = Blake2b512::digest(last);
+        out[written..written + 32].copy_from_slice(&last[..32]);
+        written += 32;
+    }
+
+    let mut digest = Blake2bVar::new(out.len() - written).unwrap();
+    Update::update(&mut digest, &last);
+    digest.finalize_variable(&mut out[written..]).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use argon2::{Algorithm, Argon2, Params, Version};
+
+    #[test]
+    fn test_matches_argon2_crate() {
+        for (m_cost, t_cost, p_cost) in [
+            (8, 1, 1),
+            (64, 2, 1),
+            (32, 1, 4),
+            (100, 3, 4),
+            (256, 1, 3),
+            (1024, 2, 4),
+        ] {
+            let mut expected = [0u8; 32];
+            Argon2::new(
+                Algorithm::Argon2d,
+                Version::V0x13,
+                Params::new(m_cost, t_cost, p_cost, Some(32)).unwrap(),
+            )
+            .hash_password_into(b"password", b"somesalt", &mut expected)
+            .unwrap();
+
+            let mut calls = 0;
+            let mut out = [0u8; 32];
+            hash(
+                b"password",
+                b"somesalt",
+                m_cost,
+                t_cost,
+                p_cost,
+                &mut out,
+                |completed, total| {
+                    calls += 1;
+                    assert_eq!(completed, calls);
+                    assert_eq!(total, t_cost * 4 * p_cost);
+                    true
+                },
+            )
+            .unwrap();
+            assert_eq!(out, expected);
+            assert_eq!(calls, t_cost * 4 * p_cost);
+        }
+
+        let mut out = [0u8; 32];
+        let stopped = hash(b"password", b"somesalt", 64, 2, 1, &mut out, |c, _| c < 3);
+        assert_eq!(stopped, Err(Stopped));
+        assert_eq!(out, [0u8; 32]);
+    }
+
+    #[test]
+    fn test_rfc9106_vector() {
+        // RFC 9106, section 5.1.
+        let mut out = [0u8; 32];
+        hash_keyed(
+            &[1; 32],
+            &[2; 16],
+            &[3; 8],
+            &[4; 12],
+            32,
+            3,
+            4,
+            &mut out,
+            |_, _| true,
+        )
+        .unwrap();
+        assert_eq!(
+            hex::encode(out),
+            "512b391b6f1162975371d30919734294f868e3be3984f3c1a13a4db9fabe4acb"
+        );
+    }
+}
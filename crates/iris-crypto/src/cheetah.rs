@@ -1,10 +1,12 @@
 use ibig::UBig;
 use iris_ztd::{
     crypto::cheetah::{
-        ch_add, ch_neg, ch_scal_big, trunc_g_order, CheetahPoint, F6lt, A_GEN, G_ORDER,
+        ch_add, ch_double_scal, ch_double_scal_batch, ch_neg, ch_scal_gen_ct, trunc_g_order,
+        trunc_g_order_ct, CheetahPoint, F6lt, A_GEN, G_ORDER,
     },
+    crypto::scalar::Scalar,
     tip5::hash::hash_varlen,
-    Belt, Digest, Hashable, Noun, NounDecode, NounEncode,
+    Belt, Digest, Hashable, Noun, NounDecode, NounEncode, PRIME,
 };
 use iris_ztd_derive::{NounDecode, NounEncode};
 extern crate alloc;
@@ -23,30 +25,12 @@ impl PublicKey {
         }
 
         // Compute scalar = s*G - c*pubkey
-        // This is equivalent to: scalar = s*G + (-c)*pubkey
-        let sg = match ch_scal_big(&sig.s, &A_GEN) {
+        // This is equivalent to: scalar = s*G + c*(-pubkey)
+        let scalar = match ch_double_scal(&sig.s, &A_GEN, &sig.c, &ch_neg(&self.0)) {
             Ok(pt) => pt,
             Err(_) => return false,
         };
-        let c_pk = match ch_scal_big(&sig.c, &self.0) {
-            Ok(pt) => pt,
-            Err(_) => return false,
-        };
-        let scalar = match ch_add(&sg, &ch_neg(&c_pk)) {
-            Ok(pt) => pt,
-            Err(_) => return false,
-        };
-        let chal = {
-            let mut transcript: Vec<Belt> = Vec::new();
-            transcript.extend_from_slice(&scalar.x.0);
-            transcript.extend_from_slice(&scalar.y.0);
-            transcript.extend_from_slice(&self.0.x.0);
-            transcript.extend_from_slice(&self.0.y.0);
-            transcript.extend_from_slice(&m.0);
-            trunc_g_order(&hash_varlen(&mut transcript))
-        };
-
-        chal == sig.c
+        signature_challenge(&scalar, self, m) == sig.c
     }
 
     pub fn to_be_bytes(&self) -> [u8; 97] {
@@ -93,6 +77,22 @@ impl PublicKey {
         })
     }
 
+    /// Like [`PublicKey::from_be_bytes`], but checks the length and prefix, and that the point is
+    /// a valid, non-identity point of the curve.
+    pub fn try_from_be_bytes(bytes: &[u8]) -> Option<PublicKey> {
+        if bytes.len() != 97 || bytes[0] != 0x01 {
+            return None;
+        }
+        let canonical = bytes[1..]
+            .chunks_exact(8)
+            .all(|chunk| u64::from_be_bytes(chunk.try_into().unwrap()) < PRIME);
+        let key = PublicKey::from_be_bytes(bytes);
+        if !canonical || key.0 == CheetahPoint::identity() || !key.0.in_curve() {
+            return None;
+        }
+        Some(key)
+    }
+
     /// SLIP-10 compatible serialization (legacy 65-byte format for compatibility)
     pub(crate) fn to_slip10_bytes(&self) -> Vec<u8> {
         let mut data = Vec::new();
@@ -151,6 +151,66 @@ impl<'a> core::iter::Sum<&'a PublicKey> for PublicKey {
     }
 }
 
+/// The Schnorr challenge `H(R, P, m)` for nonce point `R` and public key `P`.
+pub(crate) fn signature_challenge(r: &CheetahPoint, key: &PublicKey, m: &Digest) -> UBig {
+    let mut transcript: Vec<Belt> = Vec::new();
+    transcript.extend_from_slice(&r.x.0);
+    transcript.extend_from_slice(&r.y.0);
+    transcript.extend_from_slice(&key.0.x.0);
+    transcript.extend_from_slice(&key.0.y.0);
+    transcript.extend_from_slice(&m.0);
+    trunc_g_order(&hash_varlen(&mut transcript))
+}
+
+/// Verify many signatures at once, returning the indices of the invalid ones on failure.
+///
+/// A signature carries its challenge rather than its nonce point, so `R = s*G - c*P` has to be
+/// recovered and hashed for every signature, and it is the recovery that dominates. Here the
+/// recoveries of the whole batch run together (see [`ch_double_scal_batch`]), which for large
+/// batches costs a fraction of calling [`PublicKey::verify`] on each signature.
+pub fn verify_batch(items: &[(PublicKey, Digest, Signature)]) -> Result<(), Vec<usize>> {
+    let (in_range, mut invalid): (Vec<usize>, Vec<usize>) = (0..items.len()).partition(|&i| {
+        let sig = &items[i].2;
+        sig.c != UBig::from(0u64)
+            && sig.c < *G_ORDER
+            && sig.s != UBig::from(0u64)
+            && sig.s < *G_ORDER
+    });
+    let neg_keys = in_range
+        .iter()
+        .map(|&i| ch_neg(&items[i].0 .0))
+        .collect::<Vec<_>>();
+    let terms = in_range
+        .iter()
+        .zip(&neg_keys)
+        .map(|(&i, neg_key)| (&items[i].2.s, &A_GEN, &items[i].2.c, neg_key))
+        .collect::<Vec<_>>();
+
+    match ch_double_scal_batch(&terms) {
+        Ok(nonces) => {
+            for (&i, r) in in_range.iter().zip(nonces) {
+                let (key, m, sig) = &items[i];
+                if signature_challenge(&r, key, m) != sig.c {
+                    invalid.push(i);
+                }
+            }
+        }
+        // Not expected, as the batch only divides by nonzero values; check one by one.
+        Err(_) => invalid.extend(
+            in_range
+                .into_iter()
+                .filter(|&i| !items[i].0.verify(&items[i].1, &items[i].2)),
+        ),
+    }
+
+    if invalid.is_empty() {
+        Ok(())
+    } else {
+        invalid.sort_unstable();
+        Err(invalid)
+    }
+}
+
 impl Hashable for PublicKey {
     fn hash(&self) -> Digest {
         self.to_noun().hash()
@@ -208,41 +268,55 @@ impl Hashable for Signature {
     }
 }
 
+/// A secret scalar. It is kept as a [`Scalar`] throughout, so that no operation on it depends on
+/// its value.
 #[derive(Debug, Clone)]
-pub struct PrivateKey(pub UBig);
+pub struct PrivateKey(pub Scalar);
 
 impl PrivateKey {
+    /// Parse a 32-byte big-endian key, returning `None` unless it is below [`G_ORDER`].
+    pub fn from_be_bytes(bytes: &[u8]) -> Option<Self> {
+        Scalar::from_be_bytes(bytes.try_into().ok()?).map(PrivateKey)
+    }
+
     pub fn public_key(&self) -> PublicKey {
-        PublicKey(ch_scal_big(&self.0, &A_GEN).unwrap())
+        PublicKey(ch_scal_gen_ct(&self.0))
     }
 
     pub fn sign(&self, m: &Digest) -> Signature {
         self.sign_multi(m, &self.nonce_for(m), &self.public_key())
     }
 
-    pub fn nonce_for(&self, m: &Digest) -> UBig {
+    pub fn nonce_for(&self, m: &Digest) -> Scalar {
         let pubkey = self.public_key().0;
         let nonce = {
             let mut transcript = Vec::new();
             transcript.extend_from_slice(&pubkey.x.0);
             transcript.extend_from_slice(&pubkey.y.0);
             transcript.extend_from_slice(&m.0);
-            self.0.to_le_bytes().chunks(4).for_each(|chunk| {
+            // The key's bytes without trailing zeros, as `UBig::to_le_bytes` gives them. Only the
+            // number of chunks depends on the key, and changing it would change every nonce.
+            let bytes = self.0.to_le_bytes();
+            let len = bytes.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+            bytes[..len].chunks(4).for_each(|chunk| {
                 let mut buf = [0u8; 4];
                 buf[..chunk.len()].copy_from_slice(chunk);
                 transcript.push(Belt(u32::from_le_bytes(buf) as u64));
             });
-            trunc_g_order(&hash_varlen(&mut transcript))
+            trunc_g_order_ct(&hash_varlen(&mut transcript))
         };
         nonce
     }
 
-    pub fn combine_nonces(nonces: &[UBig]) -> UBig {
-        nonces.iter().fold(UBig::from(0u64), |acc, x| &acc + x) % &*G_ORDER
+    pub fn combine_nonces(nonces: &[Scalar]) -> Scalar {
+        nonces.iter().fold(Scalar::ZERO, |acc, x| acc + *x)
     }
 
     /// Perform a multiparty sign
     ///
+    /// Public keys are simply summed and nonces are deterministic, so this is only safe between
+    /// signers who trust each other's keys. See [`crate::musig`] for a protocol that is not.
+    ///
     /// # Arguments
     /// * `m` - The digest of message to sign
     /// * `shared_nonce` - The challenge nonce. This is after taking `nonce_for(m)` on all private keys, and combining them with [`PrivateKey::combine_nonces`].
@@ -256,9 +330,9 @@ impl PrivateKey {
     /// ```
     /// # use iris_ztd::{Digest, Belt};
     /// # use iris_crypto::cheetah::*;
-    /// # use ibig::UBig;
-    /// let pk1 = PrivateKey(UBig::from(123u64));
-    /// let pk2 = PrivateKey(UBig::from(456u64));
+    /// # use iris_ztd::crypto::scalar::Scalar;
+    /// let pk1 = PrivateKey(Scalar::from_u64(123));
+    /// let pk2 = PrivateKey(Scalar::from_u64(456));
     /// let m = Digest([Belt(8), Belt(9), Belt(10), Belt(11), Belt(12)]);
     /// let nonce1 = pk1.nonce_for(&m);
     /// let nonce2 = pk2.nonce_for(&m);
@@ -272,12 +346,12 @@ impl PrivateKey {
     pub fn sign_multi(
         &self,
         m: &Digest,
-        shared_nonce: &UBig,
+        shared_nonce: &Scalar,
         combined_pubkey: &PublicKey,
     ) -> Signature {
         let chal = {
             // scalar = nonce * G
-            let scalar = ch_scal_big(shared_nonce, &A_GEN).unwrap();
+            let scalar = ch_scal_gen_ct(shared_nonce);
             let mut transcript = Vec::new();
             transcript.extend_from_slice(&scalar.x.0);
             transcript.extend_from_slice(&scalar.y.0);
@@ -287,15 +361,15 @@ impl PrivateKey {
             trunc_g_order(&hash_varlen(&mut transcript))
         };
         let nonce = self.nonce_for(m);
-        let sig = (&nonce + &chal * &self.0) % &*G_ORDER;
-        Signature { c: chal, s: sig }
+        let sig = nonce + Scalar::from_ubig(&chal) * self.0;
+        Signature {
+            c: chal,
+            s: sig.to_ubig(),
+        }
     }
 
     pub fn to_be_bytes(&self) -> [u8; 32] {
-        let bytes = self.0.to_be_bytes();
-        let mut arr = [0u8; 32];
-        arr[32 - bytes.len()..].copy_from_slice(&bytes);
-        arr
+        self.0.to_be_bytes()
     }
 }
 
@@ -303,7 +377,7 @@ impl core::ops::Add for &PrivateKey {
     type Output = PrivateKey;
 
     fn add(self, other: &PrivateKey) -> PrivateKey {
-        PrivateKey((&self.0 + &other.0) % &*G_ORDER)
+        PrivateKey(self.0 + other.0)
     }
 }
 
@@ -311,7 +385,7 @@ impl core::ops::Add for PrivateKey {
     type Output = PrivateKey;
 
     fn add(self, other: PrivateKey) -> PrivateKey {
-        PrivateKey((&self.0 + &other.0) % &*G_ORDER)
+        PrivateKey(self.0 + other.0)
     }
 }
 
@@ -325,7 +399,7 @@ impl core::ops::Sub for &PrivateKey {
     type Output = PrivateKey;
 
     fn sub(self, other: &PrivateKey) -> PrivateKey {
-        PrivateKey((&self.0 - &other.0) % &*G_ORDER)
+        PrivateKey(self.0 - other.0)
     }
 }
 
@@ -337,13 +411,13 @@ impl core::ops::SubAssign for PrivateKey {
 
 impl core::iter::Sum<PrivateKey> for PrivateKey {
     fn sum<I: Iterator<Item = PrivateKey>>(iter: I) -> Self {
-        iter.fold(PrivateKey(UBig::from(0u64)), |acc, x| &acc + &x)
+        iter.fold(PrivateKey(Scalar::ZERO), |acc, x| &acc + &x)
     }
 }
 
 impl<'a> core::iter::Sum<&'a PrivateKey> for PrivateKey {
     fn sum<I: Iterator<Item = &'a PrivateKey>>(iter: I) -> Self {
-        iter.fold(PrivateKey(UBig::from(0u64)), |acc, x| &acc + x)
+        iter.fold(PrivateKey(Scalar::ZERO), |acc, x| &acc + x)
     }
 }
 
@@ -353,12 +427,7 @@ mod tests {
 
     #[test]
     fn mupk_test() {
-        let privs = [
-            UBig::from(123u64),
-            UBig::from(124u64),
-            &*G_ORDER - &UBig::from(1u64),
-        ]
-        .map(PrivateKey);
+        let privs = [Scalar::from_u64(123), Scalar::from_u64(124), -Scalar::ONE].map(PrivateKey);
         let pubs = privs.clone().map(|p| p.public_key());
         let pub_key: PublicKey = pubs.iter().sum();
         let priv_key: PrivateKey = privs.iter().sum();
@@ -368,12 +437,7 @@ mod tests {
 
     #[test]
     fn musig_test() {
-        let privs = [
-            UBig::from(123u64),
-            UBig::from(124u64),
-            &*G_ORDER - &UBig::from(1u64),
-        ]
-        .map(PrivateKey);
+        let privs = [Scalar::from_u64(123), Scalar::from_u64(124), -Scalar::ONE].map(PrivateKey);
         let pubs = privs.clone().map(|p| p.public_key());
         let pub_key: PublicKey = pubs.iter().sum();
         let priv_key: PrivateKey = privs.iter().sum();
@@ -401,7 +465,7 @@ mod tests {
 
     #[test]
     fn test_sign_and_verify() {
-        let priv_key = PrivateKey(UBig::from(123u64));
+        let priv_key = PrivateKey(Scalar::from_u64(123));
         let digest = Digest([Belt(1), Belt(2), Belt(3), Belt(4), Belt(5)]);
         let signature = priv_key.sign(&digest);
         let pubkey = priv_key.public_key();
@@ -431,6 +495,97 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_scalar_multiplication() {
+        use iris_ztd::crypto::cheetah::{ch_scal, ch_scal_gen, ch_scal_wnaf};
+
+        let p = PrivateKey(Scalar::from_u64(987654321)).public_key().0;
+        for n in [0u64, 1, 2, 15, 16, 17, 31, 255, 256, 0xdead_beef, u64::MAX] {
+            let n_big = UBig::from(n);
+            assert_eq!(ch_scal_gen(&n_big).unwrap(), ch_scal(n, &A_GEN).unwrap());
+            assert_eq!(ch_scal_wnaf(&n_big, &p).unwrap(), ch_scal(n, &p).unwrap());
+        }
+
+        let a = &*G_ORDER - UBig::from(12345u64);
+        let b = UBig::from_str_radix(
+            "3f1e2d3c4b5a69788796a5b4c3d2e1f00112233445566778899aabbccddeeff",
+            16,
+        )
+        .unwrap();
+        assert_eq!(ch_scal_gen(&a).unwrap(), ch_scal_wnaf(&a, &A_GEN).unwrap());
+        assert_eq!(ch_scal_gen(&G_ORDER).unwrap(), CheetahPoint::identity());
+        assert_eq!(
+            ch_double_scal(&a, &A_GEN, &b, &p).unwrap(),
+            ch_add(&ch_scal_gen(&a).unwrap(), &ch_scal_wnaf(&b, &p).unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_constant_time_arithmetic() {
+        use iris_ztd::crypto::cheetah::{ch_scal_big, ch_scal_ct};
+
+        let n = &*G_ORDER;
+        let values = [
+            UBig::from(0u64),
+            UBig::from(1u64),
+            UBig::from(0xdead_beefu64),
+            n - UBig::from(1u64),
+            n >> 1,
+            (UBig::from(1u64) << 256) - UBig::from(1u64),
+            UBig::from_str_radix(
+                "3f1e2d3c4b5a69788796a5b4c3d2e1f00112233445566778899aabbccddeeff",
+                16,
+            )
+            .unwrap(),
+        ];
+        for a in &values {
+            let sa = Scalar::from_ubig(a);
+            assert_eq!(sa.to_ubig(), a % n);
+            assert_eq!((-sa).to_ubig(), (n - a % n) % n);
+            for b in &values {
+                let sb = Scalar::from_ubig(b);
+                assert_eq!((sa + sb).to_ubig(), (a + b) % n);
+                assert_eq!((sa - sb).to_ubig(), (a % n + n - b % n) % n);
+                assert_eq!((sa * sb).to_ubig(), (a * b) % n);
+            }
+        }
+        let words = [PRIME - 1, 12345, PRIME - 2, 0xffff_ffff, 7];
+        assert_eq!(trunc_g_order_ct(&words).to_ubig(), trunc_g_order(&words));
+
+        let n_bytes = Scalar::from_ubig(&(n - UBig::from(1u64))).to_be_bytes();
+        assert!(Scalar::from_be_bytes(&n_bytes).is_some());
+        assert!(Scalar::from_be_bytes(&[0xff; 32]).is_none());
+
+        let p = PrivateKey(Scalar::from_u64(987654321)).public_key().0;
+        for k in &values {
+            let sk = Scalar::from_ubig(k);
+            assert_eq!(ch_scal_gen_ct(&sk), ch_scal_big(k, &A_GEN).unwrap());
+            assert_eq!(ch_scal_ct(&sk, &p), ch_scal_big(&(k % n), &p).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let mut items = (1..=6u64)
+            .map(|i| {
+                let key = PrivateKey(Scalar::from_u64(1000 + i));
+                let digest = Digest([Belt(i), Belt(2), Belt(3), Belt(4), Belt(5)]);
+                let sig = key.sign(&digest);
+                (key.public_key(), digest, sig)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(verify_batch(&items), Ok(()));
+        assert_eq!(verify_batch(&[]), Ok(()));
+
+        items[1].1 .0[0] = Belt(0);
+        items[4].2.s += UBig::from(1u64);
+        items[5].2.c = UBig::from(0u64);
+        assert_eq!(verify_batch(&items), Err(vec![1, 4, 5]));
+        for (i, (key, digest, sig)) in items.iter().enumerate() {
+            assert_eq!(key.verify(digest, sig), ![1, 4, 5].contains(&i));
+        }
+    }
+
     #[test]
     fn test_vector() {
         // from nockchain zkvm-jetpack cheetah_jets.rs test_batch_verify_affine
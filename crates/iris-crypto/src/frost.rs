@@ -0,0 +1,902 @@
+//! FROST threshold Schnorr signatures over the Cheetah curve.
+//!
+//! Any `min_signers` of the `max_signers` participants can produce a [`Signature`] under the group
+//! key that verifies with [`PublicKey::verify`], exactly like a single-key signature. A note locked
+//! to the hash of the group key can therefore be spent with one signature, while control of it is
+//! distributed.
+//!
+//! Key shares come from [`trusted_dealer_keygen`], or from the distributed key generation in
+//! [`dkg`], where no party ever learns the group secret. Signing takes two rounds: each signer
+//! [`commit`]s to a pair of nonces, a coordinator collects the commitments into a
+//! [`SigningPackage`], each signer [`sign`]s it, and the coordinator [`aggregate`]s the shares.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use ibig::UBig;
+use iris_ztd::crypto::cheetah::{ch_scal_gen_ct, CheetahPoint, A_GEN, G_ORDER};
+use iris_ztd::crypto::scalar::Scalar;
+use iris_ztd::Digest;
+
+use crate::cheetah::{PrivateKey, PublicKey, Signature};
+use crate::schnorr::{
+    add, invert, mul, mul_gen_secret, random_scalar, signature_challenge, Transcript,
+};
+
+/// A participant's nonzero index, at which its share of the secret polynomial is evaluated.
+pub type Identifier = u16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrostError {
+    /// `min_signers` must be at least 2 and at most `max_signers`.
+    InvalidThreshold,
+    InvalidIdentifier(Identifier),
+    /// A DKG package from this participant is missing.
+    MissingPackage(Identifier),
+    InvalidProofOfKnowledge(Identifier),
+    /// The secret share does not match the sender's polynomial commitment.
+    InvalidSecretShare(Identifier),
+    NotEnoughSigners,
+    /// The signing package has no commitment from this signer, or a different one.
+    InvalidCommitment(Identifier),
+    MissingSignatureShare(Identifier),
+    InvalidSignatureShare(Identifier),
+    /// A point is the identity, which honest participants only produce with negligible
+    /// probability.
+    Degenerate,
+    InvalidEncoding,
+    Random(String),
+}
+
+impl fmt::Display for FrostError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrostError::InvalidThreshold => write!(f, "invalid threshold"),
+            FrostError::InvalidIdentifier(id) => write!(f, "invalid identifier {}", id),
+            FrostError::MissingPackage(id) => write!(f, "missing package from {}", id),
+            FrostError::InvalidProofOfKnowledge(id) => {
+                write!(f, "invalid proof of knowledge from {}", id)
+            }
+            FrostError::InvalidSecretShare(id) => write!(f, "invalid secret share from {}", id),
+            FrostError::NotEnoughSigners => write!(f, "not enough signers"),
+            FrostError::InvalidCommitment(id) => {
+                write!(f, "missing or mismatched commitment for {}", id)
+            }
+            FrostError::MissingSignatureShare(id) => {
+                write!(f, "missing signature share from {}", id)
+            }
+            FrostError::InvalidSignatureShare(id) => {
+                write!(f, "invalid signature share from {}", id)
+            }
+            FrostError::Degenerate => write!(f, "point is the identity"),
+            FrostError::InvalidEncoding => write!(f, "invalid encoding"),
+            FrostError::Random(e) => write!(f, "failed to generate randomness: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FrostError {}
+
+fn check_threshold(min_signers: u16, max_signers: u16) -> Result<(), FrostError> {
+    if min_signers < 2 || min_signers > max_signers {
+        return Err(FrostError::InvalidThreshold);
+    }
+    Ok(())
+}
+
+fn random(transcript: Transcript) -> Result<Scalar, FrostError> {
+    random_scalar(transcript).map_err(|e| FrostError::Random(e.to_string()))
+}
+
+/// A random polynomial of degree `min_signers - 1`, whose constant term is the secret.
+struct Polynomial(Vec<Scalar>);
+
+impl Polynomial {
+    fn random(
+        secret: Option<&Scalar>,
+        min_signers: u16,
+        seed: &Transcript,
+    ) -> Result<Self, FrostError> {
+        let mut coefficients = Vec::with_capacity(min_signers as usize);
+        for i in 0..min_signers {
+            match (i, secret) {
+                (0, Some(secret)) => coefficients.push(*secret),
+                _ => coefficients.push(random(seed.clone().words(&[i as u64]))?),
+            }
+        }
+        Ok(Self(coefficients))
+    }
+
+    fn evaluate(&self, x: Identifier) -> Scalar {
+        let x = Scalar::from_u64(x as u64);
+        self.0
+            .iter()
+            .rev()
+            .fold(Scalar::ZERO, |acc, a| acc * x + *a)
+    }
+
+    fn commitment(&self) -> Vec<PublicKey> {
+        self.0
+            .iter()
+            .map(|a| PublicKey(mul_gen_secret(a)))
+            .collect()
+    }
+}
+
+/// Evaluate a polynomial commitment at `x`, giving the public key of the share at `x`.
+fn evaluate_commitment(commitment: &[PublicKey], x: Identifier) -> CheetahPoint {
+    let x = UBig::from(x);
+    commitment
+        .iter()
+        .rev()
+        .fold(CheetahPoint::identity(), |acc, c| add(&mul(&x, &acc), &c.0))
+}
+
+/// A participant's long-lived signing key.
+#[derive(Debug, Clone)]
+pub struct KeyPackage {
+    pub identifier: Identifier,
+    pub signing_share: PrivateKey,
+    pub verifying_share: PublicKey,
+    pub group_key: PublicKey,
+    pub min_signers: u16,
+}
+
+/// The public keys of the group and of every participant's share, needed to aggregate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicKeyPackage {
+    pub verifying_shares: BTreeMap<Identifier, PublicKey>,
+    pub group_key: PublicKey,
+    pub min_signers: u16,
+}
+
+impl PublicKeyPackage {
+    fn from_commitment(
+        commitment: &[PublicKey],
+        identifiers: impl Iterator<Item = Identifier>,
+    ) -> Self {
+        Self {
+            verifying_shares: identifiers
+                .map(|id| (id, PublicKey(evaluate_commitment(commitment, id))))
+                .collect(),
+            group_key: commitment[0].clone(),
+            min_signers: commitment.len() as u16,
+        }
+    }
+}
+
+/// A share of the group secret sent by a trusted dealer, along with the commitment to the
+/// dealer's polynomial so that the participant can check it.
+#[derive(Debug, Clone)]
+pub struct SecretShare {
+    pub identifier: Identifier,
+    pub signing_share: PrivateKey,
+    pub commitment: Vec<PublicKey>,
+}
+
+impl SecretShare {
+    /// Check the share against the dealer's commitment, and turn it into a key package.
+    pub fn verify(&self) -> Result<KeyPackage, FrostError> {
+        let verifying_share = mul_gen_secret(&self.signing_share.0);
+        if self.commitment.is_empty()
+            || evaluate_commitment(&self.commitment, self.identifier) != verifying_share
+        {
+            return Err(FrostError::InvalidSecretShare(self.identifier));
+        }
+        Ok(KeyPackage {
+            identifier: self.identifier,
+            signing_share: self.signing_share.clone(),
+            verifying_share: PublicKey(verifying_share),
+            group_key: self.commitment[0].clone(),
+            min_signers: self.commitment.len() as u16,
+        })
+    }
+}
+
+/// Split `secret`, or a fresh random key, into shares for participants `1..=max_signers`.
+///
+/// The dealer learns the group secret, so this is for splitting an existing key or for setups
+/// where a single party is trusted at creation time. Otherwise, use [`dkg`].
+pub fn trusted_dealer_keygen(
+    secret: Option<&PrivateKey>,
+    min_signers: u16,
+    max_signers: u16,
+) -> Result<(BTreeMap<Identifier, SecretShare>, PublicKeyPackage), FrostError> {
+    check_threshold(min_signers, max_signers)?;
+    let seed = Transcript::new(b"iris/frost/dealer");
+    let seed = match secret {
+        Some(secret) => seed.secret(&secret.0),
+        None => seed,
+    };
+    let polynomial = Polynomial::random(secret.map(|s| &s.0), min_signers, &seed)?;
+    let commitment = polynomial.commitment();
+    let shares = (1..=max_signers)
+        .map(|id| {
+            let share = SecretShare {
+                identifier: id,
+                signing_share: PrivateKey(polynomial.evaluate(id)),
+                commitment: commitment.clone(),
+            };
+            (id, share)
+        })
+        .collect();
+    Ok((
+        shares,
+        PublicKeyPackage::from_commitment(&commitment, 1..=max_signers),
+    ))
+}
+
+/// Distributed key generation (Pedersen DKG with proofs of knowledge, as in the FROST paper).
+///
+/// Every participant calls [`part1`] and broadcasts its [`Round1Package`]. With everyone else's
+/// round-one packages, [`part2`] gives a [`Round2Package`] for each other participant, to be sent
+/// to them privately. With everyone else's round-two packages addressed to it, [`part3`] gives the
+/// participant its [`KeyPackage`] and the group's [`PublicKeyPackage`].
+pub mod dkg {
+    use super::*;
+
+    /// State kept by a participant between [`part1`] and [`part2`].
+    pub struct Round1Secret {
+        identifier: Identifier,
+        min_signers: u16,
+        max_signers: u16,
+        polynomial: Polynomial,
+        commitment: Vec<PublicKey>,
+    }
+
+    /// Broadcast to every other participant.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Round1Package {
+        pub commitment: Vec<PublicKey>,
+        /// Schnorr proof of knowledge of the polynomial's constant term.
+        pub proof_r: PublicKey,
+        pub proof_z: UBig,
+    }
+
+    impl Round1Package {
+        /// The commitment's points, then `proof_r`, then `proof_z` as 32 big-endian bytes.
+        ///
+        /// `proof_z` is encoded modulo [`G_ORDER`], which [`part1`] already ensures.
+        pub fn to_bytes(&self) -> Vec<u8> {
+            let mut bytes = Vec::with_capacity(97 * (self.commitment.len() + 1) + 32);
+            for point in self.commitment.iter().chain([&self.proof_r]) {
+                bytes.extend_from_slice(&point.to_be_bytes());
+            }
+            bytes.extend_from_slice(&Scalar::from_ubig(&self.proof_z).to_be_bytes());
+            bytes
+        }
+
+        /// Parse a package encoded with [`Round1Package::to_bytes`], checking that every point
+        /// is on the curve and that `proof_z` is below [`G_ORDER`].
+        pub fn from_bytes(bytes: &[u8]) -> Result<Self, FrostError> {
+            if bytes.len() < 2 * 97 + 32 || (bytes.len() - 32) % 97 != 0 {
+                return Err(FrostError::InvalidEncoding);
+            }
+            let (points, z) = bytes.split_at(bytes.len() - 32);
+            let mut points = points
+                .chunks_exact(97)
+                .map(|p| PublicKey::try_from_be_bytes(p).ok_or(FrostError::InvalidEncoding))
+                .collect::<Result<Vec<_>, _>>()?;
+            let proof_r = points.pop().unwrap();
+            let proof_z = Scalar::from_be_bytes(z.try_into().unwrap())
+                .ok_or(FrostError::InvalidEncoding)?
+                .to_ubig();
+            Ok(Self {
+                commitment: points,
+                proof_r,
+                proof_z,
+            })
+        }
+    }
+
+    /// State kept by a participant between [`part2`] and [`part3`].
+    pub struct Round2Secret {
+        identifier: Identifier,
+        min_signers: u16,
+        commitment: Vec<PublicKey>,
+        own_share: Scalar,
+        /// Everyone else's commitments, as checked by [`part2`].
+        commitments: BTreeMap<Identifier, Vec<PublicKey>>,
+    }
+
+    /// Sent privately to a single participant.
+    #[derive(Debug, Clone)]
+    pub struct Round2Package {
+        pub signing_share: Scalar,
+    }
+
+    impl Round2Package {
+        pub fn to_bytes(&self) -> [u8; 32] {
+            self.signing_share.to_be_bytes()
+        }
+
+        /// Parse a 32-byte big-endian share, which must be below [`G_ORDER`].
+        pub fn from_bytes(bytes: &[u8]) -> Result<Self, FrostError> {
+            let bytes = bytes.try_into().map_err(|_| FrostError::InvalidEncoding)?;
+            Ok(Self {
+                signing_share: Scalar::from_be_bytes(bytes).ok_or(FrostError::InvalidEncoding)?,
+            })
+        }
+    }
+
+    fn proof_challenge(identifier: Identifier, commitment: &PublicKey, r: &PublicKey) -> UBig {
+        Transcript::new(b"iris/frost/dkg-pok")
+            .words(&[identifier as u64])
+            .point(&commitment.0)
+            .point(&r.0)
+            .scalar_hash()
+    }
+
+    pub fn part1(
+        identifier: Identifier,
+        min_signers: u16,
+        max_signers: u16,
+    ) -> Result<(Round1Secret, Round1Package), FrostError> {
+        check_threshold(min_signers, max_signers)?;
+        if identifier == 0 {
+            return Err(FrostError::InvalidIdentifier(identifier));
+        }
+        let seed = Transcript::new(b"iris/frost/dkg").words(&[identifier as u64]);
+        let polynomial = Polynomial::random(None, min_signers, &seed)?;
+        let commitment = polynomial.commitment();
+
+        // Indices below `min_signers` seed the polynomial coefficients.
+        let k = random(seed.words(&[min_signers as u64]))?;
+        let proof_r = PublicKey(mul_gen_secret(&k));
+        let c = proof_challenge(identifier, &commitment[0], &proof_r);
+        let proof_z = (k + Scalar::from_ubig(&c) * polynomial.0[0]).to_ubig();
+
+        let package = Round1Package {
+            commitment: commitment.clone(),
+            proof_r,
+            proof_z,
+        };
+        let secret = Round1Secret {
+            identifier,
+            min_signers,
+            max_signers,
+            polynomial,
+            commitment,
+        };
+        Ok((secret, package))
+    }
+
+    fn check_senders<T>(
+        identifier: Identifier,
+        expected: impl Iterator<Item = Identifier>,
+        packages: &BTreeMap<Identifier, T>,
+    ) -> Result<(), FrostError> {
+        let expected = expected.collect::<BTreeSet<_>>();
+        if let Some(&id) = packages
+            .keys()
+            .find(|id| **id == 0 || **id == identifier || !expected.contains(id))
+        {
+            return Err(FrostError::InvalidIdentifier(id));
+        }
+        if let Some(&id) = expected.iter().find(|id| !packages.contains_key(id)) {
+            return Err(FrostError::MissingPackage(id));
+        }
+        Ok(())
+    }
+
+    /// Check everyone else's round-one packages, and compute their secret shares.
+    pub fn part2(
+        secret: Round1Secret,
+        round1_packages: &BTreeMap<Identifier, Round1Package>,
+    ) -> Result<(Round2Secret, BTreeMap<Identifier, Round2Package>), FrostError> {
+        if round1_packages.len() + 1 != secret.max_signers as usize {
+            return Err(FrostError::NotEnoughSigners);
+        }
+        for (&id, package) in round1_packages {
+            if id == 0 || id == secret.identifier {
+                return Err(FrostError::InvalidIdentifier(id));
+            }
+            if package.commitment.len() != secret.min_signers as usize {
+                return Err(FrostError::InvalidProofOfKnowledge(id));
+            }
+            // z * G == R + c * C_0
+            let c = proof_challenge(id, &package.commitment[0], &package.proof_r);
+            let lhs = mul(&package.proof_z, &A_GEN);
+            let rhs = add(&package.proof_r.0, &mul(&c, &package.commitment[0].0));
+            if package.proof_z >= *G_ORDER || lhs != rhs {
+                return Err(FrostError::InvalidProofOfKnowledge(id));
+            }
+        }
+
+        let shares = round1_packages
+            .keys()
+            .map(|&id| {
+                let share = Round2Package {
+                    signing_share: secret.polynomial.evaluate(id),
+                };
+                (id, share)
+            })
+            .collect();
+        let round2_secret = Round2Secret {
+            identifier: secret.identifier,
+            min_signers: secret.min_signers,
+            own_share: secret.polynomial.evaluate(secret.identifier),
+            commitment: secret.commitment,
+            commitments: round1_packages
+                .iter()
+                .map(|(&id, package)| (id, package.commitment.clone()))
+                .collect(),
+        };
+        Ok((round2_secret, shares))
+    }
+
+    /// Check the secret shares sent to this participant, and compute its key package.
+    ///
+    /// Shares are checked against the round-one commitments that [`part2`] verified.
+    pub fn part3(
+        secret: &Round2Secret,
+        round2_packages: &BTreeMap<Identifier, Round2Package>,
+    ) -> Result<(KeyPackage, PublicKeyPackage), FrostError> {
+        check_senders(
+            secret.identifier,
+            secret.commitments.keys().copied(),
+            round2_packages,
+        )?;
+
+        let mut signing_share = secret.own_share;
+        let mut group_commitment = secret.commitment.iter().map(|c| c.0).collect::<Vec<_>>();
+        for (&id, package) in round2_packages {
+            let commitment = &secret.commitments[&id];
+            if evaluate_commitment(commitment, secret.identifier)
+                != mul_gen_secret(&package.signing_share)
+            {
+                return Err(FrostError::InvalidSecretShare(id));
+            }
+            signing_share = signing_share + package.signing_share;
+            for (acc, c) in group_commitment.iter_mut().zip(commitment) {
+                *acc = add(acc, &c.0);
+            }
+        }
+
+        let group_commitment = group_commitment
+            .into_iter()
+            .map(PublicKey)
+            .collect::<Vec<_>>();
+        let identifiers = secret
+            .commitments
+            .keys()
+            .copied()
+            .chain([secret.identifier]);
+        let public_key_package = PublicKeyPackage::from_commitment(&group_commitment, identifiers);
+        if public_key_package.group_key.0.inf {
+            return Err(FrostError::Degenerate);
+        }
+        let key_package = KeyPackage {
+            identifier: secret.identifier,
+            verifying_share: PublicKey(ch_scal_gen_ct(&signing_share)),
+            signing_share: PrivateKey(signing_share),
+            group_key: public_key_package.group_key.clone(),
+            min_signers: secret.min_signers,
+        };
+        Ok((key_package, public_key_package))
+    }
+}
+
+/// A signer's round-one message: commitments to its two nonces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigningCommitments {
+    pub hiding: PublicKey,
+    pub binding: PublicKey,
+}
+
+impl SigningCommitments {
+    pub fn to_bytes(&self) -> [u8; 194] {
+        let mut bytes = [0u8; 194];
+        bytes[..97].copy_from_slice(&self.hiding.to_be_bytes());
+        bytes[97..].copy_from_slice(&self.binding.to_be_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FrostError> {
+        if bytes.len() != 194 {
+            return Err(FrostError::InvalidEncoding);
+        }
+        Ok(Self {
+            hiding: PublicKey::try_from_be_bytes(&bytes[..97])
+                .ok_or(FrostError::InvalidEncoding)?,
+            binding: PublicKey::try_from_be_bytes(&bytes[97..])
+                .ok_or(FrostError::InvalidEncoding)?,
+        })
+    }
+}
+
+/// The secret nonces behind a [`SigningCommitments`].
+///
+/// They are consumed by [`sign`] and can't be cloned, so they are never used twice.
+pub struct SigningNonces {
+    hiding: Scalar,
+    binding: Scalar,
+    commitments: SigningCommitments,
+}
+
+impl SigningNonces {
+    pub fn commitments(&self) -> &SigningCommitments {
+        &self.commitments
+    }
+}
+
+/// Round one: generate nonces, and the commitments to send to the coordinator.
+pub fn commit(key_package: &KeyPackage) -> Result<(SigningNonces, SigningCommitments), FrostError> {
+    let seed = Transcript::new(b"iris/frost/nonce").secret(&key_package.signing_share.0);
+    let hiding = random(seed.clone().words(&[1]))?;
+    let binding = random(seed.words(&[2]))?;
+    let commitments = SigningCommitments {
+        hiding: PublicKey(mul_gen_secret(&hiding)),
+        binding: PublicKey(mul_gen_secret(&binding)),
+    };
+    let nonces = SigningNonces {
+        hiding,
+        binding,
+        commitments: commitments.clone(),
+    };
+    Ok((nonces, commitments))
+}
+
+/// The message and the commitments of the participating signers, assembled by the coordinator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigningPackage {
+    pub commitments: BTreeMap<Identifier, SigningCommitments>,
+    pub message: Digest,
+}
+
+impl SigningPackage {
+    pub fn new(commitments: BTreeMap<Identifier, SigningCommitments>, message: Digest) -> Self {
+        Self {
+            commitments,
+            message,
+        }
+    }
+
+    fn group_commitment(&self, group_key: &PublicKey) -> Result<GroupCommitment, FrostError> {
+        if let Some(&id) = self.commitments.keys().find(|id| **id == 0) {
+            return Err(FrostError::InvalidIdentifier(id));
+        }
+        let list_hash = self
+            .commitments
+            .iter()
+            .fold(Transcript::new(b"iris/frost/commitments"), |t, (&id, c)| {
+                t.words(&[id as u64]).point(&c.hiding.0).point(&c.binding.0)
+            })
+            .hash();
+        let binding_factors = self
+            .commitments
+            .keys()
+            .map(|&id| {
+                let rho = Transcript::new(b"iris/frost/binding")
+                    .point(&group_key.0)
+                    .digest(&self.message)
+                    .words(&list_hash)
+                    .words(&[id as u64])
+                    .scalar_hash();
+                (id, rho)
+            })
+            .collect::<BTreeMap<_, _>>();
+        let r = self
+            .commitments
+            .iter()
+            .fold(CheetahPoint::identity(), |acc, (id, c)| {
+                let share = add(&c.hiding.0, &mul(&binding_factors[id], &c.binding.0));
+                add(&acc, &share)
+            });
+        if r.inf {
+            return Err(FrostError::Degenerate);
+        }
+        Ok(GroupCommitment {
+            challenge: signature_challenge(&r, group_key, &self.message),
+            binding_factors,
+        })
+    }
+
+    /// Lagrange coefficient of `identifier` for interpolating at 0 over the signing set.
+    fn lagrange_coefficient(&self, identifier: Identifier) -> UBig {
+        let (num, den) = self.commitments.keys().filter(|&&j| j != identifier).fold(
+            (UBig::from(1u64), UBig::from(1u64)),
+            |(num, den), &j| {
+                let diff = (UBig::from(j) + &*G_ORDER - UBig::from(identifier)) % &*G_ORDER;
+                (num * UBig::from(j) % &*G_ORDER, den * diff % &*G_ORDER)
+            },
+        );
+        num * invert(&den) % &*G_ORDER
+    }
+}
+
+struct GroupCommitment {
+    challenge: UBig,
+    binding_factors: BTreeMap<Identifier, UBig>,
+}
+
+/// A signer's round-two message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureShare(pub UBig);
+
+impl SignatureShare {
+    pub fn to_be_bytes(&self) -> [u8; 32] {
+        Scalar::from_ubig(&self.0).to_be_bytes()
+    }
+
+    pub fn from_be_bytes(bytes: &[u8]) -> Result<Self, FrostError> {
+        let s = UBig::from_be_bytes(bytes);
+        if bytes.len() != 32 || s >= *G_ORDER {
+            return Err(FrostError::InvalidEncoding);
+        }
+        Ok(Self(s))
+    }
+}
+
+/// Round two: sign the package, consuming the nonces committed to in round one.
+pub fn sign(
+    package: &SigningPackage,
+    nonces: SigningNonces,
+    key_package: &KeyPackage,
+) -> Result<SignatureShare, FrostError> {
+    let id = key_package.identifier;
+    if package.commitments.len() < key_package.min_signers as usize {
+        return Err(FrostError::NotEnoughSigners);
+    }
+    if package.commitments.get(&id) != Some(&nonces.commitments) {
+        return Err(FrostError::InvalidCommitment(id));
+    }
+    let GroupCommitment {
+        challenge,
+        binding_factors,
+    } = package.group_commitment(&key_package.group_key)?;
+    let lambda = package.lagrange_coefficient(id);
+    let [rho, lambda, c] = [&binding_factors[&id], &lambda, &challenge].map(Scalar::from_ubig);
+    let (d, e, s) = (nonces.hiding, nonces.binding, key_package.signing_share.0);
+    Ok(SignatureShare((d + e * rho + lambda * s * c).to_ubig()))
+}
+
+/// Check every signature share and combine them into a signature under the group key.
+pub fn aggregate(
+    package: &SigningPackage,
+    shares: &BTreeMap<Identifier, SignatureShare>,
+    public_key_package: &PublicKeyPackage,
+) -> Result<Signature, FrostError> {
+    if package.commitments.len() < public_key_package.min_signers as usize {
+        return Err(FrostError::NotEnoughSigners);
+    }
+    if let Some(&id) = shares
+        .keys()
+        .find(|id| !package.commitments.contains_key(id))
+    {
+        return Err(FrostError::InvalidCommitment(id));
+    }
+    let GroupCommitment {
+        challenge,
+        binding_factors,
+    } = package.group_commitment(&public_key_package.group_key)?;
+
+    let mut s = UBig::from(0u64);
+    for (&id, commitments) in &package.commitments {
+        let share = shares
+            .get(&id)
+            .ok_or(FrostError::MissingSignatureShare(id))?;
+        let verifying_share = public_key_package
+            .verifying_shares
+            .get(&id)
+            .ok_or(FrostError::InvalidIdentifier(id))?;
+        // z_i * G == D_i + rho_i * E_i + (c * lambda_i) * Y_i
+        let e = (&challenge * package.lagrange_coefficient(id)) % &*G_ORDER;
+        let expected = add(
+            &add(
+                &commitments.hiding.0,
+                &mul(&binding_factors[&id], &commitments.binding.0),
+            ),
+            &mul(&e, &verifying_share.0),
+        );
+        if share.0 >= *G_ORDER || mul(&share.0, &A_GEN) != expected {
+            return Err(FrostError::InvalidSignatureShare(id));
+        }
+        s = (s + &share.0) % &*G_ORDER;
+    }
+
+    Ok(Signature { c: challenge, s })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iris_ztd::Belt;
+
+    fn message() -> Digest {
+        Digest([Belt(1), Belt(2), Belt(3), Belt(4), Belt(5)])
+    }
+
+    fn sign_with(
+        key_packages: &[&KeyPackage],
+        public_key_package: &PublicKeyPackage,
+    ) -> Result<Signature, FrostError> {
+        let (nonces, commitments): (Vec<_>, BTreeMap<_, _>) = key_packages
+            .iter()
+            .map(|kp| {
+                let (nonces, commitments) = commit(kp).unwrap();
+                (nonces, (kp.identifier, commitments))
+            })
+            .unzip();
+        let package = SigningPackage::new(commitments, message());
+        let shares = nonces
+            .into_iter()
+            .zip(key_packages)
+            .map(|(nonces, kp)| Ok((kp.identifier, sign(&package, nonces, kp)?)))
+            .collect::<Result<BTreeMap<_, _>, FrostError>>()?;
+        aggregate(&package, &shares, public_key_package)
+    }
+
+    #[test]
+    fn test_frost_trusted_dealer() {
+        let secret = PrivateKey(Scalar::from_u64(123456789));
+        let (shares, public_key_package) = trusted_dealer_keygen(Some(&secret), 2, 3).unwrap();
+        assert_eq!(public_key_package.group_key, secret.public_key());
+
+        let key_packages = shares
+            .values()
+            .map(|share| share.verify().unwrap())
+            .collect::<Vec<_>>();
+        let mut bad_share = shares[&2].clone();
+        bad_share.signing_share = PrivateKey(Scalar::ONE);
+        assert_eq!(
+            bad_share.verify().unwrap_err(),
+            FrostError::InvalidSecretShare(2)
+        );
+
+        for signers in [
+            vec![&key_packages[0], &key_packages[2]],
+            vec![&key_packages[1], &key_packages[2]],
+            key_packages.iter().collect(),
+        ] {
+            let sig = sign_with(&signers, &public_key_package).unwrap();
+            assert!(secret.public_key().verify(&message(), &sig));
+        }
+        assert_eq!(
+            sign_with(&[&key_packages[0]], &public_key_package).unwrap_err(),
+            FrostError::NotEnoughSigners
+        );
+        // The coordinator enforces the threshold too, not just the signers.
+        let (_, commitments) = commit(&key_packages[0]).unwrap();
+        let package = SigningPackage::new([(1, commitments)].into(), message());
+        assert_eq!(
+            aggregate(&package, &BTreeMap::new(), &public_key_package).unwrap_err(),
+            FrostError::NotEnoughSigners
+        );
+
+        // A wrong signature share is caught and attributed.
+        let (n1, c1) = commit(&key_packages[0]).unwrap();
+        let (n2, c2) = commit(&key_packages[1]).unwrap();
+        let package = SigningPackage::new([(1, c1), (2, c2)].into(), message());
+        let share1 = sign(&package, n1, &key_packages[0]).unwrap();
+        let share2 = sign(&package, n2, &key_packages[1]).unwrap();
+        let forged = SignatureShare((share2.0.clone() + 1u64) % &*G_ORDER);
+        assert_eq!(
+            aggregate(
+                &package,
+                &[(1, share1.clone()), (2, forged)].into(),
+                &public_key_package
+            )
+            .unwrap_err(),
+            FrostError::InvalidSignatureShare(2)
+        );
+        assert_eq!(
+            aggregate(&package, &[(1, share1.clone())].into(), &public_key_package).unwrap_err(),
+            FrostError::MissingSignatureShare(2)
+        );
+        let bytes = share2.to_be_bytes();
+        let share2 = SignatureShare::from_be_bytes(&bytes).unwrap();
+        let sig = aggregate(
+            &package,
+            &[(1, share1), (2, share2)].into(),
+            &public_key_package,
+        )
+        .unwrap();
+        assert!(secret.public_key().verify(&message(), &sig));
+        assert_eq!(
+            SigningCommitments::from_bytes(&package.commitments[&1].to_bytes()).unwrap(),
+            package.commitments[&1]
+        );
+
+        assert_eq!(
+            trusted_dealer_keygen(None, 1, 3).unwrap_err(),
+            FrostError::InvalidThreshold
+        );
+    }
+
+    #[test]
+    fn test_frost_dkg() {
+        let ids = [1u16, 2, 3];
+        let round1 = ids
+            .iter()
+            .map(|&id| (id, dkg::part1(id, 2, 3).unwrap()))
+            .collect::<Vec<_>>();
+        // Packages go over the wire as bytes.
+        let packages1 = round1
+            .iter()
+            .map(|(id, (_, package))| {
+                let decoded = dkg::Round1Package::from_bytes(&package.to_bytes()).unwrap();
+                assert_eq!(decoded, *package);
+                (*id, decoded)
+            })
+            .collect::<BTreeMap<_, _>>();
+        let bytes = packages1[&1].to_bytes();
+        assert_eq!(bytes.len(), 3 * 97 + 32);
+        for bad in [&bytes[..bytes.len() - 1], &bytes[..97 + 32]] {
+            assert_eq!(
+                dkg::Round1Package::from_bytes(bad),
+                Err(FrostError::InvalidEncoding)
+            );
+        }
+        let mut bad = bytes.clone();
+        bad[3 * 97..].fill(0xff);
+        assert_eq!(
+            dkg::Round1Package::from_bytes(&bad),
+            Err(FrostError::InvalidEncoding)
+        );
+        let mut bad = bytes;
+        bad[97 + 50] ^= 1;
+        assert_eq!(
+            dkg::Round1Package::from_bytes(&bad),
+            Err(FrostError::InvalidEncoding)
+        );
+        let others = |id: Identifier, packages: &BTreeMap<Identifier, dkg::Round1Package>| {
+            packages
+                .iter()
+                .filter(|(j, _)| **j != id)
+                .map(|(j, p)| (*j, p.clone()))
+                .collect::<BTreeMap<_, _>>()
+        };
+
+        let mut tampered = others(1, &packages1);
+        tampered.get_mut(&2).unwrap().proof_z += 1u64;
+        let (secret, _) = dkg::part1(1, 2, 3).unwrap();
+        assert_eq!(
+            dkg::part2(secret, &tampered).err(),
+            Some(FrostError::InvalidProofOfKnowledge(2))
+        );
+
+        let round2 = round1
+            .into_iter()
+            .map(|(id, (secret, _))| (id, dkg::part2(secret, &others(id, &packages1)).unwrap()))
+            .collect::<Vec<_>>();
+        let received = |id: Identifier| {
+            round2
+                .iter()
+                .filter(|(j, _)| *j != id)
+                .map(|(j, (_, shares))| {
+                    let share = dkg::Round2Package::from_bytes(&shares[&id].to_bytes()).unwrap();
+                    assert_eq!(share.signing_share, shares[&id].signing_share);
+                    (*j, share)
+                })
+                .collect::<BTreeMap<_, _>>()
+        };
+        assert!(dkg::Round2Package::from_bytes(&[0xff; 32]).is_err());
+        assert!(dkg::Round2Package::from_bytes(&[0; 31]).is_err());
+
+        let mut bad = received(3);
+        let share = &mut bad.get_mut(&1).unwrap().signing_share;
+        *share = *share + Scalar::ONE;
+        assert_eq!(
+            dkg::part3(&round2[2].1 .0, &bad).unwrap_err(),
+            FrostError::InvalidSecretShare(1)
+        );
+
+        let results = round2
+            .iter()
+            .map(|(id, (secret, _))| dkg::part3(secret, &received(*id)).unwrap())
+            .collect::<Vec<_>>();
+        let public_key_package = results[0].1.clone();
+        assert!(results.iter().all(|(_, pkp)| *pkp == public_key_package));
+        assert_eq!(public_key_package.min_signers, 2);
+        for (key_package, _) in &results {
+            assert_eq!(
+                public_key_package.verifying_shares[&key_package.identifier],
+                key_package.verifying_share
+            );
+        }
+
+        let sig = sign_with(&[&results[0].0, &results[2].0], &public_key_package).unwrap();
+        assert!(public_key_package.group_key.verify(&message(), &sig));
+    }
+}
@@ -0,0 +1,181 @@
+use std::fmt;
+
+use argon2::{Algorithm, Argon2, Params, Version, MIN_SALT_LEN};
+use bip39::Mnemonic;
+use zeroize::Zeroize;
+
+use crate::argon2d;
+use crate::slip10::{derive_master_key, ExtendedKey};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeygenError {
+    InvalidParams(String),
+    SaltTooShort,
+    Cancelled,
+}
+
+impl fmt::Display for KeygenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeygenError::InvalidParams(e) => write!(f, "invalid Argon2 parameters: {}", e),
+            KeygenError::SaltTooShort => {
+                write!(f, "salt must be at least {} bytes", MIN_SALT_LEN)
+            }
+            KeygenError::Cancelled => write!(f, "key generation was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for KeygenError {}
+
+/// Argon2d cost parameters for turning entropy and salt into a mnemonic.
+///
+/// Different parameters give a different mnemonic for the same entropy and salt, so only
+/// [`KeygenParams::STANDARD`] reproduces the keys of `nockchain-wallet keygen`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeygenParams {
+    /// Memory size in KiB.
+    pub m_cost: u32,
+    /// Number of passes.
+    pub t_cost: u32,
+    /// Number of lanes.
+    pub p_cost: u32,
+}
+
+impl KeygenParams {
+    /// 768 MiB, 6 passes, 4 lanes, as used by `nockchain-wallet keygen`.
+    pub const STANDARD: Self = Self::new(768 * 1024, 6, 4);
+    /// 128 MiB, 4 passes, 1 lane, for browser tabs and mobile devices.
+    pub const BROWSER: Self = Self::new(128 * 1024, 4, 1);
+    /// 64 KiB, 1 pass, 1 lane. Offers no protection; only for tests and CI.
+    pub const INSECURE_TESTING: Self = Self::new(64, 1, 1);
+
+    pub const fn new(m_cost: u32, t_cost: u32, p_cost: u32) -> Self {
+        Self {
+            m_cost,
+            t_cost,
+            p_cost,
+        }
+    }
+
+    fn params(&self) -> Result<Params, KeygenError> {
+        Params::new(self.m_cost, self.t_cost, self.p_cost, Some(32))
+            .map_err(|e| KeygenError::InvalidParams(e.to_string()))
+    }
+}
+
+impl Default for KeygenParams {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+/// How far key generation has got, in Argon2 segments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeygenProgress {
+    pub completed: u32,
+    pub total: u32,
+}
+
+impl KeygenProgress {
+    pub fn fraction(&self) -> f64 {
+        self.completed as f64 / self.total as f64
+    }
+}
+
+/// Generate master key from entropy and salt using Argon2 with `params` + BIP39 + SLIP-10
+pub fn gen_master_key_with_params(
+    entropy: &[u8],
+    salt: &[u8],
+    params: &KeygenParams,
+) -> Result<(String, ExtendedKey), KeygenError> {
+    if salt.len() < MIN_SALT_LEN {
+        return Err(KeygenError::SaltTooShort);
+    }
+    let mut argon_output = [0u8; 32];
+    Argon2::new(Algorithm::Argon2d, Version::V0x13, params.params()?)
+        .hash_password_into(entropy, salt, &mut argon_output)
+        .map_err(|e| KeygenError::InvalidParams(e.to_string()))?;
+    Ok(master_key_from_argon(argon_output))
+}
+
+/// Like [`gen_master_key_with_params`], calling `progress` as the Argon2 hash advances.
+///
+/// `progress` is called `t_cost * 4 * p_cost` times (96 times for [`KeygenParams::STANDARD`]), and
+/// returning `false` cancels key generation. This runs single-threaded and somewhat slower than
+/// [`gen_master_key_with_params`].
+pub fn gen_master_key_with_progress(
+    entropy: &[u8],
+    salt: &[u8],
+    params: &KeygenParams,
+    mut progress: impl FnMut(KeygenProgress) -> bool,
+) -> Result<(String, ExtendedKey), KeygenError> {
+    if salt.len() < MIN_SALT_LEN {
+        return Err(KeygenError::SaltTooShort);
+    }
+    params.params()?;
+    let mut argon_output = [0u8; 32];
+    argon2d::hash(
+        entropy,
+        salt,
+        params.m_cost,
+        params.t_cost,
+        params.p_cost,
+        &mut argon_output,
+        |completed, total| progress(KeygenProgress { completed, total }),
+    )
+    .map_err(|_| KeygenError::Cancelled)?;
+    Ok(master_key_from_argon(argon_output))
+}
+
+fn master_key_from_argon(mut argon_output: [u8; 32]) -> (String, ExtendedKey) {
+    argon_output.reverse();
+    let mnemonic = Mnemonic::from_entropy(&argon_output).unwrap();
+    argon_output.zeroize();
+    (
+        mnemonic.to_string(),
+        derive_master_key(&mnemonic.to_seed("")),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keygen_progress() {
+        let entropy = [7u8; 32];
+        let salt = [9u8; 16];
+        let params = KeygenParams::new(256, 2, 2);
+
+        let (mnemonic, key) = gen_master_key_with_params(&entropy, &salt, &params).unwrap();
+        let mut updates = vec![];
+        let (mnemonic2, key2) = gen_master_key_with_progress(&entropy, &salt, &params, |p| {
+            updates.push(p);
+            true
+        })
+        .unwrap();
+        assert_eq!(mnemonic, mnemonic2);
+        assert_eq!(key.chain_code, key2.chain_code);
+        assert_eq!(updates.len(), 16);
+        assert_eq!(updates.last().unwrap().fraction(), 1.0);
+
+        let (other, _) =
+            gen_master_key_with_params(&entropy, &salt, &KeygenParams::INSECURE_TESTING).unwrap();
+        assert_ne!(mnemonic, other);
+
+        assert_eq!(
+            gen_master_key_with_progress(&entropy, &salt, &params, |p| p.completed < 5)
+                .unwrap_err(),
+            KeygenError::Cancelled
+        );
+        assert_eq!(
+            gen_master_key_with_params(&entropy, &salt[..4], &params).unwrap_err(),
+            KeygenError::SaltTooShort
+        );
+        assert!(matches!(
+            gen_master_key_with_progress(&entropy, &salt, &KeygenParams::new(256, 0, 1), |_| true),
+            Err(KeygenError::InvalidParams(_))
+        ));
+    }
+}
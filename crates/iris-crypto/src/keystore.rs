@@ -0,0 +1,422 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use bip39::Mnemonic;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::slip10::{derive_master_key, ExtendedKey};
+
+/// Current keystore format version.
+///
+/// Version 1 derives a 32-byte key with Argon2id and encrypts the JSON-encoded contents with
+/// XChaCha20-Poly1305. The header fields (version, KDF parameters, salt and nonce) are
+/// authenticated as associated data.
+pub const KEYSTORE_VERSION: u32 = 1;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const AAD_DOMAIN: &[u8] = b"iris-keystore";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeystoreError {
+    UnsupportedVersion(u32),
+    InvalidKdfParams(String),
+    InvalidSecret(String),
+    Malformed(String),
+    /// The password is wrong, or the keystore has been tampered with.
+    Decryption,
+    Random(String),
+}
+
+impl fmt::Display for KeystoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeystoreError::UnsupportedVersion(v) => write!(f, "unsupported keystore version {}", v),
+            KeystoreError::InvalidKdfParams(e) => write!(f, "invalid KDF parameters: {}", e),
+            KeystoreError::InvalidSecret(e) => write!(f, "invalid secret: {}", e),
+            KeystoreError::Malformed(e) => write!(f, "malformed keystore: {}", e),
+            KeystoreError::Decryption => write!(f, "wrong password or corrupted keystore"),
+            KeystoreError::Random(e) => write!(f, "failed to generate randomness: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for KeystoreError {}
+
+/// The secret a wallet is restored from.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeystoreSecret {
+    /// BIP39 entropy, 16 to 32 bytes.
+    Entropy(#[serde(with = "hex")] Vec<u8>),
+    /// BIP39 mnemonic phrase.
+    Mnemonic(String),
+}
+
+impl KeystoreSecret {
+    pub fn mnemonic(&self) -> Result<Mnemonic, KeystoreError> {
+        match self {
+            KeystoreSecret::Entropy(entropy) => Mnemonic::from_entropy(entropy),
+            KeystoreSecret::Mnemonic(phrase) => Mnemonic::parse(phrase),
+        }
+        .map_err(|e| KeystoreError::InvalidSecret(e.to_string()))
+    }
+}
+
+impl fmt::Debug for KeystoreSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeystoreSecret::Entropy(_) => write!(f, "Entropy(..)"),
+            KeystoreSecret::Mnemonic(_) => write!(f, "Mnemonic(..)"),
+        }
+    }
+}
+
+impl Drop for KeystoreSecret {
+    fn drop(&mut self) {
+        match self {
+            KeystoreSecret::Entropy(entropy) => entropy.zeroize(),
+            KeystoreSecret::Mnemonic(phrase) => phrase.zeroize(),
+        }
+    }
+}
+
+/// Everything that is stored encrypted in a keystore.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeystoreContents {
+    pub secret: KeystoreSecret,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
+impl KeystoreContents {
+    pub fn from_entropy(entropy: &[u8]) -> Result<Self, KeystoreError> {
+        Self::new(KeystoreSecret::Entropy(entropy.to_vec()))
+    }
+
+    pub fn from_mnemonic(phrase: &str) -> Result<Self, KeystoreError> {
+        Self::new(KeystoreSecret::Mnemonic(phrase.to_string()))
+    }
+
+    fn new(secret: KeystoreSecret) -> Result<Self, KeystoreError> {
+        secret.mnemonic()?;
+        Ok(Self {
+            secret,
+            labels: BTreeMap::new(),
+        })
+    }
+
+    pub fn with_label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.labels.insert(key.into(), value.into());
+        self
+    }
+
+    /// Derive the SLIP-10 master key, as `gen_master_key` does for a fresh mnemonic.
+    pub fn master_key(&self, passphrase: &str) -> Result<ExtendedKey, KeystoreError> {
+        let seed = Zeroizing::new(self.secret.mnemonic()?.to_seed(passphrase));
+        Ok(derive_master_key(&seed[..]))
+    }
+}
+
+/// Argon2id cost parameters used to derive the encryption key from a password.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// Memory size in KiB.
+    pub m_cost: u32,
+    /// Number of passes.
+    pub t_cost: u32,
+    /// Degree of parallelism.
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    /// 64 MiB, 3 passes, 1 lane: affordable in a browser tab.
+    fn default() -> Self {
+        Self::new(64 * 1024, 3, 1)
+    }
+}
+
+impl KdfParams {
+    /// Largest accepted memory size: 1 GiB.
+    pub const MAX_M_COST: u32 = 1024 * 1024;
+    /// Largest accepted number of passes.
+    pub const MAX_T_COST: u32 = 64;
+    /// Largest accepted degree of parallelism.
+    pub const MAX_P_COST: u32 = 16;
+
+    pub const fn new(m_cost: u32, t_cost: u32, p_cost: u32) -> Self {
+        Self {
+            m_cost,
+            t_cost,
+            p_cost,
+        }
+    }
+
+    /// Reject parameters above the `MAX_*` limits.
+    ///
+    /// The parameters of a keystore come from its (untrusted) JSON, and are only authenticated
+    /// once the key has been derived, so without a cap a crafted file could make decryption
+    /// allocate and hash without bound.
+    pub fn check(&self) -> Result<(), KeystoreError> {
+        let limits = [
+            ("m_cost", self.m_cost, Self::MAX_M_COST),
+            ("t_cost", self.t_cost, Self::MAX_T_COST),
+            ("p_cost", self.p_cost, Self::MAX_P_COST),
+        ];
+        for (name, value, max) in limits {
+            if value > max {
+                return Err(KeystoreError::InvalidKdfParams(format!(
+                    "{} {} exceeds the limit of {}",
+                    name, value, max
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn derive_key(
+        &self,
+        password: &str,
+        salt: &[u8],
+    ) -> Result<Zeroizing<[u8; 32]>, KeystoreError> {
+        self.check()?;
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, Some(32))
+            .map_err(|e| KeystoreError::InvalidKdfParams(e.to_string()))?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(password.as_bytes(), salt, &mut key[..])
+            .map_err(|e| KeystoreError::InvalidKdfParams(e.to_string()))?;
+        Ok(key)
+    }
+}
+
+/// A password-encrypted [`KeystoreContents`], serialized as JSON.
+///
+/// ```json
+/// {"version":1,"kdf":{"m_cost":65536,"t_cost":3,"p_cost":1},"salt":"…","nonce":"…","ciphertext":"…"}
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u32,
+    pub kdf: KdfParams,
+    #[serde(with = "hex")]
+    pub salt: Vec<u8>,
+    #[serde(with = "hex")]
+    pub nonce: Vec<u8>,
+    #[serde(with = "hex")]
+    pub ciphertext: Vec<u8>,
+}
+
+impl Keystore {
+    /// Encrypt `contents` under `password`, with a fresh random salt and nonce.
+    pub fn encrypt(
+        contents: &KeystoreContents,
+        password: &str,
+        kdf: KdfParams,
+    ) -> Result<Self, KeystoreError> {
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        getrandom::getrandom(&mut salt).map_err(|e| KeystoreError::Random(e.to_string()))?;
+        getrandom::getrandom(&mut nonce).map_err(|e| KeystoreError::Random(e.to_string()))?;
+        Self::encrypt_with(contents, password, kdf, &salt, &nonce)
+    }
+
+    fn encrypt_with(
+        contents: &KeystoreContents,
+        password: &str,
+        kdf: KdfParams,
+        salt: &[u8],
+        nonce: &[u8; NONCE_LEN],
+    ) -> Result<Self, KeystoreError> {
+        contents.secret.mnemonic()?;
+        let plaintext = Zeroizing::new(
+            serde_json::to_vec(contents).map_err(|e| KeystoreError::Malformed(e.to_string()))?,
+        );
+
+        let mut keystore = Keystore {
+            version: KEYSTORE_VERSION,
+            kdf,
+            salt: salt.to_vec(),
+            nonce: nonce.to_vec(),
+            ciphertext: vec![],
+        };
+        let key = kdf.derive_key(password, salt)?;
+        keystore.ciphertext = XChaCha20Poly1305::new((&*key).into())
+            .encrypt(
+                &XNonce::from(*nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: &keystore.aad(),
+                },
+            )
+            .map_err(|_| KeystoreError::Decryption)?;
+        Ok(keystore)
+    }
+
+    /// Associated data binding the header to the ciphertext, so that e.g. the KDF parameters
+    /// cannot be weakened without detection.
+    fn aad(&self) -> Vec<u8> {
+        let mut aad = AAD_DOMAIN.to_vec();
+        aad.extend_from_slice(&self.version.to_be_bytes());
+        aad.extend_from_slice(&self.kdf.m_cost.to_be_bytes());
+        aad.extend_from_slice(&self.kdf.t_cost.to_be_bytes());
+        aad.extend_from_slice(&self.kdf.p_cost.to_be_bytes());
+        aad.extend_from_slice(&(self.salt.len() as u32).to_be_bytes());
+        aad.extend_from_slice(&self.salt);
+        aad.extend_from_slice(&self.nonce);
+        aad
+    }
+
+    pub fn decrypt(&self, password: &str) -> Result<KeystoreContents, KeystoreError> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(KeystoreError::UnsupportedVersion(self.version));
+        }
+        let nonce: [u8; NONCE_LEN] =
+            self.nonce.as_slice().try_into().map_err(|_| {
+                KeystoreError::Malformed(format!("nonce must be {} bytes", NONCE_LEN))
+            })?;
+        let key = self.kdf.derive_key(password, &self.salt)?;
+        let plaintext = Zeroizing::new(
+            XChaCha20Poly1305::new((&*key).into())
+                .decrypt(
+                    &XNonce::from(nonce),
+                    Payload {
+                        msg: &self.ciphertext,
+                        aad: &self.aad(),
+                    },
+                )
+                .map_err(|_| KeystoreError::Decryption)?,
+        );
+        serde_json::from_slice(&plaintext).map_err(|e| KeystoreError::Malformed(e.to_string()))
+    }
+
+    /// Re-encrypt the contents under `new_password`, optionally with different KDF parameters.
+    pub fn change_password(
+        &self,
+        old_password: &str,
+        new_password: &str,
+        kdf: Option<KdfParams>,
+    ) -> Result<Self, KeystoreError> {
+        let contents = self.decrypt(old_password)?;
+        Self::encrypt(&contents, new_password, kdf.unwrap_or(self.kdf))
+    }
+
+    /// Decrypt the keystore and return its secret as a mnemonic phrase.
+    pub fn export_mnemonic(&self, password: &str) -> Result<String, KeystoreError> {
+        Ok(self.decrypt(password)?.secret.mnemonic()?.to_string())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("keystore serialization cannot fail")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, KeystoreError> {
+        let keystore: Keystore =
+            serde_json::from_str(json).map_err(|e| KeystoreError::Malformed(e.to_string()))?;
+        if keystore.version != KEYSTORE_VERSION {
+            return Err(KeystoreError::UnsupportedVersion(keystore.version));
+        }
+        keystore.kdf.check()?;
+        Ok(keystore)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MNEMONIC: &str = "dice domain inspire horse time initial monitor nature mass impose tone benefit vibrant dash kiss mosquito rice then color ribbon agent method drop fat";
+    const CHEAP: KdfParams = KdfParams::new(64, 1, 1);
+
+    #[test]
+    fn test_keystore() {
+        let contents = KeystoreContents::from_mnemonic(MNEMONIC)
+            .unwrap()
+            .with_label("name", "main");
+        let keystore = Keystore::encrypt(&contents, "hunter2", CHEAP).unwrap();
+        assert_eq!(keystore.salt.len(), SALT_LEN);
+
+        let json = keystore.to_json();
+        let keystore = Keystore::from_json(&json).unwrap();
+        let decrypted = keystore.decrypt("hunter2").unwrap();
+        assert_eq!(decrypted.labels["name"], "main");
+        assert_eq!(keystore.export_mnemonic("hunter2").unwrap(), MNEMONIC);
+        assert_eq!(
+            decrypted.master_key("").unwrap().chain_code,
+            contents.master_key("").unwrap().chain_code
+        );
+        assert_eq!(
+            keystore.decrypt("hunter3").unwrap_err(),
+            KeystoreError::Decryption
+        );
+
+        // The header is authenticated: weakening the KDF breaks decryption.
+        let mut tampered = keystore.clone();
+        tampered.kdf.t_cost = 2;
+        assert_eq!(
+            tampered.decrypt("hunter2").unwrap_err(),
+            KeystoreError::Decryption
+        );
+
+        let changed = keystore
+            .change_password("hunter2", "correct horse", Some(KdfParams::new(128, 1, 1)))
+            .unwrap();
+        assert_ne!(changed.salt, keystore.salt);
+        assert_eq!(changed.kdf.m_cost, 128);
+        assert_eq!(changed.export_mnemonic("correct horse").unwrap(), MNEMONIC);
+        assert!(keystore
+            .change_password("wrong", "correct horse", None)
+            .is_err());
+
+        // Entropy is exported as the equivalent mnemonic.
+        let entropy = Mnemonic::parse(MNEMONIC).unwrap().to_entropy();
+        let contents = KeystoreContents::from_entropy(&entropy).unwrap();
+        let keystore = Keystore::encrypt(&contents, "", CHEAP).unwrap();
+        assert_eq!(keystore.export_mnemonic("").unwrap(), MNEMONIC);
+
+        assert!(KeystoreContents::from_entropy(&[0u8; 7]).is_err());
+        let future = json.replacen("\"version\":1", "\"version\":2", 1);
+        assert_eq!(
+            Keystore::from_json(&future).unwrap_err(),
+            KeystoreError::UnsupportedVersion(2)
+        );
+
+        // Oversized KDF parameters are refused before any work is done.
+        let greedy = json.replacen("\"m_cost\":64", "\"m_cost\":4294967295", 1);
+        assert!(matches!(
+            Keystore::from_json(&greedy),
+            Err(KeystoreError::InvalidKdfParams(_))
+        ));
+        let mut greedy = keystore.clone();
+        greedy.kdf.p_cost = KdfParams::MAX_P_COST + 1;
+        assert!(matches!(
+            greedy.decrypt(""),
+            Err(KeystoreError::InvalidKdfParams(_))
+        ));
+        assert!(matches!(
+            Keystore::encrypt(
+                &contents,
+                "",
+                KdfParams::new(64, KdfParams::MAX_T_COST + 1, 1)
+            ),
+            Err(KeystoreError::InvalidKdfParams(_))
+        ));
+        assert!(KdfParams::default().check().is_ok());
+    }
+
+    #[test]
+    fn test_keystore_deterministic() {
+        let contents = KeystoreContents::from_mnemonic(MNEMONIC).unwrap();
+        let a = Keystore::encrypt_with(&contents, "pw", CHEAP, &[1; SALT_LEN], &[2; NONCE_LEN])
+            .unwrap();
+        let b = Keystore::encrypt_with(&contents, "pw", CHEAP, &[1; SALT_LEN], &[2; NONCE_LEN])
+            .unwrap();
+        assert_eq!(a, b);
+        let c = Keystore::encrypt(&contents, "pw", CHEAP).unwrap();
+        assert_ne!(a.nonce, c.nonce);
+    }
+}
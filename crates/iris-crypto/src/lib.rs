@@ -1,34 +1,26 @@
+mod argon2d;
 pub mod cheetah;
+pub mod frost;
+pub mod keygen;
+pub mod keystore;
+pub mod musig;
+mod schnorr;
+pub mod signer;
 pub mod slip10;
 
-pub use cheetah::{PrivateKey, PublicKey, Signature};
-pub use slip10::{derive_master_key, ExtendedKey};
-
-use argon2::{Algorithm, Argon2, Params, Version};
-use bip39::Mnemonic;
+pub use cheetah::{verify_batch, PrivateKey, PublicKey, Signature};
+pub use keygen::{
+    gen_master_key_with_params, gen_master_key_with_progress, KeygenError, KeygenParams,
+    KeygenProgress,
+};
+pub use keystore::{KdfParams, Keystore, KeystoreContents, KeystoreError, KeystoreSecret};
+pub use signer::Signer;
+pub use slip10::{derive_master_key, DerivationPath, ExtendedKey};
 
 /// Generate master key from entropy and salt using Argon2 + BIP39 + SLIP-10
 pub fn gen_master_key(entropy: &[u8], salt: &[u8]) -> (String, ExtendedKey) {
-    let mut argon_output = [0u8; 32];
-    let params = Params::new(
-        786_432,  // m_cost: 768 MiB in KiB
-        6,        // t_cost: 6 iterations
-        4,        // p_cost: 4 threads
-        Some(32), // output length
-    )
-    .expect("Invalid Argon2 parameters");
-
-    Argon2::new(Algorithm::Argon2d, Version::V0x13, params)
-        .hash_password_into(entropy, salt, &mut argon_output)
-        .expect("Invalid entropy and/or salt");
-
-    argon_output.reverse();
-
-    let mnemonic = Mnemonic::from_entropy(&argon_output).unwrap();
-    (
-        mnemonic.to_string(),
-        derive_master_key(&mnemonic.to_seed("")),
-    )
+    gen_master_key_with_params(entropy, salt, &KeygenParams::STANDARD)
+        .expect("Invalid entropy and/or salt")
 }
 
 #[cfg(test)]
@@ -0,0 +1,494 @@
+//! MuSig2 multi-signatures over the Cheetah curve.
+//!
+//! Each signer runs a [`SigningSession`]: it publishes a [`PublicNonce`], collects everyone
+//! else's, produces a [`PartialSignature`], and collects everyone else's partial signatures. The
+//! result is an ordinary [`Signature`] that verifies against [`KeyAggContext::aggregate_key`] with
+//! [`PublicKey::verify`].
+//!
+//! Unlike [`PrivateKey::sign_multi`], keys are weighted by aggregation coefficients, so no signer
+//! can choose a key that cancels out the others, and nonces are random and can only be used once.
+
+use core::fmt;
+
+use ibig::UBig;
+use iris_ztd::crypto::cheetah::{CheetahPoint, A_GEN, G_ORDER};
+use iris_ztd::crypto::scalar::Scalar;
+use iris_ztd::Digest;
+
+use crate::cheetah::{PrivateKey, PublicKey, Signature};
+use crate::schnorr::{add, mul, mul_gen_secret, random_scalar, signature_challenge, Transcript};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MuSigError {
+    NoKeys,
+    DuplicateKey(PublicKey),
+    /// The key is not part of the [`KeyAggContext`].
+    UnknownSigner(PublicKey),
+    /// A different nonce was already received from this signer.
+    ConflictingNonce(PublicKey),
+    /// Not every signer has sent a nonce yet.
+    MissingNonces,
+    /// Not every signer has sent a partial signature yet.
+    MissingPartialSignatures,
+    /// This session has already signed.
+    NonceAlreadyUsed,
+    InvalidPartialSignature(PublicKey),
+    /// The aggregate nonce or key is the identity, which honest signers only produce with
+    /// negligible probability.
+    Degenerate,
+    InvalidEncoding,
+    Random(String),
+}
+
+impl fmt::Display for MuSigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MuSigError::NoKeys => write!(f, "no keys to aggregate"),
+            MuSigError::DuplicateKey(_) => write!(f, "duplicate key"),
+            MuSigError::UnknownSigner(_) => write!(f, "key is not part of the session"),
+            MuSigError::ConflictingNonce(_) => write!(f, "conflicting nonce from signer"),
+            MuSigError::MissingNonces => write!(f, "nonces are missing"),
+            MuSigError::MissingPartialSignatures => write!(f, "partial signatures are missing"),
+            MuSigError::NonceAlreadyUsed => write!(f, "session nonce was already used"),
+            MuSigError::InvalidPartialSignature(_) => write!(f, "invalid partial signature"),
+            MuSigError::Degenerate => write!(f, "aggregate point is the identity"),
+            MuSigError::InvalidEncoding => write!(f, "invalid encoding"),
+            MuSigError::Random(e) => write!(f, "failed to generate randomness: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MuSigError {}
+
+/// The signers' public keys, and their aggregate key.
+///
+/// Keys are sorted, so every signer computes the same context regardless of the order they were
+/// given in.
+#[derive(Debug, Clone)]
+pub struct KeyAggContext {
+    keys: Vec<PublicKey>,
+    coefficients: Vec<UBig>,
+    aggregate: PublicKey,
+}
+
+impl KeyAggContext {
+    pub fn new(mut keys: Vec<PublicKey>) -> Result<Self, MuSigError> {
+        if keys.is_empty() {
+            return Err(MuSigError::NoKeys);
+        }
+        keys.sort();
+        if let Some(pair) = keys.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(MuSigError::DuplicateKey(pair[0].clone()));
+        }
+
+        let list_hash = keys
+            .iter()
+            .fold(Transcript::new(b"iris/musig/keylist"), |t, key| {
+                t.point(&key.0)
+            })
+            .hash();
+        let coefficients = keys
+            .iter()
+            .map(|key| {
+                Transcript::new(b"iris/musig/keyagg")
+                    .words(&list_hash)
+                    .point(&key.0)
+                    .scalar_hash()
+            })
+            .collect::<Vec<_>>();
+        let aggregate = keys
+            .iter()
+            .zip(&coefficients)
+            .fold(CheetahPoint::identity(), |acc, (key, a)| {
+                add(&acc, &mul(a, &key.0))
+            });
+        if aggregate.inf {
+            return Err(MuSigError::Degenerate);
+        }
+
+        Ok(Self {
+            keys,
+            coefficients,
+            aggregate: PublicKey(aggregate),
+        })
+    }
+
+    pub fn aggregate_key(&self) -> &PublicKey {
+        &self.aggregate
+    }
+
+    pub fn keys(&self) -> &[PublicKey] {
+        &self.keys
+    }
+
+    pub fn coefficient(&self, key: &PublicKey) -> Option<&UBig> {
+        Some(&self.coefficients[self.index_of(key).ok()?])
+    }
+
+    fn index_of(&self, key: &PublicKey) -> Result<usize, MuSigError> {
+        self.keys
+            .binary_search(key)
+            .map_err(|_| MuSigError::UnknownSigner(key.clone()))
+    }
+}
+
+/// A signer's round-one message: two nonce points.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicNonce {
+    pub r1: PublicKey,
+    pub r2: PublicKey,
+}
+
+impl PublicNonce {
+    pub fn to_bytes(&self) -> [u8; 194] {
+        let mut bytes = [0u8; 194];
+        bytes[..97].copy_from_slice(&self.r1.to_be_bytes());
+        bytes[97..].copy_from_slice(&self.r2.to_be_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MuSigError> {
+        if bytes.len() != 194 {
+            return Err(MuSigError::InvalidEncoding);
+        }
+        Ok(Self {
+            r1: PublicKey::try_from_be_bytes(&bytes[..97]).ok_or(MuSigError::InvalidEncoding)?,
+            r2: PublicKey::try_from_be_bytes(&bytes[97..]).ok_or(MuSigError::InvalidEncoding)?,
+        })
+    }
+}
+
+/// A signer's round-two message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialSignature(pub UBig);
+
+impl PartialSignature {
+    pub fn to_be_bytes(&self) -> [u8; 32] {
+        Scalar::from_ubig(&self.0).to_be_bytes()
+    }
+
+    pub fn from_be_bytes(bytes: &[u8]) -> Result<Self, MuSigError> {
+        let s = UBig::from_be_bytes(bytes);
+        if bytes.len() != 32 || s >= *G_ORDER {
+            return Err(MuSigError::InvalidEncoding);
+        }
+        Ok(Self(s))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionState {
+    /// Waiting for the nonces of other signers.
+    CollectingNonces,
+    /// All nonces are in, and this signer can sign.
+    ReadyToSign,
+    /// This signer has signed, and is waiting for the partial signatures of others.
+    CollectingSignatures,
+    /// All partial signatures are in, and the signature can be finalized.
+    Complete,
+}
+
+/// Values fixed once every nonce is known.
+#[derive(Debug, Clone)]
+struct AggregateNonce {
+    /// Coefficient of the second nonce points.
+    b: UBig,
+    challenge: UBig,
+}
+
+/// One signer's view of a MuSig2 signing session for a single message.
+///
+/// The secret nonce never leaves the session, and is discarded once it has been used to sign,
+/// so a session can sign at most once. Sessions can't be cloned for the same reason.
+pub struct SigningSession {
+    ctx: KeyAggContext,
+    message: Digest,
+    private_key: PrivateKey,
+    signer: usize,
+    secret_nonce: Option<(Scalar, Scalar)>,
+    nonces: Vec<Option<PublicNonce>>,
+    partials: Vec<Option<UBig>>,
+    aggregate_nonce: Option<AggregateNonce>,
+}
+
+impl fmt::Debug for SigningSession {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningSession")
+            .field("message", &self.message)
+            .field("signer", &self.ctx.keys[self.signer])
+            .field("state", &self.state())
+            .finish_non_exhaustive()
+    }
+}
+
+impl SigningSession {
+    /// Start a session to sign `message` with `private_key`, generating a fresh nonce.
+    pub fn new(
+        ctx: KeyAggContext,
+        private_key: &PrivateKey,
+        message: Digest,
+    ) -> Result<Self, MuSigError> {
+        let signer = ctx.index_of(&private_key.public_key())?;
+        let nonce_transcript = |i: u64| {
+            Transcript::new(b"iris/musig/nonce")
+                .secret(&private_key.0)
+                .point(&ctx.aggregate.0)
+                .digest(&message)
+                .words(&[i])
+        };
+        let random =
+            |i| random_scalar(nonce_transcript(i)).map_err(|e| MuSigError::Random(e.to_string()));
+        let k1 = random(1)?;
+        let k2 = random(2)?;
+        let public_nonce = PublicNonce {
+            r1: PublicKey(mul_gen_secret(&k1)),
+            r2: PublicKey(mul_gen_secret(&k2)),
+        };
+
+        let n = ctx.keys.len();
+        let mut session = Self {
+            ctx,
+            message,
+            private_key: private_key.clone(),
+            signer,
+            secret_nonce: Some((k1, k2)),
+            nonces: vec![None; n],
+            partials: vec![None; n],
+            aggregate_nonce: None,
+        };
+        session.add_nonce(&private_key.public_key(), public_nonce)?;
+        Ok(session)
+    }
+
+    pub fn context(&self) -> &KeyAggContext {
+        &self.ctx
+    }
+
+    pub fn message(&self) -> &Digest {
+        &self.message
+    }
+
+    /// This signer's nonce, to be sent to every other signer.
+    pub fn public_nonce(&self) -> &PublicNonce {
+        self.nonces[self.signer].as_ref().unwrap()
+    }
+
+    pub fn state(&self) -> SessionState {
+        if self.aggregate_nonce.is_none() {
+            SessionState::CollectingNonces
+        } else if self.secret_nonce.is_some() {
+            SessionState::ReadyToSign
+        } else if self.partials.iter().any(Option::is_none) {
+            SessionState::CollectingSignatures
+        } else {
+            SessionState::Complete
+        }
+    }
+
+    /// Record the nonce of the signer with public key `key`.
+    ///
+    /// Receiving the same nonce twice is harmless, but a different nonce from the same signer is
+    /// rejected.
+    pub fn add_nonce(&mut self, key: &PublicKey, nonce: PublicNonce) -> Result<(), MuSigError> {
+        let index = self.ctx.index_of(key)?;
+        match &self.nonces[index] {
+            Some(existing) if *existing == nonce => return Ok(()),
+            Some(_) => return Err(MuSigError::ConflictingNonce(key.clone())),
+            None => self.nonces[index] = Some(nonce),
+        }
+
+        if self.nonces.iter().all(Option::is_some) {
+            self.aggregate_nonce = Some(self.aggregate()?);
+        }
+        Ok(())
+    }
+
+    fn aggregate(&self) -> Result<AggregateNonce, MuSigError> {
+        let (r1, r2) = self.nonces.iter().flatten().fold(
+            (CheetahPoint::identity(), CheetahPoint::identity()),
+            |(r1, r2), nonce| (add(&r1, &nonce.r1.0), add(&r2, &nonce.r2.0)),
+        );
+        let b = Transcript::new(b"iris/musig/noncecoef")
+            .point(&r1)
+            .point(&r2)
+            .point(&self.ctx.aggregate.0)
+            .digest(&self.message)
+            .scalar_hash();
+        let r = add(&r1, &mul(&b, &r2));
+        if r.inf {
+            return Err(MuSigError::Degenerate);
+        }
+        let challenge = signature_challenge(&r, &self.ctx.aggregate, &self.message);
+        Ok(AggregateNonce { b, challenge })
+    }
+
+    /// Produce this signer's partial signature, consuming the secret nonce.
+    pub fn sign(&mut self) -> Result<PartialSignature, MuSigError> {
+        let AggregateNonce { b, challenge } = self
+            .aggregate_nonce
+            .clone()
+            .ok_or(MuSigError::MissingNonces)?;
+        let (k1, k2) = self
+            .secret_nonce
+            .take()
+            .ok_or(MuSigError::NonceAlreadyUsed)?;
+        let a = &self.ctx.coefficients[self.signer];
+        let [b, c, a] = [&b, &challenge, a].map(Scalar::from_ubig);
+        let s = (k1 + b * k2 + c * a * self.private_key.0).to_ubig();
+        self.partials[self.signer] = Some(s.clone());
+        Ok(PartialSignature(s))
+    }
+
+    /// Check the partial signature of the signer with public key `key`.
+    pub fn verify_partial(
+        &self,
+        key: &PublicKey,
+        partial: &PartialSignature,
+    ) -> Result<bool, MuSigError> {
+        let index = self.ctx.index_of(key)?;
+        let AggregateNonce { b, challenge } = self
+            .aggregate_nonce
+            .as_ref()
+            .ok_or(MuSigError::MissingNonces)?;
+        let nonce = self.nonces[index].as_ref().unwrap();
+        if partial.0 >= *G_ORDER {
+            return Ok(false);
+        }
+        // s_i * G == R1_i + b * R2_i + (c * a_i) * P_i
+        let lhs = mul(&partial.0, &A_GEN);
+        let e = (challenge * &self.ctx.coefficients[index]) % &*G_ORDER;
+        let rhs = add(&add(&nonce.r1.0, &mul(b, &nonce.r2.0)), &mul(&e, &key.0));
+        Ok(lhs == rhs)
+    }
+
+    /// Record the partial signature of the signer with public key `key`, after checking it.
+    pub fn add_partial_signature(
+        &mut self,
+        key: &PublicKey,
+        partial: PartialSignature,
+    ) -> Result<(), MuSigError> {
+        if !self.verify_partial(key, &partial)? {
+            return Err(MuSigError::InvalidPartialSignature(key.clone()));
+        }
+        let index = self.ctx.index_of(key)?;
+        self.partials[index] = Some(partial.0);
+        Ok(())
+    }
+
+    /// Combine all partial signatures into a signature under the aggregate key.
+    pub fn finalize(&self) -> Result<Signature, MuSigError> {
+        let AggregateNonce { challenge, .. } = self
+            .aggregate_nonce
+            .as_ref()
+            .ok_or(MuSigError::MissingNonces)?;
+        let s = self
+            .partials
+            .iter()
+            .try_fold(UBig::from(0u64), |acc, s| Some(acc + s.as_ref()?))
+            .ok_or(MuSigError::MissingPartialSignatures)?
+            % &*G_ORDER;
+        Ok(Signature {
+            c: challenge.clone(),
+            s,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iris_ztd::Belt;
+
+    fn keys() -> Vec<PrivateKey> {
+        [11u64, 22, 33]
+            .map(|k| PrivateKey(Scalar::from_u64(k)))
+            .to_vec()
+    }
+
+    #[test]
+    fn test_musig() {
+        let privs = keys();
+        let pubs = privs.iter().map(|k| k.public_key()).collect::<Vec<_>>();
+        let m = Digest([Belt(1), Belt(2), Belt(3), Belt(4), Belt(5)]);
+
+        let ctx = KeyAggContext::new(pubs.clone()).unwrap();
+        let reversed = KeyAggContext::new(pubs.iter().rev().cloned().collect()).unwrap();
+        assert_eq!(ctx.aggregate_key(), reversed.aggregate_key());
+        // Keys are weighted, so the aggregate is not the plain sum.
+        assert_ne!(*ctx.aggregate_key(), pubs.iter().sum::<PublicKey>());
+
+        let mut sessions = privs
+            .iter()
+            .map(|k| SigningSession::new(ctx.clone(), k, m).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(sessions[0].state(), SessionState::CollectingNonces);
+        assert_eq!(sessions[0].sign(), Err(MuSigError::MissingNonces));
+
+        // Round one: exchange nonces, serialized.
+        let nonces = sessions
+            .iter()
+            .map(|s| s.public_nonce().to_bytes())
+            .collect::<Vec<_>>();
+        for session in &mut sessions {
+            for (key, nonce) in pubs.iter().zip(&nonces) {
+                session
+                    .add_nonce(key, PublicNonce::from_bytes(nonce).unwrap())
+                    .unwrap();
+            }
+            assert_eq!(session.state(), SessionState::ReadyToSign);
+        }
+        let other = SigningSession::new(ctx.clone(), &privs[1], m).unwrap();
+        assert_eq!(
+            sessions[0].add_nonce(&pubs[1], other.public_nonce().clone()),
+            Err(MuSigError::ConflictingNonce(pubs[1].clone()))
+        );
+
+        // Round two: exchange partial signatures.
+        let partials = sessions
+            .iter_mut()
+            .map(|s| s.sign().unwrap().to_be_bytes())
+            .collect::<Vec<_>>();
+        assert_eq!(sessions[0].sign(), Err(MuSigError::NonceAlreadyUsed));
+        assert_eq!(sessions[0].state(), SessionState::CollectingSignatures);
+        assert!(matches!(
+            sessions[0].finalize(),
+            Err(MuSigError::MissingPartialSignatures)
+        ));
+
+        let forged = PartialSignature(UBig::from(12345u64));
+        assert_eq!(
+            sessions[0].add_partial_signature(&pubs[1], forged),
+            Err(MuSigError::InvalidPartialSignature(pubs[1].clone()))
+        );
+        for session in &mut sessions {
+            for (key, partial) in pubs.iter().zip(&partials) {
+                let partial = PartialSignature::from_be_bytes(partial).unwrap();
+                session.add_partial_signature(key, partial).unwrap();
+            }
+            assert_eq!(session.state(), SessionState::Complete);
+            let sig = session.finalize().unwrap();
+            assert!(ctx.aggregate_key().verify(&m, &sig));
+        }
+
+        let stranger = PrivateKey(Scalar::from_u64(44));
+        assert!(matches!(
+            SigningSession::new(ctx.clone(), &stranger, m),
+            Err(MuSigError::UnknownSigner(_))
+        ));
+        assert!(matches!(
+            KeyAggContext::new(vec![pubs[0].clone(), pubs[0].clone()]),
+            Err(MuSigError::DuplicateKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_musig_single_signer() {
+        let key = PrivateKey(Scalar::from_u64(7));
+        let m = Digest([Belt(9), Belt(8), Belt(7), Belt(6), Belt(5)]);
+        let ctx = KeyAggContext::new(vec![key.public_key()]).unwrap();
+        let mut session = SigningSession::new(ctx.clone(), &key, m).unwrap();
+        assert_eq!(session.state(), SessionState::ReadyToSign);
+        session.sign().unwrap();
+        assert!(ctx.aggregate_key().verify(&m, &session.finalize().unwrap()));
+    }
+}
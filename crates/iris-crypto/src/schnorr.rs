@@ -0,0 +1,96 @@
+//! Helpers shared by the multi-party Schnorr protocols.
+
+use ibig::modular::ModuloRing;
+use ibig::UBig;
+use iris_ztd::crypto::cheetah::{
+    ch_add, ch_scal_big, ch_scal_gen_ct, trunc_g_order, trunc_g_order_ct, CheetahPoint, G_ORDER,
+};
+use iris_ztd::crypto::scalar::Scalar;
+use iris_ztd::tip5::hash::hash_varlen;
+use iris_ztd::{Belt, Digest};
+
+pub(crate) use crate::cheetah::signature_challenge;
+
+/// Domain-separated hash of curve points and scalars into a scalar.
+#[derive(Clone)]
+pub(crate) struct Transcript(Vec<Belt>);
+
+impl Transcript {
+    pub(crate) fn new(tag: &[u8]) -> Self {
+        let mut belts = vec![Belt(tag.len() as u64)];
+        belts.extend(Belt::from_bytes(tag));
+        Self(belts)
+    }
+
+    pub(crate) fn point(mut self, p: &CheetahPoint) -> Self {
+        self.0.extend_from_slice(&p.x.0);
+        self.0.extend_from_slice(&p.y.0);
+        self
+    }
+
+    /// Append a scalar as its 32 little-endian bytes.
+    pub(crate) fn secret(mut self, s: &Scalar) -> Self {
+        self.0.extend(Belt::from_bytes(&s.to_le_bytes()));
+        self
+    }
+
+    pub(crate) fn digest(mut self, m: &Digest) -> Self {
+        self.0.extend_from_slice(&m.0);
+        self
+    }
+
+    pub(crate) fn words(mut self, words: &[u64]) -> Self {
+        self.0.extend(words.iter().map(|&w| Belt(w)));
+        self
+    }
+
+    pub(crate) fn hash(mut self) -> [u64; 5] {
+        hash_varlen(&mut self.0)
+    }
+
+    pub(crate) fn scalar_hash(self) -> UBig {
+        trunc_g_order(&self.hash())
+    }
+
+    /// [`Transcript::scalar_hash`] for transcripts that contain secrets.
+    pub(crate) fn secret_scalar_hash(self) -> Scalar {
+        trunc_g_order_ct(&self.hash())
+    }
+}
+
+pub(crate) fn mul(k: &UBig, p: &CheetahPoint) -> CheetahPoint {
+    ch_scal_big(k, p).unwrap()
+}
+
+/// `k * G` for a secret `k`, in constant time.
+pub(crate) fn mul_gen_secret(k: &Scalar) -> CheetahPoint {
+    ch_scal_gen_ct(k)
+}
+
+pub(crate) fn add(p: &CheetahPoint, q: &CheetahPoint) -> CheetahPoint {
+    ch_add(p, q).unwrap()
+}
+
+/// A nonzero scalar derived from fresh randomness and the given transcript.
+///
+/// Mixing in the transcript, which should include the secret key and message, keeps nonces
+/// unpredictable even if the system randomness is weak.
+pub(crate) fn random_scalar(transcript: Transcript) -> Result<Scalar, getrandom::Error> {
+    let mut rand = [0u8; 32];
+    getrandom::getrandom(&mut rand)?;
+    let k = transcript.words(&rand.map(u64::from)).secret_scalar_hash();
+    if k.is_zero() {
+        // Only with negligible probability.
+        return Ok(Scalar::ONE);
+    }
+    Ok(k)
+}
+
+/// Inverse of a nonzero scalar modulo the group order.
+pub(crate) fn invert(x: &UBig) -> UBig {
+    let ring = ModuloRing::new(&G_ORDER);
+    ring.from(x)
+        .inverse()
+        .expect("zero has no inverse")
+        .residue()
+}
@@ -0,0 +1,43 @@
+use core::convert::Infallible;
+use core::future::Future;
+
+use iris_ztd::Digest;
+
+use crate::cheetah::{PrivateKey, PublicKey, Signature};
+
+/// Something that holds a private key, and can sign digests with it.
+///
+/// Signing may be asynchronous, so that the key can live outside of the process, for example in
+/// a hardware wallet or a remote signing daemon. The returned future need not be `Send`, so that
+/// signers backed by JavaScript promises can implement this trait.
+pub trait Signer {
+    type Error;
+
+    fn public_key(&self) -> PublicKey;
+
+    fn sign(&self, digest: &Digest) -> impl Future<Output = Result<Signature, Self::Error>>;
+}
+
+impl Signer for PrivateKey {
+    type Error = Infallible;
+
+    fn public_key(&self) -> PublicKey {
+        PrivateKey::public_key(self)
+    }
+
+    async fn sign(&self, digest: &Digest) -> Result<Signature, Infallible> {
+        Ok(PrivateKey::sign(self, digest))
+    }
+}
+
+impl<S: Signer + ?Sized> Signer for &S {
+    type Error = S::Error;
+
+    fn public_key(&self) -> PublicKey {
+        (**self).public_key()
+    }
+
+    fn sign(&self, digest: &Digest) -> impl Future<Output = Result<Signature, Self::Error>> {
+        (**self).sign(digest)
+    }
+}
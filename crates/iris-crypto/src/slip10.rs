@@ -1,6 +1,10 @@
+use core::fmt;
+use core::str::FromStr;
+
 use hmac::{Hmac, Mac};
-use ibig::UBig;
-use iris_ztd::crypto::cheetah::{ch_add, ch_scal_big, A_GEN, G_ORDER};
+use iris_ztd::crypto::cheetah::{ch_add, ch_scal_big, A_GEN};
+use iris_ztd::crypto::scalar::Scalar;
+use iris_ztd::Hashable;
 use sha2::Sha512;
 
 use crate::cheetah::{PrivateKey, PublicKey};
@@ -11,25 +15,300 @@ fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; 64] {
     mac.finalize().into_bytes().into()
 }
 
+/// Offset of hardened child indices.
+pub const HARDENED: u32 = 1 << 31;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DerivationPathError {
+    /// The path does not start with `m`.
+    MissingRoot,
+    InvalidIndex(String),
+}
+
+impl fmt::Display for DerivationPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DerivationPathError::MissingRoot => write!(f, "derivation path must start with 'm'"),
+            DerivationPathError::InvalidIndex(s) => write!(f, "invalid child index '{}'", s),
+        }
+    }
+}
+
+impl std::error::Error for DerivationPathError {}
+
+/// A sequence of child indices, written like `m/44'/0'/0/5`.
+///
+/// Hardened indices are stored with [`HARDENED`] added, and are written with a `'` suffix.
+/// Parsing also accepts `h` and `H` suffixes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DerivationPath(pub Vec<u32>);
+
+impl DerivationPath {
+    pub fn indices(&self) -> &[u32] {
+        &self.0
+    }
+
+    pub fn child(mut self, index: u32) -> Self {
+        self.0.push(index);
+        self
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = DerivationPathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('/');
+        if parts.next() != Some("m") {
+            return Err(DerivationPathError::MissingRoot);
+        }
+        parts
+            .map(|part| {
+                let (digits, hardened) = match part.strip_suffix(['\'', 'h', 'H']) {
+                    Some(digits) => (digits, true),
+                    None => (part, false),
+                };
+                let invalid = || DerivationPathError::InvalidIndex(part.to_string());
+                if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(invalid());
+                }
+                let index: u32 = digits.parse().map_err(|_| invalid())?;
+                if index >= HARDENED {
+                    return Err(invalid());
+                }
+                Ok(if hardened { index + HARDENED } else { index })
+            })
+            .collect::<Result<_, _>>()
+            .map(DerivationPath)
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m")?;
+        for &index in &self.0 {
+            if index >= HARDENED {
+                write!(f, "/{}'", index - HARDENED)?;
+            } else {
+                write!(f, "/{}", index)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Version bytes of base58 extended public keys, as for BIP32 mainnet keys.
+pub const XPUB_VERSION: [u8; 4] = [0x04, 0x88, 0xb2, 0x1e];
+/// Version bytes of base58 extended private keys, as for BIP32 mainnet keys.
+pub const XPRV_VERSION: [u8; 4] = [0x04, 0x88, 0xad, 0xe4];
+
+/// Length of a serialized extended key, up to and including the chain code.
+const HEADER_LEN: usize = 4 + 1 + 4 + 4 + 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtendedKeyError {
+    /// Not base58, or the checksum does not match.
+    InvalidBase58(String),
+    UnknownVersion([u8; 4]),
+    InvalidLength(usize),
+    /// A master key (depth 0) with a parent fingerprint or child number.
+    InvalidDepth,
+    InvalidPrivateKey,
+    InvalidPublicKey,
+}
+
+impl fmt::Display for ExtendedKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtendedKeyError::InvalidBase58(e) => write!(f, "invalid base58check: {}", e),
+            ExtendedKeyError::UnknownVersion(v) => write!(f, "unknown version bytes {:02x?}", v),
+            ExtendedKeyError::InvalidLength(len) => {
+                write!(f, "invalid extended key length {}", len)
+            }
+            ExtendedKeyError::InvalidDepth => {
+                write!(f, "master key has a parent fingerprint or child number")
+            }
+            ExtendedKeyError::InvalidPrivateKey => write!(f, "invalid private key"),
+            ExtendedKeyError::InvalidPublicKey => write!(f, "invalid public key"),
+        }
+    }
+}
+
+impl std::error::Error for ExtendedKeyError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeriveError {
+    /// The parent is at depth 255, so its child's depth would not fit in a byte.
+    DepthExceeded,
+    /// A hardened index was requested from a key without its private half.
+    HardenedFromPublic,
+}
+
+impl fmt::Display for DeriveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeriveError::DepthExceeded => write!(f, "cannot derive beyond depth 255"),
+            DeriveError::HardenedFromPublic => {
+                write!(f, "cannot derive hardened child without private key")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DeriveError {}
+
 /// SLIP-10 Extended Key (private or public key + chain code)
 #[derive(Debug, Clone)]
 pub struct ExtendedKey {
     pub private_key: Option<PrivateKey>,
     pub public_key: PublicKey,
     pub chain_code: [u8; 32],
+    /// Number of derivations from the master key, which has depth 0.
+    pub depth: u8,
+    /// Fingerprint of the parent key, or zeros for the master key.
+    pub parent_fingerprint: [u8; 4],
+    /// Index this key was derived at, or 0 for the master key.
+    pub child_number: u32,
 }
 
 impl ExtendedKey {
+    /// First 4 bytes of the hash of the public key, which is also its pkh.
+    pub fn fingerprint(&self) -> [u8; 4] {
+        let mut fingerprint = [0u8; 4];
+        fingerprint.copy_from_slice(&self.public_key.hash().to_bytes()[..4]);
+        fingerprint
+    }
+
+    /// The same key without its private half, for watch-only use.
+    pub fn to_public(&self) -> ExtendedKey {
+        ExtendedKey {
+            private_key: None,
+            ..self.clone()
+        }
+    }
+
+    fn serialize(&self, version: [u8; 4], key: &[u8]) -> String {
+        let mut data = Vec::with_capacity(HEADER_LEN + key.len());
+        data.extend_from_slice(&version);
+        data.push(self.depth);
+        data.extend_from_slice(&self.parent_fingerprint);
+        data.extend_from_slice(&self.child_number.to_be_bytes());
+        data.extend_from_slice(&self.chain_code);
+        data.extend_from_slice(key);
+        bs58::encode(data).with_check().into_string()
+    }
+
+    /// Base58check encoding of the public half of this key.
+    ///
+    /// The payload is the version, depth, parent fingerprint, child number, chain code and
+    /// [`PublicKey::to_be_bytes`], like a BIP32 extended key but with 97-byte public keys.
+    pub fn to_xpub(&self) -> String {
+        self.serialize(XPUB_VERSION, &self.public_key.to_be_bytes())
+    }
+
+    /// Base58check encoding of this key including its private key, which is serialized as `0x00`
+    /// followed by its 32 big-endian bytes.
+    pub fn to_xprv(&self) -> Option<String> {
+        let private_key = self.private_key.as_ref()?;
+        let mut key = [0u8; 33];
+        key[1..].copy_from_slice(&private_key.to_be_bytes());
+        Some(self.serialize(XPRV_VERSION, &key))
+    }
+
+    /// Parse a key encoded with [`ExtendedKey::to_xpub`] or [`ExtendedKey::to_xprv`].
+    pub fn from_base58(s: &str) -> Result<ExtendedKey, ExtendedKeyError> {
+        let data = bs58::decode(s)
+            .with_check(None)
+            .into_vec()
+            .map_err(|e| ExtendedKeyError::InvalidBase58(e.to_string()))?;
+        if data.len() < HEADER_LEN {
+            return Err(ExtendedKeyError::InvalidLength(data.len()));
+        }
+
+        let version: [u8; 4] = data[..4].try_into().unwrap();
+        let depth = data[4];
+        let parent_fingerprint: [u8; 4] = data[5..9].try_into().unwrap();
+        let child_number = u32::from_be_bytes(data[9..13].try_into().unwrap());
+        let chain_code: [u8; 32] = data[13..HEADER_LEN].try_into().unwrap();
+        let key = &data[HEADER_LEN..];
+        if depth == 0 && (parent_fingerprint != [0; 4] || child_number != 0) {
+            return Err(ExtendedKeyError::InvalidDepth);
+        }
+
+        let (private_key, public_key) = match version {
+            XPUB_VERSION => {
+                if key.len() != 97 {
+                    return Err(ExtendedKeyError::InvalidLength(data.len()));
+                }
+                let public_key =
+                    PublicKey::try_from_be_bytes(key).ok_or(ExtendedKeyError::InvalidPublicKey)?;
+                (None, public_key)
+            }
+            XPRV_VERSION => {
+                if key.len() != 33 {
+                    return Err(ExtendedKeyError::InvalidLength(data.len()));
+                }
+                let private_key = PrivateKey::from_be_bytes(&key[1..])
+                    .filter(|k| key[0] == 0 && !k.0.is_zero())
+                    .ok_or(ExtendedKeyError::InvalidPrivateKey)?;
+                let public_key = private_key.public_key();
+                (Some(private_key), public_key)
+            }
+            _ => return Err(ExtendedKeyError::UnknownVersion(version)),
+        };
+
+        Ok(ExtendedKey {
+            private_key,
+            public_key,
+            chain_code,
+            depth,
+            parent_fingerprint,
+            child_number,
+        })
+    }
+
+    /// Derive the key at `path`, relative to this key.
+    ///
+    /// # Panics
+    ///
+    /// See [`ExtendedKey::derive_child`]; [`ExtendedKey::try_derive_path`] returns an error
+    /// instead.
+    pub fn derive_path(&self, path: &DerivationPath) -> ExtendedKey {
+        self.try_derive_path(path).unwrap()
+    }
+
+    /// Derive the key at `path`, relative to this key, failing if any step of it fails.
+    pub fn try_derive_path(&self, path: &DerivationPath) -> Result<ExtendedKey, DeriveError> {
+        path.0
+            .iter()
+            .try_fold(self.clone(), |key, &index| key.try_derive_child(index))
+    }
+
     /// Derive a child key at the given index using SLIP-10
+    ///
+    /// # Panics
+    ///
+    /// If this key is at depth 255, or `index` is hardened and this key has no private key.
+    /// [`ExtendedKey::try_derive_child`] returns an error instead.
     pub fn derive_child(&self, index: u32) -> ExtendedKey {
-        let hardened = index >= (1 << 31);
+        self.try_derive_child(index).unwrap()
+    }
+
+    /// Derive a child key at the given index using SLIP-10, failing where
+    /// [`ExtendedKey::derive_child`] would panic.
+    pub fn try_derive_child(&self, index: u32) -> Result<ExtendedKey, DeriveError> {
+        let hardened = index >= HARDENED;
+        let depth = self
+            .depth
+            .checked_add(1)
+            .ok_or(DeriveError::DepthExceeded)?;
 
         let mut data = Vec::new();
         if hardened {
             let private_key = self
                 .private_key
                 .as_ref()
-                .expect("Cannot derive hardened child without private key");
+                .ok_or(DeriveError::HardenedFromPublic)?;
             data.push(0x00);
             data.extend_from_slice(&private_key.to_be_bytes());
             data.extend_from_slice(&index.to_be_bytes());
@@ -41,33 +320,39 @@ impl ExtendedKey {
         let mut result = hmac_sha512(&self.chain_code, &data);
 
         loop {
-            let left = UBig::from_be_bytes(&result[..32]);
+            let left = Scalar::from_be_bytes(result[..32].try_into().unwrap());
             let mut chain_code = [0u8; 32];
             chain_code.copy_from_slice(&result[32..]);
 
-            if left < *G_ORDER {
+            if let Some(left) = left {
                 match self.private_key.as_ref() {
                     Some(pk) => {
-                        let s = (&left + &pk.0) % &*G_ORDER;
-                        if s != UBig::from(0u64) {
+                        let s = left + pk.0;
+                        if !s.is_zero() {
                             let private_key = PrivateKey(s);
                             let public_key = private_key.public_key();
-                            return ExtendedKey {
+                            return Ok(ExtendedKey {
                                 private_key: Some(private_key),
                                 public_key,
                                 chain_code,
-                            };
+                                depth,
+                                parent_fingerprint: self.fingerprint(),
+                                child_number: index,
+                            });
                         }
                     }
                     None => {
-                        let mut point = ch_scal_big(&left, &A_GEN).unwrap();
+                        let mut point = ch_scal_big(&left.to_ubig(), &A_GEN).unwrap();
                         point = ch_add(&point, &self.public_key.0).unwrap();
                         if !point.inf {
-                            return ExtendedKey {
+                            return Ok(ExtendedKey {
                                 private_key: None,
                                 public_key: PublicKey(point),
                                 chain_code,
-                            };
+                                depth,
+                                parent_fingerprint: self.fingerprint(),
+                                child_number: index,
+                            });
                         }
                     }
                 }
@@ -86,16 +371,19 @@ pub fn derive_master_key(seed: &[u8]) -> ExtendedKey {
     const DOMAIN_SEPARATOR: &[u8] = b"Nockchain seed";
     let mut result = hmac_sha512(DOMAIN_SEPARATOR, seed);
     loop {
-        let s = UBig::from_be_bytes(&result[..32]);
+        let s = Scalar::from_be_bytes(result[..32].try_into().unwrap());
         let mut chain_code = [0u8; 32];
         chain_code.copy_from_slice(&result[32..]);
-        if s < *G_ORDER && s != UBig::from(0u64) {
+        if let Some(s) = s.filter(|s| !s.is_zero()) {
             let private_key = PrivateKey(s);
             let public_key = private_key.public_key();
             return ExtendedKey {
                 private_key: Some(private_key),
                 public_key,
                 chain_code,
+                depth: 0,
+                parent_fingerprint: [0; 4],
+                child_number: 0,
             };
         }
         result = hmac_sha512(DOMAIN_SEPARATOR, &result[..]);
@@ -168,4 +456,125 @@ mod tests {
             from_b58("AJMwSLmz1k9YnDa1iQTVbpz4Jr4hZojxeCHiqLY7TnjYLxXjZtmbskw")
         );
     }
+
+    #[test]
+    fn test_derivation_path() {
+        let path: DerivationPath = "m/44'/0h/1/5H".parse().unwrap();
+        assert_eq!(path.indices(), &[44 + HARDENED, HARDENED, 1, 5 + HARDENED]);
+        assert_eq!(path.to_string(), "m/44'/0'/1/5'");
+        assert_eq!(
+            "m".parse::<DerivationPath>().unwrap(),
+            DerivationPath::default()
+        );
+        assert_eq!(
+            "44'/0".parse::<DerivationPath>(),
+            Err(DerivationPathError::MissingRoot)
+        );
+        for bad in ["m/", "m/x", "m/-1", "m/+1", "m/1''", "m/2147483648"] {
+            assert!(bad.parse::<DerivationPath>().is_err(), "{}", bad);
+        }
+
+        let mnemonic = Mnemonic::parse("clutch inmate mango seek attract credit illegal popular term loyal fiber output trumpet lucky garbage merge menu certain dynamic aim trip fantasy master unveil").unwrap();
+        let key = derive_master_key(&mnemonic.to_seed(""));
+        assert_eq!(
+            (key.depth, key.parent_fingerprint, key.child_number),
+            (0, [0; 4], 0)
+        );
+
+        let child = key.derive_path(&"m/0'/7".parse().unwrap());
+        let expected = key.derive_child(HARDENED).derive_child(7);
+        assert_eq!(child.chain_code, expected.chain_code);
+        assert_eq!(child.depth, 2);
+        assert_eq!(child.child_number, 7);
+        assert_eq!(
+            child.parent_fingerprint,
+            key.derive_child(HARDENED).fingerprint()
+        );
+        assert_eq!(key.fingerprint()[..], key.public_key.hash().to_bytes()[..4]);
+    }
+
+    #[test]
+    fn test_extended_key_base58() {
+        let mnemonic = Mnemonic::parse("clutch inmate mango seek attract credit illegal popular term loyal fiber output trumpet lucky garbage merge menu certain dynamic aim trip fantasy master unveil").unwrap();
+        let master = derive_master_key(&mnemonic.to_seed(""));
+        let account = master.derive_path(&"m/44'/0'".parse().unwrap());
+
+        let xprv = account.to_xprv().unwrap();
+        let parsed = ExtendedKey::from_base58(&xprv).unwrap();
+        assert_eq!(
+            parsed.private_key.as_ref().unwrap().to_be_bytes(),
+            account.private_key.as_ref().unwrap().to_be_bytes()
+        );
+        assert_eq!(parsed.public_key, account.public_key);
+        assert_eq!(parsed.chain_code, account.chain_code);
+        assert_eq!(
+            (parsed.depth, parsed.parent_fingerprint, parsed.child_number),
+            (2, account.parent_fingerprint, HARDENED)
+        );
+        assert_eq!(parsed.to_xprv().unwrap(), xprv);
+
+        // A watch-only wallet derives the same public keys from the xpub.
+        let xpub = account.to_xpub();
+        let watch_only = ExtendedKey::from_base58(&xpub).unwrap();
+        assert!(watch_only.private_key.is_none());
+        assert!(watch_only.to_xprv().is_none());
+        assert_eq!(account.to_public().to_xpub(), xpub);
+        assert_eq!(
+            watch_only.derive_child(5).to_xpub(),
+            account.derive_child(5).to_xpub()
+        );
+        assert!(ExtendedKey::from_base58(&master.to_xpub()).is_ok());
+
+        let mut corrupted = xpub.clone().into_bytes();
+        corrupted[20] = if corrupted[20] == b'2' { b'3' } else { b'2' };
+        assert!(matches!(
+            ExtendedKey::from_base58(core::str::from_utf8(&corrupted).unwrap()),
+            Err(ExtendedKeyError::InvalidBase58(_))
+        ));
+
+        let reencode = |f: &dyn Fn(&mut Vec<u8>)| {
+            let mut data = bs58::decode(&xpub).with_check(None).into_vec().unwrap();
+            f(&mut data);
+            ExtendedKey::from_base58(&bs58::encode(data).with_check().into_string()).err()
+        };
+        assert_eq!(
+            reencode(&|d| d[0] = 0),
+            Some(ExtendedKeyError::UnknownVersion([0, 0x88, 0xb2, 0x1e]))
+        );
+        assert_eq!(
+            reencode(&|d| d[..4].copy_from_slice(&XPRV_VERSION)),
+            Some(ExtendedKeyError::InvalidLength(142))
+        );
+        assert_eq!(
+            reencode(&|d| d[4] = 0),
+            Some(ExtendedKeyError::InvalidDepth)
+        );
+        assert_eq!(
+            reencode(&|d| d[100] ^= 1),
+            Some(ExtendedKeyError::InvalidPublicKey)
+        );
+        assert_eq!(
+            reencode(&|d| d.truncate(40)),
+            Some(ExtendedKeyError::InvalidLength(40))
+        );
+
+        // Keys at the last depth parse, but have no children.
+        let mut data = bs58::decode(&xpub).with_check(None).into_vec().unwrap();
+        data[4] = 255;
+        let deepest =
+            ExtendedKey::from_base58(&bs58::encode(data).with_check().into_string()).unwrap();
+        assert_eq!(
+            deepest.try_derive_child(0).err(),
+            Some(DeriveError::DepthExceeded)
+        );
+        assert_eq!(
+            watch_only.try_derive_child(HARDENED).err(),
+            Some(DeriveError::HardenedFromPublic)
+        );
+        let too_long = DerivationPath(vec![0; 256]);
+        assert_eq!(
+            master.try_derive_path(&too_long).err(),
+            Some(DeriveError::DepthExceeded)
+        );
+    }
 }
@@ -1,7 +1,13 @@
 // Client module is only available for native targets (not WASM)
 #![cfg(not(target_arch = "wasm32"))]
 
-use iris_nockchain_types::{BalanceUpdate, RawTx, TxId};
+use std::collections::BTreeSet;
+use std::time::Duration;
+#[cfg(feature = "tokio")]
+use std::time::Instant;
+
+use iris_nockchain_types::{BalanceUpdate, BlockHeight, RawTx, TxId};
+use iris_ztd::Digest;
 use tonic::transport::Channel;
 
 use crate::pb::common::v1::{Base58Hash, Base58Pubkey, PageRequest};
@@ -30,11 +36,15 @@ pub enum ClientError {
 
     #[error("Conversion error: {0}")]
     Conversion(#[from] crate::common::ConversionError),
+
+    #[error("Transaction was not confirmed after {0} rebroadcasts")]
+    ConfirmationTimeout(u32),
 }
 
 #[derive(Clone)]
 pub struct PublicNockchainGrpcClient {
     client: NockchainServiceClient<Channel>,
+    balance_options: BalanceOptions,
 }
 
 pub enum BalanceRequest {
@@ -42,86 +52,233 @@ pub enum BalanceRequest {
     FirstName(String),
 }
 
-impl PublicNockchainGrpcClient {
-    pub async fn connect<T: AsRef<str>>(address: T) -> Result<Self> {
-        let client = NockchainServiceClient::connect(address.as_ref().to_string()).await?;
-        Ok(Self { client })
+impl BalanceRequest {
+    fn selector(&self) -> wallet_get_balance_request::Selector {
+        match self {
+            BalanceRequest::Address(addr) => {
+                wallet_get_balance_request::Selector::Address(Base58Pubkey { key: addr.clone() })
+            }
+            BalanceRequest::FirstName(fname) => {
+                wallet_get_balance_request::Selector::FirstName(Base58Hash {
+                    hash: fname.clone(),
+                })
+            }
+        }
     }
+}
 
-    pub async fn wallet_get_balance(&mut self, request: &BalanceRequest) -> Result<BalanceUpdate> {
-        let mut page_token = String::new();
-        let mut all_notes: Vec<pb_common_v2::BalanceEntry> = Vec::new();
-        let mut height: Option<pb_common_v1::BlockHeight> = None;
-        let mut block_id: Option<pb_common_v1::Hash> = None;
+/// Paging and retry behavior of balance requests.
+#[derive(Debug, Clone)]
+pub struct BalanceOptions {
+    /// Maximum notes per page. 0 lets the server choose.
+    pub page_items_limit: u32,
+    /// Soft limit on the size of a page in bytes. 0 lets the server choose.
+    pub max_bytes: u64,
+    /// How many times to start over when the snapshot changes while paging.
+    pub snapshot_retries: u32,
+    /// Delay before the first retry. Each further retry waits this much longer.
+    ///
+    /// Without the `tokio` feature there is no timer, and retries are made right away.
+    pub retry_delay: Duration,
+}
 
-        loop {
-            let sel = match request {
-                BalanceRequest::Address(addr) => {
-                    wallet_get_balance_request::Selector::Address(Base58Pubkey {
-                        key: addr.clone(),
-                    })
-                }
-                BalanceRequest::FirstName(fname) => {
-                    wallet_get_balance_request::Selector::FirstName(Base58Hash {
-                        hash: fname.clone(),
-                    })
-                }
-            };
-
-            let req = WalletGetBalanceRequest {
-                selector: Some(sel),
-                page: Some(PageRequest {
-                    client_page_items_limit: 0, // let server choose default/cap
-                    page_token: page_token.clone(),
-                    max_bytes: 0,
-                }),
-            };
-
-            let resp = self.client.wallet_get_balance(req).await?.into_inner();
-            let balance = match resp.result {
-                Some(wallet_get_balance_response::Result::Balance(b)) => b,
-                Some(wallet_get_balance_response::Result::Error(e)) => {
-                    return Err(ClientError::ServerError(e.message))
-                }
-                None => return Err(ClientError::EmptyResponse),
-            };
+impl Default for BalanceOptions {
+    fn default() -> Self {
+        Self {
+            page_items_limit: 0,
+            max_bytes: 0,
+            snapshot_retries: 3,
+            retry_delay: Duration::from_millis(500),
+        }
+    }
+}
 
-            if height.is_none() {
-                height = balance.height;
-                block_id = balance.block_id;
-            }
+/// Pages of a balance, fetched one at a time. See
+/// [`PublicNockchainGrpcClient::wallet_balance_pages`].
+pub struct BalancePages<'a> {
+    client: &'a mut PublicNockchainGrpcClient,
+    selector: wallet_get_balance_request::Selector,
+    options: BalanceOptions,
+    page_token: String,
+    snapshot: Option<(
+        Option<pb_common_v1::BlockHeight>,
+        Option<pb_common_v1::Hash>,
+    )>,
+    done: bool,
+}
 
-            if balance.height != height || balance.block_id != block_id {
-                return Err(ClientError::SnapshotChanged);
+impl BalancePages<'_> {
+    /// Resume from a token previously returned by [`BalancePages::page_token`].
+    ///
+    /// The snapshot of the earlier pages is not known, so it is not checked against.
+    pub fn with_page_token(mut self, page_token: String) -> Self {
+        self.page_token = page_token;
+        self
+    }
+
+    /// Token of the next page to fetch. Empty before the first page and after the last one.
+    pub fn page_token(&self) -> &str {
+        &self.page_token
+    }
+
+    /// Fetch the next page, or `None` once all pages were returned, or after an error.
+    ///
+    /// Each page carries the snapshot's height and block id, and only the notes of that page.
+    pub async fn next(&mut self) -> Option<Result<BalanceUpdate>> {
+        if self.done {
+            return None;
+        }
+        let result = self.fetch().await;
+        if result.is_err() || self.page_token.is_empty() {
+            self.done = true;
+        }
+        Some(result)
+    }
+
+    async fn fetch(&mut self) -> Result<BalanceUpdate> {
+        let req = WalletGetBalanceRequest {
+            selector: Some(self.selector.clone()),
+            page: Some(PageRequest {
+                client_page_items_limit: self.options.page_items_limit,
+                page_token: self.page_token.clone(),
+                max_bytes: self.options.max_bytes,
+            }),
+        };
+
+        let resp = self
+            .client
+            .client
+            .wallet_get_balance(req)
+            .await?
+            .into_inner();
+        let mut balance = match resp.result {
+            Some(wallet_get_balance_response::Result::Balance(b)) => b,
+            Some(wallet_get_balance_response::Result::Error(e)) => {
+                return Err(ClientError::ServerError(e.message))
             }
+            None => return Err(ClientError::EmptyResponse),
+        };
 
-            all_notes.extend(balance.notes.into_iter());
-            page_token = balance
-                .page
-                .and_then(|p| {
-                    if p.next_page_token.is_empty() {
-                        None
-                    } else {
-                        Some(p.next_page_token)
-                    }
-                })
-                .unwrap_or_default();
+        let snapshot = (balance.height, balance.block_id);
+        match &self.snapshot {
+            None => self.snapshot = Some(snapshot),
+            Some(s) if *s != snapshot => return Err(ClientError::SnapshotChanged),
+            Some(_) => {}
+        }
+
+        self.page_token = balance
+            .page
+            .take()
+            .map(|p| p.next_page_token)
+            .unwrap_or_default();
 
-            if page_token.is_empty() {
-                break;
+        Ok(balance.try_into()?)
+    }
+}
+
+/// Where a submitted transaction is in its lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxStatus {
+    /// The node has not accepted the transaction yet.
+    Pending,
+    /// The node accepted the transaction, but it is not in a block yet.
+    Accepted,
+    /// The transaction's outputs exist, as of the block at this height.
+    Confirmed(BlockHeight),
+    /// Some inputs were spent by a different transaction.
+    Dropped,
+}
+
+/// How [`PublicNockchainGrpcClient::submit_and_confirm`] polls for a transaction.
+#[cfg(feature = "tokio")]
+#[derive(Debug, Clone)]
+pub struct ConfirmationPolicy {
+    /// Delay before the first poll, and after each rebroadcast.
+    pub poll_interval: Duration,
+    pub max_poll_interval: Duration,
+    /// Factor the poll interval grows by after each poll. 0 is treated as 1.
+    pub backoff: u32,
+    /// Rebroadcast a transaction that has not been included in a block within this long.
+    pub rebroadcast_after: Duration,
+    pub max_rebroadcasts: u32,
+    /// Number of blocks, including the one with the transaction, to wait for.
+    pub confirmations: u64,
+}
+
+#[cfg(feature = "tokio")]
+impl Default for ConfirmationPolicy {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            max_poll_interval: Duration::from_secs(30),
+            backoff: 2,
+            rebroadcast_after: Duration::from_secs(120),
+            max_rebroadcasts: 3,
+            confirmations: 1,
+        }
+    }
+}
+
+impl PublicNockchainGrpcClient {
+    pub async fn connect<T: AsRef<str>>(address: T) -> Result<Self> {
+        let client = NockchainServiceClient::connect(address.as_ref().to_string()).await?;
+        Ok(Self {
+            client,
+            balance_options: BalanceOptions::default(),
+        })
+    }
+
+    /// Use `options` for all balance requests made by this client.
+    pub fn with_balance_options(mut self, options: BalanceOptions) -> Self {
+        self.balance_options = options;
+        self
+    }
+
+    /// Fetch every page of the balance, from a single snapshot.
+    ///
+    /// If the snapshot changes while paging, the whole balance is fetched again, up to
+    /// [`BalanceOptions::snapshot_retries`] times.
+    pub async fn wallet_get_balance(&mut self, request: &BalanceRequest) -> Result<BalanceUpdate> {
+        let mut retries = 0;
+        loop {
+            match self.collect_balance(request).await {
+                Err(ClientError::SnapshotChanged)
+                    if retries < self.balance_options.snapshot_retries =>
+                {
+                    retries += 1;
+                    #[cfg(feature = "tokio")]
+                    tokio::time::sleep(self.balance_options.retry_delay * retries).await;
+                }
+                result => return result,
             }
         }
+    }
 
-        let pb_balance = pb_common_v2::Balance {
-            notes: all_notes,
-            height,
-            block_id,
-            page: Some(pb_common_v1::PageResponse {
-                next_page_token: String::new(),
-            }),
+    async fn collect_balance(&mut self, request: &BalanceRequest) -> Result<BalanceUpdate> {
+        let mut pages = self.wallet_balance_pages(request);
+        let mut balance = match pages.next().await {
+            Some(page) => page?,
+            None => return Err(ClientError::EmptyResponse),
         };
+        while let Some(page) = pages.next().await {
+            balance.notes.0.extend(page?.notes.0);
+        }
+        Ok(balance)
+    }
 
-        Ok(pb_balance.try_into()?)
+    /// Fetch the balance one page at a time, without buffering all notes.
+    ///
+    /// Pages are not retried if the snapshot changes. Instead, [`BalancePages::next`] returns
+    /// [`ClientError::SnapshotChanged`], and the caller has to start over.
+    pub fn wallet_balance_pages(&mut self, request: &BalanceRequest) -> BalancePages<'_> {
+        BalancePages {
+            selector: request.selector(),
+            options: self.balance_options.clone(),
+            client: self,
+            page_token: String::new(),
+            snapshot: None,
+            done: false,
+        }
     }
 
     pub async fn wallet_send_transaction(&mut self, raw_tx: &RawTx) -> Result<TxId> {
@@ -149,6 +306,9 @@ impl PublicNockchainGrpcClient {
         }
     }
 
+    /// Whether the node has accepted the transaction with id `tx_id`.
+    ///
+    /// This is the node's own answer: `false` for a transaction it has not seen or has rejected.
     pub async fn transaction_accepted(&mut self, tx_id: Base58Hash) -> Result<bool> {
         let request = TransactionAcceptedRequest { tx_id: Some(tx_id) };
         let response = self
@@ -158,11 +318,434 @@ impl PublicNockchainGrpcClient {
             .into_inner();
 
         match response.result {
-            Some(transaction_accepted_response::Result::Accepted(_)) => Ok(true),
+            Some(transaction_accepted_response::Result::Accepted(accepted)) => Ok(accepted),
             Some(transaction_accepted_response::Result::Error(err)) => {
                 Err(ClientError::ServerError(err.message))
             }
             None => Err(ClientError::EmptyResponse),
         }
     }
+
+    /// Submit `raw_tx`, and poll until it has `policy.confirmations` confirmations or is dropped.
+    ///
+    /// `on_status` is called whenever the status changes. A transaction that is still pending or
+    /// only accepted after `policy.rebroadcast_after` is submitted again, up to
+    /// `policy.max_rebroadcasts` times, after which [`ClientError::ConfirmationTimeout`] is
+    /// returned.
+    #[cfg(feature = "tokio")]
+    pub async fn submit_and_confirm(
+        &mut self,
+        raw_tx: &RawTx,
+        policy: &ConfirmationPolicy,
+        mut on_status: impl FnMut(TxStatus),
+    ) -> Result<TxStatus> {
+        self.wallet_send_transaction(raw_tx).await?;
+
+        let backoff = policy.backoff.max(1);
+        let mut interval = policy.poll_interval;
+        let mut submitted_at = Instant::now();
+        let mut rebroadcasts = 0;
+        let mut last = None;
+
+        loop {
+            tokio::time::sleep(interval).await;
+            interval = (interval * backoff).min(policy.max_poll_interval);
+
+            let (status, tip) = self.status_at_tip(raw_tx).await?;
+            if last != Some(status) {
+                on_status(status);
+                last = Some(status);
+            }
+
+            match status {
+                TxStatus::Confirmed(height)
+                    if tip.saturating_sub(height) + 1 >= policy.confirmations =>
+                {
+                    return Ok(status)
+                }
+                TxStatus::Dropped => return Ok(status),
+                // A transaction the node accepted may still never make it into a block.
+                TxStatus::Pending | TxStatus::Accepted
+                    if submitted_at.elapsed() >= policy.rebroadcast_after =>
+                {
+                    if rebroadcasts == policy.max_rebroadcasts {
+                        return Err(ClientError::ConfirmationTimeout(rebroadcasts));
+                    }
+                    // The node may reject a transaction it already knows about.
+                    match self.wallet_send_transaction(raw_tx).await {
+                        Ok(_) | Err(ClientError::ServerError(_)) => {}
+                        Err(e) => return Err(e),
+                    }
+                    rebroadcasts += 1;
+                    submitted_at = Instant::now();
+                    interval = policy.poll_interval;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Find out where `raw_tx` is in its lifecycle.
+    ///
+    /// A transaction is confirmed once any of its outputs appear in the balance, and dropped once
+    /// any of its inputs are gone while none of the outputs are there. Outputs may be spent after
+    /// confirmation, so all of them are checked; a transaction whose outputs have all been spent
+    /// is only told apart from a dropped one while the node still reports it as accepted.
+    pub async fn transaction_status(&mut self, raw_tx: &RawTx) -> Result<TxStatus> {
+        Ok(self.status_at_tip(raw_tx).await?.0)
+    }
+
+    async fn status_at_tip(&mut self, raw_tx: &RawTx) -> Result<(TxStatus, BlockHeight)> {
+        let mut tip = 0;
+
+        let outputs = raw_tx.outputs();
+        let output_first_names = outputs
+            .iter()
+            .map(|note| note.name.first)
+            .collect::<BTreeSet<_>>();
+        for first_name in output_first_names {
+            let balance = self.balance_of_first_name(&first_name).await?;
+            tip = tip.max(balance.height);
+            let found = balance
+                .notes
+                .0
+                .iter()
+                .find(|(n, _)| outputs.iter().any(|o| o.name == *n));
+            if let Some((_, note)) = found {
+                return Ok((TxStatus::Confirmed(note.origin_page), tip));
+            }
+        }
+
+        let tx_id = Base58Hash {
+            hash: raw_tx.id.to_string(),
+        };
+        if self.transaction_accepted(tx_id).await? {
+            return Ok((TxStatus::Accepted, tip));
+        }
+
+        let first_names = raw_tx
+            .spends
+            .0
+            .iter()
+            .map(|(name, _)| name.first)
+            .collect::<BTreeSet<_>>();
+        for first_name in first_names {
+            let balance = self.balance_of_first_name(&first_name).await?;
+            tip = tip.max(balance.height);
+            let missing = raw_tx.spends.0.iter().any(|(name, _)| {
+                name.first == first_name && !balance.notes.0.iter().any(|(n, _)| n == name)
+            });
+            if missing {
+                return Ok((TxStatus::Dropped, tip));
+            }
+        }
+
+        Ok((TxStatus::Pending, tip))
+    }
+
+    async fn balance_of_first_name(&mut self, first_name: &Digest) -> Result<BalanceUpdate> {
+        self.wallet_get_balance(&BalanceRequest::FirstName(first_name.to_string()))
+            .await
+    }
+}
+
+#[cfg(all(test, feature = "mock-server"))]
+mod tests {
+    use super::*;
+    use crate::mock::MockNockchain;
+    use bip39::Mnemonic;
+    use iris_crypto::derive_master_key;
+    use iris_nockchain_types::{Name, Note, NoteData, Pkh, SpendCondition, TxBuilder, Version};
+    use iris_ztd::Hashable;
+
+    #[tokio::test]
+    async fn test_submit_and_confirm() {
+        let mnemonic = Mnemonic::parse("dice domain inspire horse time initial monitor nature mass impose tone benefit vibrant dash kiss mosquito rice then color ribbon agent method drop fat").unwrap();
+        let master = derive_master_key(&mnemonic.to_seed(""));
+        let private_key = master.private_key.clone().unwrap();
+        let pkh = private_key.public_key().hash();
+        let spend_condition = SpendCondition::new_pkh(Pkh::single(pkh));
+        let recipient_key = master.derive_child(0).private_key.unwrap();
+        let note = Note::new(
+            Version::V1,
+            0,
+            Name::new(spend_condition.first_name(), 0u64.hash()),
+            NoteData::empty(),
+            1 << 24,
+        );
+        let recipient = recipient_key.public_key().hash();
+        let send = |gift| {
+            TxBuilder::new(1 << 10)
+                .simple_spend(
+                    vec![(note.clone(), spend_condition.clone())],
+                    recipient,
+                    gift,
+                    pkh,
+                    false,
+                )
+                .unwrap()
+                .sign(&private_key)
+                .build()
+                .to_raw_tx()
+        };
+
+        let mock = MockNockchain::new().with_fee_per_word(1 << 10);
+        mock.add_note(note.clone());
+        let url = mock.clone().serve().await.unwrap();
+        let mut client = PublicNockchainGrpcClient::connect(url).await.unwrap();
+
+        let tx = send(1 << 22);
+        let conflicting = send(1 << 21);
+        assert_eq!(
+            client.transaction_status(&tx).await.unwrap(),
+            TxStatus::Pending
+        );
+
+        // Blocks keep coming while we wait for confirmations.
+        let miner = mock.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                miner.mine_block();
+            }
+        });
+
+        let policy = ConfirmationPolicy {
+            poll_interval: Duration::from_millis(5),
+            max_poll_interval: Duration::from_millis(20),
+            confirmations: 3,
+            ..Default::default()
+        };
+        let mut statuses = vec![];
+        let status = client
+            .submit_and_confirm(&tx, &policy, |s| statuses.push(s))
+            .await
+            .unwrap();
+        let TxStatus::Confirmed(height) = status else {
+            panic!("unexpected status {status:?}");
+        };
+        assert!(mock.height() >= height + 2);
+        // The mock includes transactions right away, so they are never seen as only accepted.
+        assert_eq!(statuses, vec![status]);
+
+        // Spending one of the outputs afterwards does not make the transaction look dropped.
+        let output = tx.outputs().into_iter().next().unwrap();
+        let output = mock
+            .balance()
+            .notes
+            .0
+            .into_iter()
+            .find(|(n, _)| *n == output.name)
+            .unwrap()
+            .1;
+        let key = [&private_key, &recipient_key]
+            .into_iter()
+            .find(|k| {
+                SpendCondition::new_pkh(Pkh::single(k.public_key().hash())).first_name()
+                    == output.name.first
+            })
+            .unwrap();
+        let output_condition = SpendCondition::new_pkh(Pkh::single(key.public_key().hash()));
+        let respend = TxBuilder::new(1 << 10)
+            .simple_spend(vec![(output, output_condition)], pkh, 100, pkh, false)
+            .unwrap()
+            .sign(key)
+            .build()
+            .to_raw_tx();
+        mock.apply_tx(&respend).unwrap();
+        assert_eq!(
+            client.transaction_status(&tx).await.unwrap(),
+            TxStatus::Confirmed(height)
+        );
+
+        assert_eq!(
+            client.transaction_status(&conflicting).await.unwrap(),
+            TxStatus::Dropped
+        );
+    }
+
+    #[tokio::test]
+    async fn test_confirmation_timeout() {
+        let mnemonic = Mnemonic::parse("dice domain inspire horse time initial monitor nature mass impose tone benefit vibrant dash kiss mosquito rice then color ribbon agent method drop fat").unwrap();
+        let private_key = derive_master_key(&mnemonic.to_seed(""))
+            .private_key
+            .unwrap();
+        let pkh = private_key.public_key().hash();
+        let spend_condition = SpendCondition::new_pkh(Pkh::single(pkh));
+        let note = Note::new(
+            Version::V1,
+            0,
+            Name::new(spend_condition.first_name(), 0u64.hash()),
+            NoteData::empty(),
+            1 << 24,
+        );
+        let tx = TxBuilder::new(1 << 10)
+            .simple_spend(vec![(note.clone(), spend_condition)], pkh, 1000, pkh, false)
+            .unwrap()
+            .sign(&private_key)
+            .build()
+            .to_raw_tx();
+
+        // The node accepts the transaction, but never mines it.
+        let mock = MockNockchain::new()
+            .with_fee_per_word(1 << 10)
+            .hold_transactions();
+        mock.add_note(note);
+        let url = mock.clone().serve().await.unwrap();
+        let mut client = PublicNockchainGrpcClient::connect(url).await.unwrap();
+
+        let policy = ConfirmationPolicy {
+            poll_interval: Duration::from_millis(5),
+            max_poll_interval: Duration::from_millis(5),
+            rebroadcast_after: Duration::from_millis(20),
+            max_rebroadcasts: 2,
+            ..Default::default()
+        };
+        let mut statuses = vec![];
+        let result = client
+            .submit_and_confirm(&tx, &policy, |s| statuses.push(s))
+            .await;
+        assert!(matches!(result, Err(ClientError::ConfirmationTimeout(2))));
+        assert_eq!(statuses, vec![TxStatus::Accepted]);
+        assert!(mock.is_accepted(&tx.id));
+        assert_eq!(mock.height(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_accepted() {
+        let mnemonic = Mnemonic::parse("dice domain inspire horse time initial monitor nature mass impose tone benefit vibrant dash kiss mosquito rice then color ribbon agent method drop fat").unwrap();
+        let private_key = derive_master_key(&mnemonic.to_seed(""))
+            .private_key
+            .unwrap();
+        let pkh = private_key.public_key().hash();
+        let spend_condition = SpendCondition::new_pkh(Pkh::single(pkh));
+        let note = Note::new(
+            Version::V1,
+            0,
+            Name::new(spend_condition.first_name(), 0u64.hash()),
+            NoteData::empty(),
+            1 << 24,
+        );
+        let tx = TxBuilder::new(1 << 10)
+            .simple_spend(vec![(note.clone(), spend_condition)], pkh, 1000, pkh, false)
+            .unwrap()
+            .sign(&private_key)
+            .build()
+            .to_raw_tx();
+
+        let mock = MockNockchain::new().with_fee_per_word(1 << 10);
+        mock.add_note(note);
+        let url = mock.clone().serve().await.unwrap();
+        let mut client = PublicNockchainGrpcClient::connect(url).await.unwrap();
+        let tx_id = Base58Hash {
+            hash: tx.id.to_string(),
+        };
+
+        // The node answers `accepted: false` for transactions it has never seen.
+        assert!(!client.transaction_accepted(tx_id.clone()).await.unwrap());
+        mock.apply_tx(&tx).unwrap();
+        assert!(client.transaction_accepted(tx_id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_balance_pages() {
+        let first_name = SpendCondition::new_pkh(Pkh::single(0u64.hash())).first_name();
+        let mock = MockNockchain::new().with_page_limit(3);
+        for i in 0..5u64 {
+            mock.add_note(Note::new(
+                Version::V1,
+                0,
+                Name::new(first_name, i.hash()),
+                NoteData::empty(),
+                1000,
+            ));
+        }
+        let url = mock.clone().serve().await.unwrap();
+        let request = BalanceRequest::FirstName(first_name.to_string());
+
+        // The caller's page size applies below the server's cap.
+        let mut client = PublicNockchainGrpcClient::connect(url)
+            .await
+            .unwrap()
+            .with_balance_options(BalanceOptions {
+                page_items_limit: 2,
+                ..Default::default()
+            });
+        let mut pages = client.wallet_balance_pages(&request);
+        let mut sizes = vec![];
+        while let Some(page) = pages.next().await {
+            sizes.push(page.unwrap().notes.0.len());
+        }
+        assert_eq!(sizes, vec![2, 2, 1]);
+        assert_eq!(
+            client
+                .wallet_get_balance(&request)
+                .await
+                .unwrap()
+                .notes
+                .0
+                .len(),
+            5
+        );
+
+        // A new block between pages ends the stream, but paging can be resumed.
+        let mut pages = client.wallet_balance_pages(&request);
+        pages.next().await.unwrap().unwrap();
+        let token = pages.page_token().to_string();
+        mock.mine_block();
+        assert!(matches!(
+            pages.next().await,
+            Some(Err(ClientError::SnapshotChanged))
+        ));
+        assert!(pages.next().await.is_none());
+
+        let mut pages = client.wallet_balance_pages(&request).with_page_token(token);
+        let mut rest = 0;
+        while let Some(page) = pages.next().await {
+            let page = page.unwrap();
+            assert_eq!(page.height, 1);
+            rest += page.notes.0.len();
+        }
+        assert_eq!(rest, 3);
+    }
+
+    #[tokio::test]
+    async fn test_balance_snapshot_retries() {
+        let first_name = SpendCondition::new_pkh(Pkh::single(0u64.hash())).first_name();
+        let mock = MockNockchain::new().with_page_limit(2);
+        for i in 0..5u64 {
+            mock.add_note(Note::new(
+                Version::V1,
+                0,
+                Name::new(first_name, i.hash()),
+                NoteData::empty(),
+                1000,
+            ));
+        }
+        let url = mock.clone().serve().await.unwrap();
+        let request = BalanceRequest::FirstName(first_name.to_string());
+        let mut client = PublicNockchainGrpcClient::connect(url)
+            .await
+            .unwrap()
+            .with_balance_options(BalanceOptions {
+                snapshot_retries: 2,
+                retry_delay: Duration::from_millis(1),
+                ..Default::default()
+            });
+
+        // The first attempt sees a new block on its second page, the retry gets a stable snapshot.
+        mock.mine_after_pages(2);
+        let balance = client.wallet_get_balance(&request).await.unwrap();
+        assert_eq!(balance.height, 2);
+        assert_eq!(balance.block_id, mock.block_id());
+        assert_eq!(balance.notes.0.len(), 5);
+
+        // Every attempt sees the snapshot change: the first one and two retries, two pages each.
+        mock.mine_after_pages(usize::MAX);
+        assert!(matches!(
+            client.wallet_get_balance(&request).await,
+            Err(ClientError::SnapshotChanged)
+        ));
+        assert_eq!(mock.height(), 2 + 3 * 2);
+    }
 }
@@ -18,6 +18,7 @@ use crate::pb::common::v2::{
     Spend as PbSpend, SpendCondition as PbSpendCondition, SpendEntry as PbSpendEntry,
     Witness as PbWitness, WitnessSpend as PbWitnessSpend,
 };
+use crate::pb::wallet::v1::{BundleInput as PbBundleInput, TxBundle as PbTxBundle};
 
 // =========================
 // Primitive type conversions
@@ -720,6 +721,72 @@ impl TryFrom<PbSeed> for Seed {
     }
 }
 
+impl TryFrom<PbSpend> for Spend {
+    type Error = ConversionError;
+    fn try_from(spend: PbSpend) -> Result<Self, Self::Error> {
+        match spend.spend_kind.required("Spend", "spend_kind")? {
+            spend::SpendKind::Witness(w) => {
+                let witness_pb = w.witness.required("WitnessSpend", "witness")?;
+                let pkh_signature = witness_pb
+                    .pkh_signature
+                    .required("Witness", "pkh_signature")?
+                    .try_into()?;
+                let lock_merkle_proof = witness_pb
+                    .lock_merkle_proof
+                    .required("Witness", "lock_merkle_proof")?;
+                let spend_condition = lock_merkle_proof
+                    .spend_condition
+                    .required("LockMerkleProof", "spend_condition")?
+                    .try_into()?;
+                let proof = lock_merkle_proof
+                    .proof
+                    .required("LockMerkleProof", "proof")?;
+
+                let witness = Witness {
+                    lock_merkle_proof: LockMerkleProof {
+                        spend_condition,
+                        axis: lock_merkle_proof.axis,
+                        proof: MerkleProof {
+                            root: proof.root.required("MerkleProof", "root")?.try_into()?,
+                            path: proof
+                                .path
+                                .into_iter()
+                                .map(|h| h.try_into())
+                                .collect::<Result<Vec<_>, _>>()?,
+                        },
+                    },
+                    pkh_signature,
+                    hax_map: {
+                        let mut map = iris_ztd::ZMap::new();
+                        for hax in witness_pb.hax {
+                            let hash: Digest =
+                                hax.hash.required("HaxPreimage", "hash")?.try_into()?;
+                            let noun = iris_ztd::cue(&hax.value).ok_or(
+                                ConversionError::Invalid("HaxPreimage value (invalid jam)"),
+                            )?;
+                            map.insert(hash, noun);
+                        }
+                        map
+                    },
+                    tim: (),
+                };
+
+                let seeds: Result<Vec<Seed>, ConversionError> =
+                    w.seeds.into_iter().map(|s| s.try_into()).collect();
+
+                Ok(Spend {
+                    witness,
+                    seeds: Seeds(seeds?),
+                    fee: w.fee.required("WitnessSpend", "fee")?.into(),
+                })
+            }
+            spend::SpendKind::Legacy(_) => {
+                Err(ConversionError::Invalid("Legacy spends are not supported"))
+            }
+        }
+    }
+}
+
 impl TryFrom<PbRawTransaction> for RawTx {
     type Error = ConversionError;
     fn try_from(tx: PbRawTransaction) -> Result<Self, Self::Error> {
@@ -730,67 +797,7 @@ impl TryFrom<PbRawTransaction> for RawTx {
             .into_iter()
             .map(|entry| {
                 let name = entry.name.required("SpendEntry", "name")?.try_into()?;
-                let spend_pb = entry.spend.required("SpendEntry", "spend")?;
-                let spend = match spend_pb.spend_kind.required("Spend", "spend_kind")? {
-                    spend::SpendKind::Witness(w) => {
-                        let witness_pb = w.witness.required("WitnessSpend", "witness")?;
-                        let pkh_signature = witness_pb
-                            .pkh_signature
-                            .required("Witness", "pkh_signature")?
-                            .try_into()?;
-                        let lock_merkle_proof = witness_pb
-                            .lock_merkle_proof
-                            .required("Witness", "lock_merkle_proof")?;
-                        let spend_condition = lock_merkle_proof
-                            .spend_condition
-                            .required("LockMerkleProof", "spend_condition")?
-                            .try_into()?;
-                        let proof = lock_merkle_proof
-                            .proof
-                            .required("LockMerkleProof", "proof")?;
-
-                        let witness = Witness {
-                            lock_merkle_proof: LockMerkleProof {
-                                spend_condition,
-                                axis: lock_merkle_proof.axis,
-                                proof: MerkleProof {
-                                    root: proof.root.required("MerkleProof", "root")?.try_into()?,
-                                    path: proof
-                                        .path
-                                        .into_iter()
-                                        .map(|h| h.try_into())
-                                        .collect::<Result<Vec<_>, _>>()?,
-                                },
-                            },
-                            pkh_signature,
-                            hax_map: {
-                                let mut map = iris_ztd::ZMap::new();
-                                for hax in witness_pb.hax {
-                                    let hash: Digest =
-                                        hax.hash.required("HaxPreimage", "hash")?.try_into()?;
-                                    let noun = iris_ztd::cue(&hax.value).ok_or(
-                                        ConversionError::Invalid("HaxPreimage value (invalid jam)"),
-                                    )?;
-                                    map.insert(hash, noun);
-                                }
-                                map
-                            },
-                            tim: (),
-                        };
-
-                        let seeds: Result<Vec<Seed>, ConversionError> =
-                            w.seeds.into_iter().map(|s| s.try_into()).collect();
-
-                        Spend {
-                            witness,
-                            seeds: Seeds(seeds?),
-                            fee: w.fee.required("WitnessSpend", "fee")?.into(),
-                        }
-                    }
-                    spend::SpendKind::Legacy(_) => {
-                        return Err(ConversionError::Invalid("Legacy spends are not supported"));
-                    }
-                };
+                let spend = entry.spend.required("SpendEntry", "spend")?.try_into()?;
                 Ok((name, spend))
             })
             .collect();
@@ -803,6 +810,66 @@ impl TryFrom<PbRawTransaction> for RawTx {
     }
 }
 
+// =========================
+// Transaction bundle conversions
+// =========================
+
+impl From<TxBundle> for PbTxBundle {
+    fn from(bundle: TxBundle) -> Self {
+        PbTxBundle {
+            version: TxBundle::VERSION,
+            fee_per_word: Some(PbNicks::from(bundle.fee_per_word)),
+            height: bundle.height.map(PbBlockHeight::from),
+            inputs: bundle
+                .inputs
+                .into_iter()
+                .map(|input| PbBundleInput {
+                    note: Some(PbNote::from(input.note)),
+                    spend_condition: Some(PbSpendCondition::from(input.spend_condition)),
+                    refund_lock: input.refund_lock.map(PbSpendCondition::from),
+                    spend: Some(PbSpend::from(input.spend)),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<PbTxBundle> for TxBundle {
+    type Error = ConversionError;
+    fn try_from(bundle: PbTxBundle) -> Result<Self, Self::Error> {
+        if bundle.version != TxBundle::VERSION {
+            return Err(ConversionError::UnsupportedVersion(
+                bundle.version.to_string(),
+            ));
+        }
+
+        let inputs = bundle
+            .inputs
+            .into_iter()
+            .map(|input| {
+                Ok(BundleInput {
+                    note: input.note.required("BundleInput", "note")?.try_into()?,
+                    spend_condition: input
+                        .spend_condition
+                        .required("BundleInput", "spend_condition")?
+                        .try_into()?,
+                    refund_lock: input.refund_lock.map(|v| v.try_into()).transpose()?,
+                    spend: input.spend.required("BundleInput", "spend")?.try_into()?,
+                })
+            })
+            .collect::<Result<Vec<_>, ConversionError>>()?;
+
+        Ok(TxBundle {
+            fee_per_word: bundle
+                .fee_per_word
+                .required("TxBundle", "fee_per_word")?
+                .into(),
+            height: bundle.height.map(|v| v.into()),
+            inputs,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1000,4 +1067,35 @@ mod tests {
         println!("{pb2_raw_tx:?}");
         assert_eq!(pb_raw_tx, pb2_raw_tx);
     }
+
+    #[test]
+    fn bundle_roundtrip() {
+        let pkh: Digest = "9zpwNfGdcPT1QUKw2Fnw2zvftzpAYEjzZfTqGW8KLnf3NmEJ7yR5t2Y"
+            .try_into()
+            .unwrap();
+        let spend_condition = SpendCondition::new_pkh(Pkh::single(pkh));
+        let note = Note::new(
+            Version::V1,
+            7,
+            Name::new(spend_condition.first_name(), pkh),
+            NoteData::empty(),
+            1 << 20,
+        );
+        let mut builder = TxBuilder::new(1);
+        builder
+            .simple_spend(vec![(note, spend_condition)], pkh, 1000, pkh, true)
+            .unwrap()
+            .set_height(20);
+        let bundle = builder.to_bundle();
+
+        let pb_bundle = PbTxBundle::from(bundle.clone());
+        let json = serde_json::to_string(&pb_bundle).unwrap();
+        let pb_bundle: PbTxBundle = serde_json::from_str(&json).unwrap();
+        let decoded = TxBundle::try_from(pb_bundle).unwrap();
+        assert_eq!(decoded.to_jam(), bundle.to_jam());
+
+        let mut pb_bundle = PbTxBundle::from(bundle);
+        pb_bundle.version = 2;
+        assert!(TxBundle::try_from(pb_bundle).is_err());
+    }
 }
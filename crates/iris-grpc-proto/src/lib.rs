@@ -293,6 +293,11 @@ pub mod pb {
             include!(concat!(env!("OUT_DIR"), "/nockchain.public.v2.rs"));
         }
     }
+    pub mod wallet {
+        pub mod v1 {
+            include!(concat!(env!("OUT_DIR"), "/nockchain.wallet.v1.rs"));
+        }
+    }
 
     pub const FILE_DESCRIPTOR_SET: &[u8] =
         include_bytes!(concat!(env!("OUT_DIR"), "/nockchain_descriptor.bin"));
@@ -302,3 +307,5 @@ pub mod pb {
 pub mod client;
 pub mod common;
 pub mod convert;
+#[cfg(all(feature = "mock-server", not(target_arch = "wasm32")))]
+pub mod mock;
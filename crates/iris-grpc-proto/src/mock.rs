@@ -0,0 +1,502 @@
+//! In-process implementation of `NockchainService`, for testing clients without a node.
+//!
+//! The mock keeps an in-memory set of notes and a snapshot height. Transactions are checked with
+//! [`RawTx::verify_at`] and, when valid, applied immediately in a new block.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use iris_crypto::PublicKey;
+use iris_nockchain_types::{
+    Balance, BalanceUpdate, BlockHeight, Name, Nicks, Note, Pkh, RawTx, SpendCondition, TxId,
+};
+use iris_ztd::{Digest, Hashable};
+use tokio::net::TcpListener;
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+use crate::pb::common::v1::{
+    Acknowledged, BlockHeight as PbBlockHeight, ErrorCode, ErrorStatus, PageResponse,
+};
+use crate::pb::common::v2::{Balance as PbBalance, BalanceEntry as PbBalanceEntry};
+use crate::pb::public::v2::nockchain_service_server::{NockchainService, NockchainServiceServer};
+use crate::pb::public::v2::*;
+
+/// Page size used when the client does not ask for one.
+const DEFAULT_PAGE_LIMIT: usize = 100;
+
+#[derive(Debug)]
+struct MockState {
+    height: BlockHeight,
+    block_id: Digest,
+    notes: BTreeMap<Name, Note>,
+    accepted: BTreeSet<TxId>,
+    page_limit: usize,
+    fee_per_word: Nicks,
+    mine_after_pages: usize,
+    hold_transactions: bool,
+}
+
+impl MockState {
+    fn mine_block(&mut self) {
+        self.height += 1;
+        self.block_id = (self.height, self.block_id).hash();
+    }
+}
+
+/// A `NockchainService` backed by an in-memory note set.
+///
+/// Clones share the same state, so a test can keep a handle to the mock while it is being served.
+#[derive(Debug, Clone)]
+pub struct MockNockchain {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl Default for MockNockchain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockNockchain {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(MockState {
+                height: 0,
+                block_id: 0u64.hash(),
+                notes: BTreeMap::new(),
+                accepted: BTreeSet::new(),
+                page_limit: DEFAULT_PAGE_LIMIT,
+                fee_per_word: 1 << 15,
+                mine_after_pages: 0,
+                hold_transactions: false,
+            })),
+        }
+    }
+
+    /// Maximum number of notes returned per page, regardless of the client's request.
+    pub fn with_page_limit(self, page_limit: usize) -> Self {
+        self.state().page_limit = page_limit.max(1);
+        self
+    }
+
+    /// Fee per word that submitted transactions must pay.
+    pub fn with_fee_per_word(self, fee_per_word: Nicks) -> Self {
+        self.state().fee_per_word = fee_per_word;
+        self
+    }
+
+    /// Accept valid transactions without ever including them in a block, like a node whose
+    /// mempool is stuck.
+    pub fn hold_transactions(self) -> Self {
+        self.state().hold_transactions = true;
+        self
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Add `note` to the current snapshot, without mining a block.
+    pub fn add_note(&self, note: Note) {
+        self.state().notes.insert(note.name.clone(), note);
+    }
+
+    /// Advance the snapshot by one block, returning the new height.
+    pub fn mine_block(&self) -> BlockHeight {
+        let mut state = self.state();
+        state.mine_block();
+        state.height
+    }
+
+    /// Mine a block right after each of the next `pages` balance pages is served, so that the
+    /// snapshot changes while a client is paging.
+    pub fn mine_after_pages(&self, pages: usize) {
+        self.state().mine_after_pages = pages;
+    }
+
+    pub fn height(&self) -> BlockHeight {
+        self.state().height
+    }
+
+    pub fn block_id(&self) -> Digest {
+        self.state().block_id
+    }
+
+    /// The current snapshot, as a client would see it.
+    pub fn balance(&self) -> BalanceUpdate {
+        let state = self.state();
+        BalanceUpdate {
+            height: state.height,
+            block_id: state.block_id,
+            notes: Balance(
+                state
+                    .notes
+                    .iter()
+                    .map(|(name, note)| (name.clone(), note.clone()))
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn is_accepted(&self, id: &TxId) -> bool {
+        self.state().accepted.contains(id)
+    }
+
+    /// Validate `tx` against the current notes, and apply it in a new block.
+    ///
+    /// Timelocks are checked against the height of that new block.
+    /// Spent notes are removed and outputs are added with the new height as their origin page.
+    /// Resubmitting an accepted transaction is a no-op.
+    pub fn apply_tx(&self, tx: &RawTx) -> Result<(), String> {
+        let mut state = self.state();
+        if state.accepted.contains(&tx.id) {
+            return Ok(());
+        }
+
+        let report = tx.verify_at(&state.notes, state.fee_per_word, state.height + 1);
+        if !report.is_valid() {
+            let errors = report
+                .errors
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>();
+            return Err(errors.join("; "));
+        }
+        if state.hold_transactions {
+            state.accepted.insert(tx.id);
+            return Ok(());
+        }
+
+        state.mine_block();
+        for (name, _) in &tx.spends.0 {
+            state.notes.remove(name);
+        }
+        for mut note in tx.outputs() {
+            note.origin_page = state.height;
+            state.notes.insert(note.name.clone(), note);
+        }
+        state.accepted.insert(tx.id);
+
+        Ok(())
+    }
+
+    pub fn into_service(self) -> NockchainServiceServer<Self> {
+        NockchainServiceServer::new(self)
+    }
+
+    /// Serve the mock on a local port in the background, returning its URL.
+    ///
+    /// Must be called from within a tokio runtime. The server stops when the runtime shuts down.
+    pub async fn serve(self) -> std::io::Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let incoming = TcpIncoming::from_listener(listener, true, None)
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+        tokio::spawn(
+            Server::builder()
+                .add_service(self.into_service())
+                .serve_with_incoming(incoming),
+        );
+
+        Ok(format!("http://{addr}"))
+    }
+
+    fn get_balance(&self, request: WalletGetBalanceRequest) -> Result<PbBalance, ErrorStatus> {
+        let first_name = match request.selector {
+            Some(wallet_get_balance_request::Selector::Address(address)) => {
+                let bytes = bs58::decode(&address.key)
+                    .into_vec()
+                    .ok()
+                    .filter(|b| b.len() == 97)
+                    .ok_or_else(|| invalid_request("Invalid address"))?;
+                let pkh = PublicKey::from_be_bytes(&bytes).hash();
+                SpendCondition::new_pkh(Pkh::single(pkh)).first_name()
+            }
+            Some(wallet_get_balance_request::Selector::FirstName(first_name)) => {
+                Digest::try_from(first_name.hash.as_str())
+                    .map_err(|_| invalid_request("Invalid first name"))?
+            }
+            None => return Err(invalid_request("Missing selector")),
+        };
+
+        let page = request.page.unwrap_or_default();
+        let after = parse_page_token(&page.page_token)?;
+
+        let mut state = self.state();
+        let limit = match page.client_page_items_limit as usize {
+            0 => state.page_limit,
+            n => n.min(state.page_limit),
+        };
+
+        let mut matching = state
+            .notes
+            .iter()
+            .filter(|(name, _)| name.first == first_name)
+            .filter(|(name, _)| after.as_ref().is_none_or(|after| *name > after));
+        let notes = matching.by_ref().take(limit).collect::<Vec<_>>();
+        let next_page_token = match (matching.next(), notes.last()) {
+            (Some(_), Some((name, _))) => format!("{}.{}", name.first, name.last),
+            _ => String::new(),
+        };
+
+        let balance = PbBalance {
+            notes: notes
+                .into_iter()
+                .map(|(name, note)| PbBalanceEntry {
+                    name: Some(name.into()),
+                    note: Some(note.clone().into()),
+                })
+                .collect(),
+            height: Some(PbBlockHeight {
+                value: state.height,
+            }),
+            block_id: Some(state.block_id.into()),
+            page: Some(PageResponse { next_page_token }),
+        };
+
+        if state.mine_after_pages > 0 {
+            state.mine_after_pages -= 1;
+            state.mine_block();
+        }
+
+        Ok(balance)
+    }
+
+    fn send_transaction(&self, request: WalletSendTransactionRequest) -> Result<(), ErrorStatus> {
+        let raw_tx = request
+            .raw_tx
+            .ok_or_else(|| invalid_request("Missing raw_tx"))?;
+        let raw_tx = RawTx::try_from(raw_tx).map_err(|e| invalid_request(e.to_string()))?;
+
+        let tx_id = request
+            .tx_id
+            .map(Digest::try_from)
+            .transpose()
+            .map_err(|e| invalid_request(e.to_string()))?;
+        if tx_id.is_some_and(|id| id != raw_tx.id) {
+            return Err(invalid_request("tx_id does not match raw_tx"));
+        }
+
+        self.apply_tx(&raw_tx).map_err(invalid_request)
+    }
+}
+
+fn invalid_request(message: impl Into<String>) -> ErrorStatus {
+    ErrorStatus {
+        code: ErrorCode::InvalidRequest as i32,
+        message: message.into(),
+        details: None,
+    }
+}
+
+/// Page tokens are the name of the last note returned, as `first.last`.
+fn parse_page_token(token: &str) -> Result<Option<Name>, ErrorStatus> {
+    if token.is_empty() {
+        return Ok(None);
+    }
+    let (first, last) = token
+        .split_once('.')
+        .and_then(|(f, l)| Some((Digest::try_from(f).ok()?, Digest::try_from(l).ok()?)))
+        .ok_or_else(|| invalid_request("Invalid page token"))?;
+    Ok(Some(Name::new(first, last)))
+}
+
+#[tonic::async_trait]
+impl NockchainService for MockNockchain {
+    async fn wallet_get_balance(
+        &self,
+        request: Request<WalletGetBalanceRequest>,
+    ) -> Result<Response<WalletGetBalanceResponse>, Status> {
+        let result = match self.get_balance(request.into_inner()) {
+            Ok(balance) => wallet_get_balance_response::Result::Balance(balance),
+            Err(err) => wallet_get_balance_response::Result::Error(err),
+        };
+        Ok(Response::new(WalletGetBalanceResponse {
+            result: Some(result),
+        }))
+    }
+
+    async fn wallet_send_transaction(
+        &self,
+        request: Request<WalletSendTransactionRequest>,
+    ) -> Result<Response<WalletSendTransactionResponse>, Status> {
+        let result = match self.send_transaction(request.into_inner()) {
+            Ok(()) => wallet_send_transaction_response::Result::Ack(Acknowledged {}),
+            Err(err) => wallet_send_transaction_response::Result::Error(err),
+        };
+        Ok(Response::new(WalletSendTransactionResponse {
+            result: Some(result),
+        }))
+    }
+
+    async fn transaction_accepted(
+        &self,
+        request: Request<TransactionAcceptedRequest>,
+    ) -> Result<Response<TransactionAcceptedResponse>, Status> {
+        let result = match request
+            .into_inner()
+            .tx_id
+            .and_then(|id| Digest::try_from(id.hash.as_str()).ok())
+        {
+            Some(id) => transaction_accepted_response::Result::Accepted(self.is_accepted(&id)),
+            None => transaction_accepted_response::Result::Error(invalid_request("Invalid tx_id")),
+        };
+        Ok(Response::new(TransactionAcceptedResponse {
+            result: Some(result),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{BalanceRequest, ClientError, PublicNockchainGrpcClient};
+    use crate::pb::common::v1::Base58Hash;
+    use bip39::Mnemonic;
+    use iris_crypto::derive_master_key;
+    use iris_nockchain_types::{
+        LockPrimitive, LockTim, NoteData, TimelockRange, TxBuilder, Version,
+    };
+
+    #[tokio::test]
+    async fn test_mock_server() {
+        let mnemonic = Mnemonic::parse("dice domain inspire horse time initial monitor nature mass impose tone benefit vibrant dash kiss mosquito rice then color ribbon agent method drop fat").unwrap();
+        let private_key = derive_master_key(&mnemonic.to_seed(""))
+            .private_key
+            .unwrap();
+        let public_key = private_key.public_key();
+        let pkh = public_key.hash();
+        let spend_condition = SpendCondition::new_pkh(Pkh::single(pkh));
+
+        let mock = MockNockchain::new()
+            .with_page_limit(2)
+            .with_fee_per_word(1 << 10);
+        for i in 0..5u64 {
+            mock.add_note(Note::new(
+                Version::V1,
+                0,
+                Name::new(spend_condition.first_name(), i.hash()),
+                NoteData::empty(),
+                (i + 1) * 100_000,
+            ));
+        }
+        mock.mine_block();
+
+        let url = mock.clone().serve().await.unwrap();
+        let mut client = PublicNockchainGrpcClient::connect(url).await.unwrap();
+
+        // Five notes are fetched over three pages.
+        let address = bs58::encode(public_key.to_be_bytes()).into_string();
+        let balance = client
+            .wallet_get_balance(&BalanceRequest::Address(address.clone()))
+            .await
+            .unwrap();
+        assert_eq!(balance.notes.0.len(), 5);
+        assert_eq!(balance.height, 1);
+        assert_eq!(balance.block_id, mock.block_id());
+
+        let by_first_name = client
+            .wallet_get_balance(&BalanceRequest::FirstName(
+                spend_condition.first_name().to_string(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(by_first_name.notes.0.len(), 5);
+
+        let recipient = "2nEFkqYm51yfqsYgfRx72w8FF9bmWqnkJu8XqY8T7psXufjYNRxf5ME"
+            .try_into()
+            .unwrap();
+        let notes = balance
+            .notes
+            .0
+            .iter()
+            .map(|(_, n)| (n.clone(), spend_condition.clone()))
+            .collect();
+        let mut builder = TxBuilder::new(1 << 10);
+        let tx = builder
+            .simple_spend(notes, recipient, 150_000, pkh, false)
+            .unwrap()
+            .sign(&private_key)
+            .build()
+            .to_raw_tx();
+
+        let tx_id = Base58Hash {
+            hash: tx.id.to_string(),
+        };
+        assert!(!client.transaction_accepted(tx_id.clone()).await.unwrap());
+        client.wallet_send_transaction(&tx).await.unwrap();
+        assert!(client.transaction_accepted(tx_id).await.unwrap());
+
+        let after = client
+            .wallet_get_balance(&BalanceRequest::Address(address))
+            .await
+            .unwrap();
+        assert_eq!(after.height, 2);
+        let spent = tx.spends.0.iter().map(|(n, _)| n).collect::<Vec<_>>();
+        assert!(after.notes.0.iter().all(|(n, _)| !spent.contains(&n)));
+        let change = tx
+            .outputs()
+            .into_iter()
+            .find(|n| n.name.first == spend_condition.first_name())
+            .unwrap();
+        assert!(after
+            .notes
+            .0
+            .iter()
+            .any(|(n, note)| *n == change.name && note.origin_page == 2));
+
+        // The spent notes are gone, so the same spends cannot be submitted again.
+        let mut replay = tx.clone();
+        replay.spends.0[0].1.fee += 1;
+        replay.id = replay.calc_id();
+        assert!(matches!(
+            client.wallet_send_transaction(&replay).await,
+            Err(ClientError::ServerError(_))
+        ));
+    }
+
+    #[test]
+    fn test_mock_timelock() {
+        let mnemonic = Mnemonic::parse("dice domain inspire horse time initial monitor nature mass impose tone benefit vibrant dash kiss mosquito rice then color ribbon agent method drop fat").unwrap();
+        let private_key = derive_master_key(&mnemonic.to_seed(""))
+            .private_key
+            .unwrap();
+        let pkh = private_key.public_key().hash();
+        let spend_condition = SpendCondition(vec![
+            LockPrimitive::Pkh(Pkh::single(pkh)),
+            LockPrimitive::Tim(LockTim {
+                rel: TimelockRange::new(Some(3), None),
+                abs: TimelockRange::none(),
+            }),
+        ]);
+        let note = Note::new(
+            Version::V1,
+            0,
+            Name::new(spend_condition.first_name(), 0u64.hash()),
+            NoteData::empty(),
+            1 << 24,
+        );
+        let tx = TxBuilder::new(1 << 10)
+            .simple_spend(vec![(note.clone(), spend_condition)], pkh, 1000, pkh, false)
+            .unwrap()
+            .sign(&private_key)
+            .build()
+            .to_raw_tx();
+
+        let mock = MockNockchain::new().with_fee_per_word(1 << 10);
+        mock.add_note(note);
+
+        // The transaction would land in block 1, before the note unlocks at 3.
+        let err = mock.apply_tx(&tx).unwrap_err();
+        assert!(err.contains("Tim"), "{err}");
+        assert!(!mock.is_accepted(&tx.id));
+
+        mock.mine_block();
+        mock.mine_block();
+        mock.apply_tx(&tx).unwrap();
+        assert_eq!(mock.height(), 3);
+    }
+}
@@ -2,15 +2,21 @@ use alloc::collections::btree_map::BTreeMap;
 use alloc::collections::btree_set::BTreeSet;
 use alloc::vec;
 use alloc::vec::Vec;
-use iris_crypto::PrivateKey;
-use iris_ztd::{noun_deserialize, noun_serialize, Digest, Hashable as HashableTrait, Noun};
+use iris_crypto::{PrivateKey, PublicKey, Signature, Signer};
+use iris_ztd::{
+    noun_deserialize, noun_serialize, Digest, Hashable as HashableTrait, Noun, NounEncode,
+};
 use serde::{Deserialize, Serialize};
 
+use super::bundle::{BundleInput, TxBundle};
 use super::note::Note;
+use super::selection::{CoinSelector, SelectionCandidate, SelectionTarget};
 use super::tx::{
-    LockRoot, NockchainTx, Seed, Seeds, Spend, SpendCondition, Spends, TransactionDisplay, Witness,
+    noun_words, LockMerkleProof, LockRoot, LockTree, NockchainTx, Seed, Seeds, Spend,
+    SpendCondition, Spends, TransactionDisplay, Witness,
 };
-use super::{Name, NoteData, Version};
+use super::verify::VerifyError;
+use super::{BlockHeight, Name, NoteData, Version};
 use crate::{Nicks, Pkh, RawTx};
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -23,6 +29,82 @@ pub enum MissingUnlocks {
         preimages_for: BTreeSet<Digest>,
     },
     Brn,
+    Tim {
+        spendable_from: Option<BlockHeight>,
+        spendable_until: Option<BlockHeight>,
+    },
+}
+
+impl SpendCondition {
+    /// Unlocks that `witness` still lacks in order to satisfy this spend condition.
+    pub fn missing_unlocks(&self, witness: &Witness) -> Vec<MissingUnlocks> {
+        let mut missing_unlocks = vec![];
+
+        for p in self.pkh() {
+            let mut checked_pkh = BTreeSet::new();
+            let valid_pkh = p.hashes.iter().cloned().collect::<BTreeSet<_>>();
+
+            if p.m > 0 {
+                for (pkh, _, _) in &witness.pkh_signature.0 {
+                    if !checked_pkh.contains(pkh) && valid_pkh.contains(pkh) {
+                        checked_pkh.insert(*pkh);
+                        if checked_pkh.len() as u64 >= p.m {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            if (checked_pkh.len() as u64) < p.m {
+                let sig_of = &valid_pkh ^ &checked_pkh;
+                missing_unlocks.push(MissingUnlocks::Pkh {
+                    num_sigs: p.m - checked_pkh.len() as u64,
+                    sig_of,
+                })
+            }
+        }
+
+        for h in self.hax() {
+            let valid_hax = h.0.iter().cloned().collect::<BTreeSet<_>>();
+
+            let current_hax = witness
+                .hax_map
+                .clone()
+                .into_iter()
+                .map(|v| v.0)
+                .collect::<BTreeSet<_>>();
+
+            let checked_hax = &current_hax & &valid_hax;
+
+            let preimages_for = &valid_hax ^ &checked_hax;
+            if !preimages_for.is_empty() {
+                missing_unlocks.push(MissingUnlocks::Hax { preimages_for });
+            }
+        }
+
+        if self.brn() {
+            missing_unlocks.push(MissingUnlocks::Brn);
+        }
+
+        missing_unlocks
+    }
+
+    /// Timelock unlock that is missing for a note created at `origin_page`, if spent at `height`.
+    pub fn missing_timelock(
+        &self,
+        origin_page: BlockHeight,
+        height: BlockHeight,
+    ) -> Option<MissingUnlocks> {
+        let range = self.timelock_range(origin_page);
+        if range.contains(height) {
+            None
+        } else {
+            Some(MissingUnlocks::Tim {
+                spendable_from: range.min,
+                spendable_until: range.max,
+            })
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -63,13 +145,35 @@ impl SpendBuilder {
         }
     }
 
+    /// Create a spend of a note locked to a lock tree, unlocking the branch proven by `proof`.
+    ///
+    /// Returns `None` if the proof is invalid. See [`LockTree::prove`], and its caveat about
+    /// trees with more than one branch.
+    pub fn with_proof(
+        note: Note,
+        proof: LockMerkleProof,
+        refund_lock: Option<SpendCondition>,
+    ) -> Option<Self> {
+        if !proof.verify() {
+            return None;
+        }
+
+        Some(Self {
+            note,
+            spend_condition: proof.spend_condition.clone(),
+            spend: Spend::new(Witness::from_proof(proof), Seeds(Default::default()), 0),
+            refund_lock,
+        })
+    }
+
     pub fn from_spend(
         spend: Spend,
         note: Note,
         spend_condition: SpendCondition,
         refund_lock: Option<SpendCondition>,
     ) -> Option<Self> {
-        if spend.witness.lock_merkle_proof.proof.root != spend_condition.hash() {
+        let proof = &spend.witness.lock_merkle_proof;
+        if !proof.verify() || proof.spend_condition.hash() != spend_condition.hash() {
             return None;
         }
 
@@ -127,18 +231,25 @@ impl SpendBuilder {
     }
 
     pub fn build_seed(&self, lock: SpendCondition, gift: Nicks, include_lock_data: bool) -> Seed {
-        let lock_root = LockRoot::Lock(lock.clone());
         let mut note_data = NoteData::empty();
         if include_lock_data {
-            note_data.push_lock(lock);
+            note_data.push_lock(lock.clone());
         }
-        let parent_hash = self.note.hash();
+        self.build_seed_with_data(LockRoot::Lock(lock), gift, note_data)
+    }
+
+    pub fn build_seed_with_data(
+        &self,
+        lock_root: LockRoot,
+        gift: Nicks,
+        note_data: NoteData,
+    ) -> Seed {
         Seed {
             output_source: None,
             lock_root,
             note_data,
             gift,
-            parent_hash,
+            parent_hash: self.note.hash(),
         }
     }
 
@@ -154,57 +265,12 @@ impl SpendBuilder {
     }
 
     pub fn missing_unlocks(&self) -> Vec<MissingUnlocks> {
-        let mut missing_unlocks = vec![];
-
-        for p in self.spend_condition.pkh() {
-            let mut checked_pkh = BTreeSet::new();
-            let valid_pkh = p.hashes.iter().cloned().collect::<BTreeSet<_>>();
-
-            if p.m > 0 {
-                for (pkh, _, _) in &self.spend.witness.pkh_signature.0 {
-                    if !checked_pkh.contains(pkh) && valid_pkh.contains(pkh) {
-                        checked_pkh.insert(*pkh);
-                        if checked_pkh.len() as u64 >= p.m {
-                            break;
-                        }
-                    }
-                }
-            }
-
-            if (checked_pkh.len() as u64) < p.m {
-                let sig_of = &valid_pkh ^ &checked_pkh;
-                missing_unlocks.push(MissingUnlocks::Pkh {
-                    num_sigs: p.m - checked_pkh.len() as u64,
-                    sig_of,
-                })
-            }
-        }
-
-        for h in self.spend_condition.hax() {
-            let valid_hax = h.0.iter().cloned().collect::<BTreeSet<_>>();
-
-            let current_hax = self
-                .spend
-                .witness
-                .hax_map
-                .clone()
-                .into_iter()
-                .map(|v| v.0)
-                .collect::<BTreeSet<_>>();
-
-            let checked_hax = &current_hax & &valid_hax;
-
-            let preimages_for = &valid_hax ^ &checked_hax;
-            if !preimages_for.is_empty() {
-                missing_unlocks.push(MissingUnlocks::Hax { preimages_for });
-            }
-        }
-
-        if self.spend_condition.brn() {
-            missing_unlocks.push(MissingUnlocks::Brn);
-        }
+        self.spend_condition.missing_unlocks(&self.spend.witness)
+    }
 
-        missing_unlocks
+    pub fn missing_timelock(&self, height: BlockHeight) -> Option<MissingUnlocks> {
+        self.spend_condition
+            .missing_timelock(self.note.origin_page, height)
     }
 
     pub fn add_preimage(&mut self, preimage: Noun) -> Option<Digest> {
@@ -236,6 +302,43 @@ impl SpendBuilder {
         false
     }
 
+    /// Whether a signature by `pkh` would still count towards one of the `Pkh` locks.
+    pub fn needs_signature_from(&self, pkh: &Digest) -> bool {
+        self.missing_unlocks().iter().any(|u| match u {
+            MissingUnlocks::Pkh { sig_of, .. } => sig_of.contains(pkh),
+            _ => false,
+        })
+    }
+
+    /// Sign with any [`Signer`], if a signature by its key is still needed.
+    ///
+    /// A signature that does not verify against the signer's public key is an error, rather than
+    /// being silently dropped.
+    pub async fn sign_with<S: Signer>(&mut self, signer: &S) -> Result<bool, SignError<S::Error>> {
+        let public_key = signer.public_key();
+        if !self.needs_signature_from(&public_key.hash()) {
+            return Ok(false);
+        }
+        let sig_hash = self.spend.sig_hash();
+        let signature = signer.sign(&sig_hash).await.map_err(SignError::Signer)?;
+        if !public_key.verify(&sig_hash, &signature) {
+            return Err(SignError::InvalidSignature(public_key.hash()));
+        }
+        self.spend.add_signature(public_key, signature);
+        Ok(true)
+    }
+
+    /// Add a signature produced elsewhere, if it is valid and still needed.
+    pub fn add_signature(&mut self, public_key: PublicKey, signature: Signature) -> bool {
+        if !self.needs_signature_from(&public_key.hash())
+            || !public_key.verify(&self.spend.sig_hash(), &signature)
+        {
+            return false;
+        }
+        self.spend.add_signature(public_key, signature);
+        true
+    }
+
     fn unclamped_fee(&self, fee_per_word: Nicks) -> Nicks {
         let mut fee = self.spend.unclamped_fee(fee_per_word);
 
@@ -255,11 +358,70 @@ impl SpendBuilder {
     }
 }
 
+/// A digest that still needs to be signed by one of `pkhs`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureRequest {
+    pub name: Name,
+    pub sig_hash: Digest,
+    pub pkhs: BTreeSet<Digest>,
+}
+
+/// A single payout of a batch spend.
+#[derive(Debug, Clone)]
+pub struct Payment {
+    pub lock: LockRoot,
+    pub amount: Nicks,
+    /// Note data of the output. If not set, `include_lock_data` decides whether the lock gets
+    /// included.
+    pub note_data: Option<NoteData>,
+}
+
+impl Payment {
+    pub fn new(lock: SpendCondition, amount: Nicks) -> Self {
+        Self {
+            lock: LockRoot::Lock(lock),
+            amount,
+            note_data: None,
+        }
+    }
+
+    /// Pay to a lock tree. Lock data is never included for these outputs.
+    ///
+    /// See [`LockTree`] before paying to a tree with more than one branch.
+    pub fn tree(tree: &LockTree, amount: Nicks) -> Self {
+        Self {
+            lock: LockRoot::Hash(tree.root()),
+            amount,
+            note_data: None,
+        }
+    }
+
+    pub fn pkh(recipient: Digest, amount: Nicks) -> Self {
+        Self::new(SpendCondition::new_pkh(Pkh::single(recipient)), amount)
+    }
+
+    fn note_data(&self, include_lock_data: bool) -> NoteData {
+        match &self.note_data {
+            Some(note_data) => note_data.clone(),
+            None => match &self.lock {
+                LockRoot::Lock(lock) if include_lock_data => {
+                    let mut note_data = NoteData::empty();
+                    note_data.push_lock(lock.clone());
+                    note_data
+                }
+                _ => NoteData::empty(),
+            },
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct TxBuilder {
     spends: BTreeMap<Name, SpendBuilder>,
     fee_pool: Vec<SpendBuilder>,
     fee_per_word: Nicks,
+    #[serde(default)]
+    height: Option<BlockHeight>,
 }
 
 impl TxBuilder {
@@ -269,9 +431,19 @@ impl TxBuilder {
             spends: BTreeMap::new(),
             fee_pool: vec![],
             fee_per_word,
+            height: None,
         }
     }
 
+    /// Set the block height the transaction is expected to be included at.
+    ///
+    /// Once set, timelocks are evaluated: `simple_spend` skips notes that are not spendable at
+    /// this height, and `validate` rejects spends whose timelocks are not satisfied.
+    pub fn set_height(&mut self, height: BlockHeight) -> &mut Self {
+        self.height = Some(height);
+        self
+    }
+
     pub fn from_tx(
         tx: RawTx,
         mut notes: BTreeMap<Name, (Note, SpendCondition)>,
@@ -298,6 +470,7 @@ impl TxBuilder {
                 .collect::<Result<BTreeMap<_, _>, _>>()?,
             fee_pool: vec![],
             fee_per_word: 1 << 15,
+            height: None,
         })
     }
 
@@ -315,44 +488,94 @@ impl TxBuilder {
         refund_pkh: Digest,
         include_lock_data: bool,
     ) -> Result<&mut Self, BuildError> {
-        if gift == 0 {
+        self.batch_spend_base(
+            notes,
+            vec![Payment::pkh(recipient, gift)],
+            refund_pkh,
+            include_lock_data,
+        )
+    }
+
+    /// Pay out to multiple recipients, without computing the fee.
+    ///
+    /// Notes are spent greedily in order, and payments are filled in order. A payment may be split
+    /// across several notes, in which case its seeds are merged into one output note. Each spend
+    /// keeps a single refund seed, and notes that are not needed go to the fee pool.
+    pub fn batch_spend_base(
+        &mut self,
+        notes: Vec<(Note, SpendCondition)>,
+        payments: Vec<Payment>,
+        refund_pkh: Digest,
+        include_lock_data: bool,
+    ) -> Result<&mut Self, BuildError> {
+        if payments.is_empty() || payments.iter().any(|p| p.amount == 0) {
             return Err(BuildError::ZeroGift);
         }
 
         let refund_lock = SpendCondition::new_pkh(Pkh::single(refund_pkh));
 
-        let mut remaining_gift = gift;
+        let mut payments = payments.into_iter();
+        let mut cur = payments.next();
+        let mut remaining = cur.as_ref().map(|p| p.amount).unwrap_or(0);
 
         for (note, spend_condition) in notes {
-            let gift_portion = remaining_gift.min(note.assets);
+            if !self.is_spendable(&note, &spend_condition) {
+                continue;
+            }
 
-            remaining_gift -= gift_portion;
+            let mut available = note.assets;
+            let mut paid = false;
 
             let mut spend = SpendBuilder::new(note, spend_condition, Some(refund_lock.clone()));
-            if gift_portion > 0 {
-                let seed = spend.build_seed(
-                    SpendCondition::new_pkh(Pkh::single(recipient)),
-                    gift_portion,
-                    include_lock_data,
-                );
+            while let Some(payment) = cur.as_ref() {
+                let portion = remaining.min(available);
+                if portion == 0 {
+                    break;
+                }
+                available -= portion;
+                remaining -= portion;
+                paid = true;
+
+                let note_data = payment.note_data(include_lock_data);
+                let seed = spend.build_seed_with_data(payment.lock.clone(), portion, note_data);
                 spend.seed(seed);
-                spend.compute_refund(include_lock_data);
-                assert!(spend.is_balanced());
+
+                if remaining == 0 {
+                    cur = payments.next();
+                    remaining = cur.as_ref().map(|p| p.amount).unwrap_or(0);
+                }
+            }
+
+            spend.compute_refund(include_lock_data);
+            assert!(spend.is_balanced());
+            if paid {
                 self.spend(spend);
             } else {
-                spend.compute_refund(include_lock_data);
-                assert!(spend.is_balanced());
                 self.fee_pool.push(spend);
             }
         }
 
-        if remaining_gift > 0 {
+        if cur.is_some() {
             return Err(BuildError::InsufficientFunds);
         }
 
         Ok(self)
     }
 
+    /// Pay out to multiple recipients, and set the fee.
+    pub fn batch_spend(
+        &mut self,
+        notes: Vec<(Note, SpendCondition)>,
+        payments: Vec<Payment>,
+        refund_pkh: Digest,
+        include_lock_data: bool,
+    ) -> Result<&mut Self, BuildError> {
+        self.batch_spend_base(notes, payments, refund_pkh, include_lock_data)?
+            .recalc_and_set_fee(include_lock_data)?;
+
+        Ok(self)
+    }
+
     pub fn simple_spend(
         &mut self,
         notes: Vec<(Note, SpendCondition)>,
@@ -367,6 +590,113 @@ impl TxBuilder {
         Ok(self)
     }
 
+    /// Perform a simple spend, letting `selector` pick which of `notes` to spend.
+    pub fn simple_spend_with(
+        &mut self,
+        selector: &impl CoinSelector,
+        notes: Vec<(Note, SpendCondition)>,
+        recipient: Digest,
+        gift: Nicks,
+        refund_pkh: Digest,
+        include_lock_data: bool,
+    ) -> Result<&mut Self, BuildError> {
+        self.batch_spend_with(
+            selector,
+            notes,
+            vec![Payment::pkh(recipient, gift)],
+            refund_pkh,
+            include_lock_data,
+        )
+    }
+
+    /// Perform a batch spend, letting `selector` pick which of `notes` to spend.
+    ///
+    /// Notes that are not selected are kept in the fee pool, in case the fee estimate falls short.
+    /// If the selected notes leave less over than a refund seed would cost, the excess is paid as
+    /// fee instead of creating dust.
+    pub fn batch_spend_with(
+        &mut self,
+        selector: &impl CoinSelector,
+        notes: Vec<(Note, SpendCondition)>,
+        payments: Vec<Payment>,
+        refund_pkh: Digest,
+        include_lock_data: bool,
+    ) -> Result<&mut Self, BuildError> {
+        if payments.is_empty() || payments.iter().any(|p| p.amount == 0) {
+            return Err(BuildError::ZeroGift);
+        }
+
+        let gift = payments
+            .iter()
+            .map(|p| p.amount)
+            .fold(0, Nicks::saturating_add);
+        let refund_lock = SpendCondition::new_pkh(Pkh::single(refund_pkh));
+
+        let candidates = notes
+            .into_iter()
+            .filter(|(note, sc)| self.is_spendable(note, sc))
+            .map(|(note, spend_condition)| {
+                let mut spend = SpendBuilder::new(note.clone(), spend_condition.clone(), None);
+                let seed = spend.build_seed_with_data(
+                    payments[0].lock.clone(),
+                    note.assets,
+                    payments[0].note_data(include_lock_data),
+                );
+                spend.seed(seed);
+                SelectionCandidate {
+                    fee: spend.unclamped_fee(self.fee_per_word),
+                    note,
+                    spend_condition,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut change_data = NoteData::empty();
+        if include_lock_data {
+            change_data.push_lock(refund_lock);
+        }
+        let change_fee = noun_words(&change_data.to_noun()) * self.fee_per_word;
+
+        let selected = selector
+            .select(&candidates, &SelectionTarget { gift, change_fee })
+            .ok_or(BuildError::InsufficientFunds)?;
+
+        // Selected notes go first, so that they get spent. The rest end up in the fee pool.
+        let mut candidates = candidates.into_iter().map(Some).collect::<Vec<_>>();
+        let mut ordered = selected
+            .iter()
+            .filter_map(|&i| candidates.get_mut(i)?.take())
+            .collect::<Vec<_>>();
+        ordered.extend(candidates.into_iter().flatten());
+        let notes = ordered
+            .into_iter()
+            .map(|c| (c.note, c.spend_condition))
+            .collect();
+
+        self.batch_spend(notes, payments, refund_pkh, include_lock_data)?;
+
+        let refund = self
+            .spends
+            .values()
+            .filter_map(|s| s.cur_refund())
+            .map(|s| s.gift)
+            .sum::<Nicks>();
+        if refund > 0 && refund <= change_fee {
+            let fee = self.cur_fee() + refund;
+            self.set_fee_and_balance_refund(fee, false, include_lock_data)?;
+        }
+
+        Ok(self)
+    }
+
+    fn is_spendable(&self, note: &Note, spend_condition: &SpendCondition) -> bool {
+        self.height.is_none_or(|height| {
+            spend_condition
+                .timelock_range(note.origin_page)
+                .contains(height)
+        })
+    }
+
     pub fn add_preimage(&mut self, preimage: Noun) -> Option<Digest> {
         let mut ret = None;
         for (_, s) in self.spends.iter_mut() {
@@ -385,6 +715,53 @@ impl TxBuilder {
         self
     }
 
+    /// Sign every spend that still needs a signature from `signer`'s key.
+    pub async fn sign_with<S: Signer>(
+        &mut self,
+        signer: &S,
+    ) -> Result<&mut Self, SignError<S::Error>> {
+        for spend in self.spends.values_mut() {
+            spend.sign_with(signer).await?;
+        }
+        Ok(self)
+    }
+
+    /// Digests that still need signatures, and the keys that may provide them.
+    pub fn signature_requests(&self) -> Vec<SignatureRequest> {
+        self.spends
+            .iter()
+            .filter_map(|(name, spend)| {
+                let pkhs = spend
+                    .missing_unlocks()
+                    .into_iter()
+                    .flat_map(|u| match u {
+                        MissingUnlocks::Pkh { sig_of, .. } => sig_of,
+                        _ => BTreeSet::new(),
+                    })
+                    .collect::<BTreeSet<_>>();
+                (!pkhs.is_empty()).then(|| SignatureRequest {
+                    name: name.clone(),
+                    sig_hash: spend.spend.sig_hash(),
+                    pkhs,
+                })
+            })
+            .collect()
+    }
+
+    /// Add a signature produced elsewhere to the spend of `name`.
+    ///
+    /// Returns `false` if the spend does not exist, or the signature is invalid or not needed.
+    pub fn add_signature(
+        &mut self,
+        name: &Name,
+        public_key: PublicKey,
+        signature: Signature,
+    ) -> bool {
+        self.spends
+            .get_mut(name)
+            .is_some_and(|spend| spend.add_signature(public_key, signature))
+    }
+
     pub fn validate(&mut self) -> Result<&mut Self, BuildError> {
         let cur_fee = self.cur_fee();
         let needed_fee = self.calc_fee();
@@ -399,7 +776,13 @@ impl TxBuilder {
         let unlocks = self
             .spends
             .values()
-            .flat_map(|v| v.missing_unlocks())
+            .flat_map(|v| {
+                let mut unlocks = v.missing_unlocks();
+                if let Some(height) = self.height {
+                    unlocks.extend(v.missing_timelock(height));
+                }
+                unlocks
+            })
             .collect::<Vec<_>>();
         if !unlocks.is_empty() {
             return Err(BuildError::MissingUnlocks(unlocks));
@@ -437,6 +820,45 @@ impl TxBuilder {
         }
     }
 
+    /// Export the spends of this builder into a portable bundle.
+    ///
+    /// Notes in the fee pool are not part of the transaction, and are left out.
+    pub fn to_bundle(&self) -> TxBundle {
+        TxBundle {
+            fee_per_word: self.fee_per_word,
+            height: self.height,
+            inputs: self
+                .spends
+                .values()
+                .map(|s| BundleInput {
+                    note: s.note.clone(),
+                    spend_condition: s.spend_condition.clone(),
+                    refund_lock: s.refund_lock.clone(),
+                    spend: s.spend.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn from_bundle(bundle: TxBundle) -> Result<Self, BuildError> {
+        Ok(Self {
+            spends: bundle
+                .inputs
+                .into_iter()
+                .map(|i| {
+                    let name = i.note.name.clone();
+                    let spend =
+                        SpendBuilder::from_spend(i.spend, i.note, i.spend_condition, i.refund_lock)
+                            .ok_or(BuildError::InvalidSpendCondition)?;
+                    Ok((name, spend))
+                })
+                .collect::<Result<BTreeMap<_, _>, _>>()?,
+            fee_pool: vec![],
+            fee_per_word: bundle.fee_per_word,
+            height: bundle.height,
+        })
+    }
+
     pub fn all_notes(&self) -> BTreeMap<Name, (Note, SpendCondition)> {
         self.spends
             .iter()
@@ -615,11 +1037,15 @@ pub enum BuildError {
     InsufficientFunds,
     AccountingMismatch,
     NoteNotFound(Name),
+    NoteReserved(Name),
     InvalidFee(Nicks, Nicks),
     InvalidVersion,
     InvalidSpendCondition,
     UnbalancedSpends,
     MissingUnlocks(Vec<MissingUnlocks>),
+    BundleMismatch,
+    SpendMismatch(Name),
+    Verify(Vec<VerifyError>),
 }
 
 impl core::fmt::Display for BuildError {
@@ -633,6 +1059,11 @@ impl core::fmt::Display for BuildError {
             BuildError::NoteNotFound(name) => {
                 write!(f, "Unable to find note [{} {}]", name.first, name.last)
             }
+            BuildError::NoteReserved(name) => write!(
+                f,
+                "Note [{} {}] is already spent by a pending transaction",
+                name.first, name.last
+            ),
             BuildError::InvalidFee(expected, got) => {
                 write!(
                     f,
@@ -657,6 +1088,41 @@ impl core::fmt::Display for BuildError {
                 }
                 Ok(())
             }
+            BuildError::BundleMismatch => {
+                write!(f, "Bundles do not describe the same transaction")
+            }
+            BuildError::SpendMismatch(name) => write!(
+                f,
+                "Spend of note [{} {}] differs between transactions",
+                name.first, name.last
+            ),
+            BuildError::Verify(errors) => {
+                write!(f, "Transaction failed verification:")?;
+                for e in errors {
+                    write!(f, " {e}.")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Failure to sign with a [`Signer`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum SignError<E> {
+    /// The signer did not produce a signature.
+    Signer(E),
+    /// The signer produced a signature that does not verify against the key with this pkh.
+    InvalidSignature(Digest),
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for SignError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SignError::Signer(e) => write!(f, "Signer failed: {e}"),
+            SignError::InvalidSignature(pkh) => {
+                write!(f, "Signer returned an invalid signature for key {pkh}")
+            }
         }
     }
 }
@@ -664,9 +1130,10 @@ impl core::fmt::Display for BuildError {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{LockPrimitive, LockTim, Name, NoteData, Pkh, Version};
+    use crate::{LockPrimitive, LockTim, Name, NoteData, Pkh, TimelockRange, Version};
     use alloc::{string::ToString, vec};
     use bip39::Mnemonic;
+    use core::sync::atomic::{AtomicUsize, Ordering};
     use iris_crypto::{derive_master_key, PublicKey};
     use iris_ztd::{jam, NounEncode};
 
@@ -869,6 +1336,128 @@ mod tests {
         builder.validate().unwrap();
     }
 
+    #[test]
+    fn test_batch_spend() {
+        let (private_key, _) = keys();
+
+        let notes = [
+            "7yMzrJjkb2Xu8uURP7YB3DFcotttR8dKDXF1tSp2wJmmXUvLM7SYzvM",
+            "6yMzrJjkb2Xu8uURP7YB3DFcotttR8dKDXF1tSp2wJmmXUvLM7SYzvM",
+        ]
+        .into_iter()
+        .map(|last| Note {
+            version: Version::V1,
+            origin_page: 13,
+            name: Name::new(
+                "2H7WHTE9dFXiGgx4J432DsCLuMovNkokfcnCGRg7utWGM9h13PgQvsH"
+                    .try_into()
+                    .unwrap(),
+                last.try_into().unwrap(),
+            ),
+            note_data: NoteData::empty(),
+            assets: 30000,
+        })
+        .collect::<Vec<_>>();
+
+        let refund_pkh = private_key.public_key().hash();
+        let spend_condition = SpendCondition::new_pkh(Pkh::single(refund_pkh));
+        let notes = notes
+            .into_iter()
+            .map(|v| (v, spend_condition.clone()))
+            .collect::<Vec<_>>();
+
+        let alice = SpendCondition::new_pkh(Pkh::single(
+            "2nEFkqYm51yfqsYgfRx72w8FF9bmWqnkJu8XqY8T7psXufjYNRxf5ME"
+                .try_into()
+                .unwrap(),
+        ));
+        let bob = SpendCondition::new_pkh(Pkh::new(
+            2,
+            vec![
+                "2nEFkqYm51yfqsYgfRx72w8FF9bmWqnkJu8XqY8T7psXufjYNRxf5ME"
+                    .try_into()
+                    .unwrap(),
+                "6psXufjYNRxffRx72w8FF9b5MYg8TEmWq2nEFkqYm51yfqsnkJu8XqX"
+                    .try_into()
+                    .unwrap(),
+            ],
+        ));
+        let mut bob_payment = Payment::new(bob.clone(), 15000);
+        bob_payment.note_data = Some(NoteData(vec![crate::NoteDataEntry {
+            key: "memo".to_string(),
+            val: 42u64.to_noun(),
+        }]));
+
+        let mut builder = TxBuilder::new(8);
+        builder
+            .batch_spend(
+                notes.clone(),
+                vec![Payment::new(alice.clone(), 20000), bob_payment],
+                refund_pkh,
+                false,
+            )
+            .unwrap()
+            .sign(&private_key)
+            .validate()
+            .unwrap();
+
+        // Bob's payment is split across both notes, and only the second one has a refund.
+        let spends = builder.all_spends();
+        assert_eq!(spends.len(), 2);
+        assert_eq!(
+            spends.values().filter(|s| s.cur_refund().is_some()).count(),
+            1
+        );
+
+        let tx = builder.build();
+        let fee = tx
+            .to_raw_tx()
+            .spends
+            .0
+            .iter()
+            .map(|(_, s)| s.fee)
+            .sum::<Nicks>();
+        let outputs = tx.outputs();
+        assert_eq!(outputs.len(), 3);
+        let assets_of = |lock: &SpendCondition| {
+            outputs
+                .iter()
+                .find(|n| n.name.first == lock.first_name())
+                .map(|n| n.assets)
+        };
+        assert_eq!(assets_of(&alice), Some(20000));
+        assert_eq!(assets_of(&bob), Some(15000));
+        assert_eq!(assets_of(&spend_condition), Some(60000 - 35000 - fee));
+        let bob_note = outputs
+            .iter()
+            .find(|n| n.name.first == bob.first_name())
+            .unwrap();
+        assert_eq!(bob_note.note_data.0[0].key, "memo");
+
+        let mut builder = TxBuilder::new(8);
+        assert!(matches!(
+            builder.batch_spend(
+                notes.clone(),
+                vec![
+                    Payment::new(alice.clone(), 20000),
+                    Payment::new(bob.clone(), 0)
+                ],
+                refund_pkh,
+                false,
+            ),
+            Err(BuildError::ZeroGift)
+        ));
+        assert!(matches!(
+            builder.batch_spend(
+                notes,
+                vec![Payment::new(alice, 50000), Payment::new(bob, 10001)],
+                refund_pkh,
+                false,
+            ),
+            Err(BuildError::InsufficientFunds)
+        ));
+    }
+
     #[test]
     fn test_first_name() {
         let (_, public_key) = keys();
@@ -1058,6 +1647,90 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_missing_unlock_tim() {
+        let (private_key, _) = keys();
+        let note = Note {
+            version: Version::V1,
+            origin_page: 13,
+            name: Name::new(
+                "2H7WHTE9dFXiGgx4J432DsCLuMovNkokfcnCGRg7utWGM9h13PgQvsH"
+                    .try_into()
+                    .unwrap(),
+                "7yMzrJjkb2Xu8uURP7YB3DFcotttR8dKDXF1tSp2wJmmXUvLM7SYzvM"
+                    .try_into()
+                    .unwrap(),
+            ),
+            note_data: NoteData::empty(),
+            assets: 4294967296,
+        };
+        let recipient = "2nEFkqYm51yfqsYgfRx72w8FF9bmWqnkJu8XqY8T7psXufjYNRxf5ME"
+            .try_into()
+            .unwrap();
+        let refund_pkh = "6psXufjYNRxffRx72w8FF9b5MYg8TEmWq2nEFkqYm51yfqsnkJu8XqX"
+            .try_into()
+            .unwrap();
+        let spend_condition = SpendCondition(vec![
+            LockPrimitive::Pkh(Pkh::single(private_key.public_key().hash())),
+            LockPrimitive::Tim(LockTim::coinbase()),
+        ]);
+
+        let mut builder = TxBuilder::new(1);
+        builder
+            .simple_spend(
+                vec![(note.clone(), spend_condition.clone())],
+                recipient,
+                1234567,
+                refund_pkh,
+                false,
+            )
+            .unwrap()
+            .sign(&private_key)
+            .set_height(112);
+
+        match builder.validate() {
+            Err(BuildError::MissingUnlocks(unlocks)) => assert_eq!(
+                unlocks,
+                vec![MissingUnlocks::Tim {
+                    spendable_from: Some(113),
+                    spendable_until: None,
+                }]
+            ),
+            _ => panic!("expected missing timelock"),
+        }
+
+        builder.set_height(113);
+        assert!(builder.validate().is_ok());
+
+        // Coin selection must skip notes that cannot be spent yet.
+        let mut builder = TxBuilder::new(1);
+        let res = builder.set_height(112).simple_spend(
+            vec![(note, spend_condition)],
+            recipient,
+            1234567,
+            refund_pkh,
+            false,
+        );
+        assert!(matches!(res, Err(BuildError::InsufficientFunds)));
+
+        let vault = SpendCondition(vec![
+            LockPrimitive::Pkh(Pkh::single(private_key.public_key().hash())),
+            LockPrimitive::Tim(LockTim {
+                rel: TimelockRange::new(None, Some(10)),
+                abs: TimelockRange::new(Some(20), None),
+            }),
+        ]);
+        assert_eq!(
+            vault.missing_timelock(13, 24),
+            Some(MissingUnlocks::Tim {
+                spendable_from: Some(20),
+                spendable_until: Some(23),
+            })
+        );
+        assert_eq!(vault.missing_timelock(13, 20), None);
+        assert_eq!(vault.missing_timelock(13, 23), None);
+    }
+
     #[test]
     fn test_missing_unlock_hax() {
         use crate::Hax;
@@ -1187,4 +1860,275 @@ mod tests {
             "3gBbvwuhALLvTWnLfgP3KVWz2qSWKsvLXHmFAKXfqYjiNiu1Xc32GguLGUTzfEFyWMCfWuxurCkmgUaXnWJEoWdX62tiTwmdXPhJzcEgDeoy99rmZyezkHK992jinuFNmDEDEvVd5vM19g7MRNRi5d3zWPtjCL2j9JyfT6mtTKgh9PNnWLY75A2JwzUDd6FSytomgVBeyqhjBWm7tMgkXngduhJGoZ6rS5MkyrzFhmtAYmtjVV9p4HnjDW6rrtgKXLEqUp3jpEdxXA4nHT8mtbSAxNvvQF5V4wmYddKDrzCPeWd8mccHUnsSxWLLRgEbYgUHvC6Wh5F5nKsEb6zvT9jGB9s9etXPYknTRBHmsDBWBveCmAzVy6Fa2x8iNuc15NPmQQwbbGZsmjGbVQKFT8vJz7HjcefhEZg9zbyq9BhQ3u6gY8vYqETL5u8wCvRb9bkNMkUEBcsNnkfmeXQcSdaYfTaExQFPpdLDkBPcG4bHTffXsgEwRxFpLXRWgzzM5ESBYZvKyEtk32tUodnsbQ9zun2mptmFq6zLW6kLhDwKBT6rR3ErddCE82p5qcUaC4ZLR3fiz59Hg14MQeYnBkAy7Cj3Z7WdqvfPoXhZZ2FCztn9SZXeLFxotFZNqeHp9PQu754PnCq1rUpgCUcnoQiWwyjEP7JbY6T9hLyA3m7T6b97DbEqD7iuDNwrhwbofKyyfPxFeZKap",
         );
     }
+
+    #[test]
+    fn test_lock_tree_spend() {
+        let (private_key, _) = keys();
+        let pkh = private_key.public_key().hash();
+        let other = derive_master_key(&Mnemonic::parse("dice domain inspire horse time initial monitor nature mass impose tone benefit vibrant dash kiss mosquito rice then color ribbon agent method drop fat").unwrap().to_seed(""))
+            .derive_child(1)
+            .private_key
+            .unwrap();
+
+        // 2-of-2 keys, or our key alone after block 1000.
+        let multisig = SpendCondition::new_pkh(Pkh::new(2, vec![pkh, other.public_key().hash()]));
+        let recovery = SpendCondition(vec![
+            LockPrimitive::Pkh(Pkh::single(pkh)),
+            LockPrimitive::Tim(LockTim {
+                rel: TimelockRange::none(),
+                abs: TimelockRange {
+                    min: Some(1000),
+                    max: None,
+                },
+            }),
+        ]);
+        let tree = LockTree::new(vec![multisig, recovery.clone()]).unwrap();
+
+        // Fund the tree.
+        let funding_note = Note {
+            version: Version::V1,
+            origin_page: 13,
+            name: Name::new(
+                SpendCondition::new_pkh(Pkh::single(pkh)).first_name(),
+                "7yMzrJjkb2Xu8uURP7YB3DFcotttR8dKDXF1tSp2wJmmXUvLM7SYzvM"
+                    .try_into()
+                    .unwrap(),
+            ),
+            note_data: NoteData::empty(),
+            assets: 100_000,
+        };
+        let mut builder = TxBuilder::new(1);
+        let funding = builder
+            .batch_spend(
+                vec![(funding_note, SpendCondition::new_pkh(Pkh::single(pkh)))],
+                vec![Payment::tree(&tree, 50_000)],
+                pkh,
+                true,
+            )
+            .unwrap()
+            .sign(&private_key)
+            .build()
+            .to_raw_tx();
+        let note = funding
+            .outputs()
+            .into_iter()
+            .find(|n| n.name.first == tree.first_name())
+            .unwrap();
+        assert_eq!(note.assets, 50_000);
+
+        // Spend through the recovery branch.
+        let index = tree.position(&recovery).unwrap();
+        let proof = tree.prove(index).unwrap();
+        assert_eq!(proof.axis, 3);
+        let mut spend = SpendBuilder::with_proof(
+            note.clone(),
+            proof,
+            Some(SpendCondition::new_pkh(Pkh::single(pkh))),
+        )
+        .unwrap();
+        let seed = spend.build_seed(
+            SpendCondition::new_pkh(Pkh::single(other.public_key().hash())),
+            10_000,
+            false,
+        );
+        spend.seed(seed).compute_refund(false);
+
+        let mut builder = TxBuilder::new(1);
+        builder.spend(spend);
+        builder
+            .recalc_and_set_fee(false)
+            .unwrap()
+            .sign(&private_key)
+            .set_height(999);
+        assert!(matches!(
+            builder.validate(),
+            Err(BuildError::MissingUnlocks(_))
+        ));
+        builder.set_height(1000);
+        builder.validate().unwrap();
+
+        let tx = builder.build().to_raw_tx();
+        let notes = [(note.name.clone(), note.clone())].into_iter().collect();
+        assert!(tx.verify(&notes, 1).is_valid());
+
+        // The builder can be recovered from the transaction.
+        let recovered = TxBuilder::from_tx(
+            tx.clone(),
+            [(note.name.clone(), (note.clone(), recovery))]
+                .into_iter()
+                .collect(),
+        )
+        .unwrap()
+        .build();
+        assert_eq!(recovered.id, tx.id);
+
+        // Proofs for a different tree are rejected.
+        let mut forged = tree.prove(index).unwrap();
+        forged.proof.root = note.name.first;
+        assert!(SpendBuilder::with_proof(note, forged, None).is_none());
+    }
+
+    /// Signs like a remote daemon would: after a delay, and possibly failing.
+    struct RemoteSigner {
+        key: PrivateKey,
+        online: bool,
+        calls: AtomicUsize,
+    }
+
+    impl Signer for RemoteSigner {
+        type Error = &'static str;
+
+        fn public_key(&self) -> PublicKey {
+            self.key.public_key()
+        }
+
+        async fn sign(&self, digest: &Digest) -> Result<Signature, Self::Error> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            let mut polled = false;
+            core::future::poll_fn(|cx| {
+                if polled {
+                    core::task::Poll::Ready(())
+                } else {
+                    polled = true;
+                    cx.waker().wake_by_ref();
+                    core::task::Poll::Pending
+                }
+            })
+            .await;
+            if self.online {
+                Ok(self.key.sign(digest))
+            } else {
+                Err("signer is offline")
+            }
+        }
+    }
+
+    /// Claims one key, but signs with another.
+    struct WrongKeySigner {
+        claimed: PublicKey,
+        key: PrivateKey,
+    }
+
+    impl Signer for WrongKeySigner {
+        type Error = &'static str;
+
+        fn public_key(&self) -> PublicKey {
+            self.claimed.clone()
+        }
+
+        async fn sign(&self, digest: &Digest) -> Result<Signature, Self::Error> {
+            Ok(self.key.sign(digest))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_signer() {
+        let mnemonic = Mnemonic::parse("dice domain inspire horse time initial monitor nature mass impose tone benefit vibrant dash kiss mosquito rice then color ribbon agent method drop fat").unwrap();
+        let master = derive_master_key(&mnemonic.to_seed(""));
+        let keys = (0..4)
+            .map(|i| master.derive_child(i).private_key.unwrap())
+            .collect::<Vec<_>>();
+        let pkhs = keys
+            .iter()
+            .map(|k| k.public_key().hash())
+            .collect::<Vec<_>>();
+        let remote = |i: usize, online| RemoteSigner {
+            key: keys[i].clone(),
+            online,
+            calls: AtomicUsize::new(0),
+        };
+
+        let spend_condition = SpendCondition::new_pkh(Pkh::new(2, pkhs[..3].to_vec()));
+        let note = Note {
+            version: Version::V1,
+            origin_page: 13,
+            name: Name::new(
+                spend_condition.first_name(),
+                "7yMzrJjkb2Xu8uURP7YB3DFcotttR8dKDXF1tSp2wJmmXUvLM7SYzvM"
+                    .try_into()
+                    .unwrap(),
+            ),
+            note_data: NoteData::empty(),
+            assets: 1 << 32,
+        };
+        let recipient = "2nEFkqYm51yfqsYgfRx72w8FF9bmWqnkJu8XqY8T7psXufjYNRxf5ME"
+            .try_into()
+            .unwrap();
+
+        let mut builder = TxBuilder::new(1 << 10);
+        builder
+            .simple_spend(
+                vec![(note.clone(), spend_condition)],
+                recipient,
+                1234567,
+                pkhs[0],
+                false,
+            )
+            .unwrap();
+
+        let requests = builder.signature_requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].name, note.name);
+        assert_eq!(requests[0].pkhs, pkhs[..3].iter().copied().collect());
+
+        // Keys outside the lock are never asked to sign.
+        let stranger = remote(3, true);
+        builder.sign_with(&stranger).await.unwrap();
+        assert_eq!(stranger.calls.load(Ordering::Relaxed), 0);
+
+        assert_eq!(
+            builder.sign_with(&remote(0, false)).await.err(),
+            Some(SignError::Signer("signer is offline"))
+        );
+        let wrong_key = WrongKeySigner {
+            claimed: keys[0].public_key(),
+            key: keys[3].clone(),
+        };
+        assert_eq!(
+            builder.sign_with(&wrong_key).await.err(),
+            Some(SignError::InvalidSignature(pkhs[0]))
+        );
+
+        let signer = remote(0, true);
+        builder.sign_with(&signer).await.unwrap();
+        builder.sign_with(&signer).await.unwrap();
+        assert_eq!(signer.calls.load(Ordering::Relaxed), 1);
+        let requests = builder.signature_requests();
+        assert_eq!(requests[0].pkhs, pkhs[1..3].iter().copied().collect());
+
+        // The remaining signature comes from somewhere else entirely.
+        let request = &requests[0];
+        let forged = keys[1].sign(&note.hash());
+        assert!(!builder.add_signature(&request.name, keys[1].public_key(), forged));
+        let signature = keys[1].sign(&request.sig_hash);
+        assert!(builder.add_signature(&request.name, keys[1].public_key(), signature.clone()));
+        assert!(!builder.add_signature(&request.name, keys[2].public_key(), signature));
+        assert!(builder.signature_requests().is_empty());
+
+        builder.validate().unwrap();
+        let tx = builder.build().to_raw_tx();
+        let notes = [(note.name.clone(), note)].into_iter().collect();
+        assert!(tx.verify(&notes, 1 << 10).is_valid());
+
+        // Private keys are signers too.
+        let mut builder = TxBuilder::new(1 << 10);
+        let key = keys[0].clone();
+        builder
+            .simple_spend(
+                tx.outputs()
+                    .into_iter()
+                    .filter(|n| {
+                        n.name.first == SpendCondition::new_pkh(Pkh::single(pkhs[0])).first_name()
+                    })
+                    .map(|n| (n, SpendCondition::new_pkh(Pkh::single(pkhs[0]))))
+                    .collect(),
+                recipient,
+                1000,
+                pkhs[0],
+                false,
+            )
+            .unwrap();
+        builder.sign_with(&key).await.unwrap();
+        builder.validate().unwrap();
+    }
 }
@@ -0,0 +1,445 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use iris_crypto::{PrivateKey, PublicKey, Signature};
+use iris_ztd::{cue, jam, Digest, Hashable as HashableTrait, Noun, NounDecode, NounEncode};
+use iris_ztd_derive::{NounDecode, NounEncode};
+
+use super::builder::{BuildError, MissingUnlocks};
+use super::note::{BlockHeight, Name, Note};
+use super::tx::{RawTx, Spend, SpendCondition, Spends};
+use super::verify::noun_hashable;
+use crate::Nicks;
+
+/// A spend of a bundle, along with everything needed to inspect and sign it offline.
+#[derive(Debug, Clone, NounEncode, NounDecode)]
+pub struct BundleInput {
+    pub note: Note,
+    pub spend_condition: SpendCondition,
+    pub refund_lock: Option<SpendCondition>,
+    pub spend: Spend,
+}
+
+impl BundleInput {
+    /// Whether `other` spends the same note in the same way, ignoring signatures and preimages.
+    fn same_spend(&self, other: &BundleInput) -> bool {
+        self.note.hash() == other.note.hash()
+            && self.spend_condition.hash() == other.spend_condition.hash()
+            && self.spend.sig_hash() == other.spend.sig_hash()
+    }
+}
+
+/// Partially signed transaction, meant to be passed between co-signers and offline signers.
+///
+/// Unlike a `NockchainTx`, the bundle carries the notes being spent, so that a signer can see
+/// exactly what they are signing. Bundles are versioned, and their noun encoding is tagged with
+/// [`TxBundle::TAG`].
+#[derive(Debug, Clone)]
+pub struct TxBundle {
+    pub fee_per_word: Nicks,
+    pub height: Option<BlockHeight>,
+    pub inputs: Vec<BundleInput>,
+}
+
+impl TxBundle {
+    pub const TAG: &'static str = "iris-tx-bundle";
+    pub const VERSION: u32 = 1;
+
+    pub fn from_jam(jam: &[u8]) -> Option<Self> {
+        Self::from_noun(&cue(jam)?)
+    }
+
+    pub fn to_jam(&self) -> Vec<u8> {
+        jam(self.to_noun())
+    }
+
+    pub fn notes(&self) -> BTreeMap<Name, Note> {
+        self.inputs
+            .iter()
+            .map(|i| (i.note.name.clone(), i.note.clone()))
+            .collect()
+    }
+
+    pub fn spends(&self) -> Spends {
+        Spends(
+            self.inputs
+                .iter()
+                .map(|i| (i.note.name.clone(), i.spend.clone()))
+                .collect(),
+        )
+    }
+
+    /// Sign all inputs that require a signature from `signing_key`.
+    pub fn sign(&mut self, signing_key: &PrivateKey) -> &mut Self {
+        let public_key = signing_key.public_key();
+        let pkh = public_key.hash();
+
+        for input in &mut self.inputs {
+            let required = input.spend_condition.pkh().any(|p| p.hashes.contains(&pkh));
+            let signed = input
+                .spend
+                .witness
+                .pkh_signature
+                .0
+                .iter()
+                .any(|(h, _, _)| *h == pkh);
+            if required && !signed {
+                let signature = signing_key.sign(&input.spend.sig_hash());
+                input.spend.add_signature(public_key.clone(), signature);
+            }
+        }
+
+        self
+    }
+
+    /// Merge signatures and preimages collected in `other` into this bundle.
+    ///
+    /// Both bundles must describe the same transaction, otherwise `BuildError::BundleMismatch` is
+    /// returned, and this bundle is left untouched. Signatures from `other` are only merged if
+    /// they verify, and a valid signature replaces an invalid one from the same key. Likewise,
+    /// preimages are only merged if the spend condition asks for their digest and they hash to
+    /// it, and a valid preimage is never replaced.
+    pub fn combine(&mut self, other: &TxBundle) -> Result<&mut Self, BuildError> {
+        if self.inputs.len() != other.inputs.len() {
+            return Err(BuildError::BundleMismatch);
+        }
+
+        let mut pairs = Vec::with_capacity(self.inputs.len());
+        for theirs in &other.inputs {
+            let ours = self
+                .inputs
+                .iter()
+                .position(|i| i.note.name == theirs.note.name)
+                .ok_or(BuildError::BundleMismatch)?;
+            if !self.inputs[ours].same_spend(theirs) {
+                return Err(BuildError::BundleMismatch);
+            }
+            pairs.push((ours, theirs));
+        }
+
+        for (ours, theirs) in pairs {
+            let input = &mut self.inputs[ours];
+            let sig_hash = input.spend.sig_hash();
+            let spend_condition = &input.spend_condition;
+            let witness = &mut input.spend.witness;
+            for entry in &theirs.spend.witness.pkh_signature.0 {
+                if !verifies(entry, &sig_hash) {
+                    continue;
+                }
+                let existing = witness
+                    .pkh_signature
+                    .0
+                    .iter_mut()
+                    .find(|(h, _, _)| *h == entry.0);
+                match existing {
+                    Some(existing) if !verifies(existing, &sig_hash) => *existing = entry.clone(),
+                    Some(_) => {}
+                    None => witness.pkh_signature.0.push(entry.clone()),
+                }
+            }
+            for (digest, preimage) in theirs.spend.witness.hax_map.clone() {
+                if !unlocks(spend_condition, &digest, &preimage) {
+                    continue;
+                }
+                match witness.hax_map.get(&digest) {
+                    Some(p) if unlocks(spend_condition, &digest, p) => {}
+                    // `ZMap::insert` keeps existing entries, so an invalid one must be dropped.
+                    Some(_) => {
+                        witness.hax_map = core::mem::take(&mut witness.hax_map)
+                            .into_iter()
+                            .filter(|(d, _)| *d != digest)
+                            .chain([(digest, preimage)])
+                            .collect();
+                    }
+                    None => {
+                        witness.hax_map.insert(digest, preimage);
+                    }
+                }
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// Unlocks that are still missing, per input.
+    pub fn missing_unlocks(&self) -> Vec<(Name, Vec<MissingUnlocks>)> {
+        self.inputs
+            .iter()
+            .filter_map(|i| {
+                let mut unlocks = i.spend_condition.missing_unlocks(&i.spend.witness);
+                if let Some(height) = self.height {
+                    unlocks.extend(
+                        i.spend_condition
+                            .missing_timelock(i.note.origin_page, height),
+                    );
+                }
+                if unlocks.is_empty() {
+                    None
+                } else {
+                    Some((i.note.name.clone(), unlocks))
+                }
+            })
+            .collect()
+    }
+
+    /// Drop invalid signatures, and check that the transaction is complete.
+    pub fn finalize(&mut self) -> Result<&mut Self, BuildError> {
+        for input in &mut self.inputs {
+            let sig_hash = input.spend.sig_hash();
+            input
+                .spend
+                .witness
+                .pkh_signature
+                .0
+                .retain(|entry| verifies(entry, &sig_hash));
+        }
+
+        let unlocks = self
+            .missing_unlocks()
+            .into_iter()
+            .flat_map(|(_, u)| u)
+            .collect::<Vec<_>>();
+        if !unlocks.is_empty() {
+            return Err(BuildError::MissingUnlocks(unlocks));
+        }
+
+        let report = RawTx::new(self.spends()).verify(&self.notes(), self.fee_per_word);
+        report.into_result().map_err(BuildError::Verify)?;
+
+        Ok(self)
+    }
+
+    /// Finalize a copy of the bundle, and extract the transaction out of it.
+    pub fn extract_tx(&self) -> Result<RawTx, BuildError> {
+        let mut bundle = self.clone();
+        bundle.finalize()?;
+        Ok(RawTx::new(bundle.spends()))
+    }
+}
+
+/// Whether a signature entry is by the key it claims, and signs `sig_hash`.
+fn verifies((pkh, pk, sig): &(Digest, PublicKey, Signature), sig_hash: &Digest) -> bool {
+    pk.hash() == *pkh && pk.verify(sig_hash, sig)
+}
+
+/// Whether `preimage` hashes to `digest`, and `digest` is one of the hax locks of
+/// `spend_condition`.
+fn unlocks(spend_condition: &SpendCondition, digest: &Digest, preimage: &Noun) -> bool {
+    spend_condition.hax().any(|h| h.0.contains(digest))
+        && noun_hashable(preimage)
+        && preimage.hash() == *digest
+}
+
+impl NounEncode for TxBundle {
+    fn to_noun(&self) -> Noun {
+        (
+            Self::TAG,
+            Self::VERSION,
+            self.fee_per_word,
+            self.height,
+            &self.inputs,
+        )
+            .to_noun()
+    }
+}
+
+impl NounDecode for TxBundle {
+    fn from_noun(noun: &Noun) -> Option<Self> {
+        let (tag, version, fee_per_word, height, inputs): (String, u32, _, _, _) =
+            NounDecode::from_noun(noun)?;
+
+        if tag != Self::TAG || version != Self::VERSION {
+            return None;
+        }
+
+        Some(Self {
+            fee_per_word,
+            height,
+            inputs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Pkh, Source, TxBuilder, Version};
+    use alloc::vec;
+    use bip39::Mnemonic;
+    use iris_crypto::derive_master_key;
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        let mnemonic = Mnemonic::parse("dice domain inspire horse time initial monitor nature mass impose tone benefit vibrant dash kiss mosquito rice then color ribbon agent method drop fat").unwrap();
+        let master = derive_master_key(&mnemonic.to_seed(""));
+        let key_a = master.private_key.clone().unwrap();
+        let key_b = master.derive_child(1).private_key.unwrap();
+
+        let spend_condition = SpendCondition::new_pkh(Pkh::new(
+            2,
+            vec![key_a.public_key().hash(), key_b.public_key().hash()],
+        ));
+        let note = Note::new(
+            Version::V1,
+            13,
+            Name::new_v1(
+                spend_condition.hash(),
+                Source {
+                    hash: "2H7WHTE9dFXiGgx4J432DsCLuMovNkokfcnCGRg7utWGM9h13PgQvsH"
+                        .try_into()
+                        .unwrap(),
+                    is_coinbase: false,
+                },
+            ),
+            crate::NoteData::empty(),
+            1 << 32,
+        );
+        let recipient = "2nEFkqYm51yfqsYgfRx72w8FF9bmWqnkJu8XqY8T7psXufjYNRxf5ME"
+            .try_into()
+            .unwrap();
+
+        let mut builder = TxBuilder::new(1 << 10);
+        builder
+            .simple_spend(
+                vec![(note, spend_condition)],
+                recipient,
+                1234567,
+                key_a.public_key().hash(),
+                true,
+            )
+            .unwrap();
+
+        let bundle = builder.to_bundle();
+        let summary = bundle.summary(&crate::OwnedLocks::default());
+        assert_eq!(summary.total_inputs, Some(1 << 32));
+        assert_eq!(summary.total_spent, 1 << 32);
+        let jammed = bundle.to_jam();
+        let mut bundle_a = TxBundle::from_jam(&jammed).unwrap();
+        let mut bundle_b = TxBundle::from_jam(&jammed).unwrap();
+        assert_eq!(bundle_a.to_jam(), jammed);
+
+        bundle_a.sign(&key_a);
+        assert!(matches!(
+            bundle_a.extract_tx(),
+            Err(BuildError::MissingUnlocks(_))
+        ));
+
+        bundle_b.sign(&key_b);
+        bundle_a.combine(&bundle_b).unwrap();
+        assert!(bundle_a.missing_unlocks().is_empty());
+
+        let tx = bundle_a.extract_tx().unwrap();
+        let expected = builder.sign(&key_a).sign(&key_b).build().to_raw_tx();
+        assert_eq!(tx.id, expected.id);
+
+        // The builder can be recovered from the bundle.
+        let recovered = TxBuilder::from_bundle(TxBundle::from_jam(&bundle_a.to_jam()).unwrap())
+            .unwrap()
+            .build();
+        assert_eq!(recovered.id, expected.id);
+
+        // Bundles for a different fee cannot be combined.
+        let mut other = bundle.clone();
+        other.inputs[0].spend.fee += 1;
+        assert!(matches!(
+            bundle_b.combine(&other),
+            Err(BuildError::BundleMismatch)
+        ));
+
+        // Tampered signatures are never merged, and are dropped on finalize.
+        let mut forged = bundle;
+        forged.sign(&key_a);
+        forged.inputs[0].spend.witness.pkh_signature.0[0].2 =
+            bundle_b.inputs[0].spend.witness.pkh_signature.0[0]
+                .2
+                .clone();
+        let mut signed_by_b = bundle_b.clone();
+        signed_by_b.combine(&forged).unwrap();
+        assert_eq!(signed_by_b.inputs[0].spend.witness.pkh_signature.0.len(), 1);
+        forged.combine(&bundle_b).unwrap();
+        assert!(matches!(
+            forged.clone().finalize(),
+            Err(BuildError::MissingUnlocks(_))
+        ));
+
+        // A valid signature replaces a tampered one from the same key.
+        forged.combine(&bundle_a).unwrap();
+        assert_eq!(forged.extract_tx().unwrap().id, expected.id);
+
+        assert!(TxBundle::from_noun(&(TxBundle::TAG, 2, 0, 0, 0).to_noun()).is_none());
+    }
+
+    #[test]
+    fn test_combine_preimages() {
+        use crate::{Hax, LockPrimitive};
+
+        let mnemonic = Mnemonic::parse("dice domain inspire horse time initial monitor nature mass impose tone benefit vibrant dash kiss mosquito rice then color ribbon agent method drop fat").unwrap();
+        let key = derive_master_key(&mnemonic.to_seed(""))
+            .private_key
+            .unwrap();
+        let preimage = 42.to_noun();
+        let digest = preimage.hash();
+        let spend_condition = SpendCondition(vec![
+            LockPrimitive::Pkh(Pkh::single(key.public_key().hash())),
+            LockPrimitive::Hax(Hax(vec![digest])),
+        ]);
+        let note = Note::new(
+            Version::V1,
+            13,
+            Name::new_v1(
+                spend_condition.hash(),
+                Source {
+                    hash: "2H7WHTE9dFXiGgx4J432DsCLuMovNkokfcnCGRg7utWGM9h13PgQvsH"
+                        .try_into()
+                        .unwrap(),
+                    is_coinbase: false,
+                },
+            ),
+            crate::NoteData::empty(),
+            1 << 32,
+        );
+        let recipient = "2nEFkqYm51yfqsYgfRx72w8FF9bmWqnkJu8XqY8T7psXufjYNRxf5ME"
+            .try_into()
+            .unwrap();
+
+        let mut builder = TxBuilder::new(1 << 10);
+        builder
+            .simple_spend(
+                vec![(note, spend_condition)],
+                recipient,
+                1234567,
+                key.public_key().hash(),
+                true,
+            )
+            .unwrap();
+        let mut bundle = builder.to_bundle();
+        bundle.sign(&key);
+        let preimages = |b: &TxBundle| b.inputs[0].spend.witness.hax_map.clone();
+
+        // A preimage that does not hash to its digest is never merged, nor is one the spend
+        // condition does not ask for.
+        let mut forged = bundle.clone();
+        forged.inputs[0]
+            .spend
+            .witness
+            .hax_map
+            .insert(digest, 43.to_noun());
+        forged.inputs[0].spend.add_preimage(43.to_noun());
+        let mut combined = bundle.clone();
+        combined.combine(&forged).unwrap();
+        assert!(preimages(&combined).into_iter().next().is_none());
+
+        let mut revealed = bundle.clone();
+        revealed.inputs[0].spend.add_preimage(preimage.clone());
+        combined.combine(&revealed).unwrap();
+        assert_eq!(preimages(&combined).get(&digest), Some(&preimage));
+
+        // A valid preimage is not overwritten, but replaces an invalid one.
+        combined.combine(&forged).unwrap();
+        assert_eq!(preimages(&combined).get(&digest), Some(&preimage));
+        forged.combine(&revealed).unwrap();
+        assert_eq!(preimages(&forged).get(&digest), Some(&preimage));
+
+        assert!(combined.missing_unlocks().is_empty());
+    }
+}
@@ -0,0 +1,356 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use iris_ztd::{Digest, Hashable as HashableTrait};
+
+use super::note::{Balance, BalanceUpdate, BlockHeight, Name, Note, NoteData, Source, Version};
+use super::tx::{LockPrimitive, LockTim, RawTx, SpendCondition, TxId};
+use super::verify::VerifyError;
+use crate::{Nicks, Pkh};
+
+/// Changes made by a single block, kept so that the block can be rolled back.
+#[derive(Debug, Clone)]
+struct Block {
+    height: BlockHeight,
+    id: Digest,
+    txs: Vec<TxId>,
+    spent: Vec<Note>,
+    created: Vec<Name>,
+}
+
+/// An in-memory chain of notes, for testing wallet logic without a node.
+///
+/// Transactions and mints are applied to the tip block, and their outputs have the tip's height as
+/// their `origin_page`. Each block remembers what it changed, so that it can be rolled back.
+#[derive(Debug, Clone)]
+pub struct Ledger {
+    notes: BTreeMap<Name, Note>,
+    blocks: Vec<Block>,
+    fee_per_word: Nicks,
+    /// Number of blocks and mints so far, so that block ids and coinbase sources never repeat.
+    nonce: u64,
+}
+
+impl Ledger {
+    /// Create an empty ledger whose tip block is at `height`.
+    pub fn new(height: BlockHeight, fee_per_word: Nicks) -> Self {
+        Self {
+            notes: BTreeMap::new(),
+            blocks: vec![Block {
+                height,
+                id: (height, 0u64).hash(),
+                txs: vec![],
+                spent: vec![],
+                created: vec![],
+            }],
+            fee_per_word,
+            nonce: 0,
+        }
+    }
+
+    fn tip(&self) -> &Block {
+        self.blocks.last().expect("ledger has no blocks")
+    }
+
+    fn tip_mut(&mut self) -> &mut Block {
+        self.blocks.last_mut().expect("ledger has no blocks")
+    }
+
+    pub fn height(&self) -> BlockHeight {
+        self.tip().height
+    }
+
+    pub fn block_id(&self) -> Digest {
+        self.tip().id
+    }
+
+    pub fn fee_per_word(&self) -> Nicks {
+        self.fee_per_word
+    }
+
+    pub fn notes(&self) -> &BTreeMap<Name, Note> {
+        &self.notes
+    }
+
+    pub fn get(&self, name: &Name) -> Option<&Note> {
+        self.notes.get(name)
+    }
+
+    /// Snapshot of all notes at the tip, as a node would return it.
+    pub fn balance(&self) -> BalanceUpdate {
+        BalanceUpdate {
+            height: self.height(),
+            block_id: self.block_id(),
+            notes: Balance(
+                self.notes
+                    .iter()
+                    .map(|(name, note)| (name.clone(), note.clone()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Notes at the tip whose first name is `first_name`.
+    pub fn balance_of(&self, first_name: &Digest) -> BalanceUpdate {
+        let mut balance = self.balance();
+        balance
+            .notes
+            .0
+            .retain(|(name, _)| name.first == *first_name);
+        balance
+    }
+
+    /// Start a new, empty block on top of the tip, returning its height.
+    pub fn advance(&mut self) -> BlockHeight {
+        self.nonce += 1;
+        let height = self.height() + 1;
+        let id = (height, self.block_id(), self.nonce).hash();
+        self.blocks.push(Block {
+            height,
+            id,
+            txs: vec![],
+            spent: vec![],
+            created: vec![],
+        });
+        height
+    }
+
+    /// Undo the tip block and make its parent the tip again.
+    ///
+    /// Returns the transactions that were in the block, so that they can be re-applied on a
+    /// different branch. The first block of the ledger cannot be rolled back.
+    pub fn rollback(&mut self) -> Option<Vec<TxId>> {
+        if self.blocks.len() < 2 {
+            return None;
+        }
+        let block = self.blocks.pop()?;
+        // Notes created and spent within the block are in both lists, and must end up removed.
+        for note in block.spent {
+            self.notes.insert(note.name.clone(), note);
+        }
+        for name in &block.created {
+            self.notes.remove(name);
+        }
+        Some(block.txs)
+    }
+
+    fn insert(&mut self, note: Note) {
+        self.tip_mut().created.push(note.name.clone());
+        self.notes.insert(note.name.clone(), note);
+    }
+
+    /// Create a note locked to `spend_condition` out of thin air, in the tip block.
+    pub fn mint(&mut self, spend_condition: &SpendCondition, assets: Nicks) -> Note {
+        self.mint_note(spend_condition, assets, false)
+    }
+
+    /// Mint a coinbase note for `pkh`, which only becomes spendable once it is mature.
+    ///
+    /// Returns the note along with its spend condition, which is needed to spend it.
+    pub fn mint_coinbase(&mut self, pkh: Pkh, assets: Nicks) -> (Note, SpendCondition) {
+        let spend_condition = SpendCondition(vec![
+            LockPrimitive::Pkh(pkh),
+            LockPrimitive::Tim(LockTim::coinbase()),
+        ]);
+        let note = self.mint_note(&spend_condition, assets, true);
+        (note, spend_condition)
+    }
+
+    fn mint_note(
+        &mut self,
+        spend_condition: &SpendCondition,
+        assets: Nicks,
+        is_coinbase: bool,
+    ) -> Note {
+        self.nonce += 1;
+        let source = Source {
+            hash: (self.block_id(), self.nonce).hash(),
+            is_coinbase,
+        };
+        let note = Note::new(
+            Version::V1,
+            self.height(),
+            Name::new_v1(spend_condition.hash(), source),
+            NoteData::empty(),
+            assets,
+        );
+        self.insert(note.clone());
+        note
+    }
+
+    /// Check `tx` against the notes at the tip, including timelocks.
+    pub fn check(&self, tx: &RawTx) -> Result<(), Vec<VerifyError>> {
+        tx.verify_at(&self.notes, self.fee_per_word, self.height())
+            .into_result()
+    }
+
+    /// Apply `tx` to the tip block, returning the notes it created.
+    ///
+    /// The transaction must be fully valid at the tip's height. Its inputs are removed, and its
+    /// outputs are added with the tip's height as their `origin_page`.
+    pub fn apply(&mut self, tx: &RawTx) -> Result<Vec<Note>, Vec<VerifyError>> {
+        self.check(tx)?;
+
+        let height = self.height();
+        for (name, _) in &tx.spends.0 {
+            if let Some(note) = self.notes.remove(name) {
+                self.tip_mut().spent.push(note);
+            }
+        }
+        let outputs = tx
+            .outputs()
+            .into_iter()
+            .map(|mut note| {
+                note.origin_page = height;
+                note
+            })
+            .collect::<Vec<_>>();
+        for note in &outputs {
+            self.insert(note.clone());
+        }
+        self.tip_mut().txs.push(tx.id);
+
+        Ok(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MissingUnlocks, TxBuilder, WalletState};
+    use bip39::Mnemonic;
+    use iris_crypto::derive_master_key;
+
+    #[test]
+    fn test_ledger() {
+        let mnemonic = Mnemonic::parse("dice domain inspire horse time initial monitor nature mass impose tone benefit vibrant dash kiss mosquito rice then color ribbon agent method drop fat").unwrap();
+        let private_key = derive_master_key(&mnemonic.to_seed(""))
+            .private_key
+            .unwrap();
+        let pkh = private_key.public_key().hash();
+        let spend_condition = SpendCondition::new_pkh(Pkh::single(pkh));
+        let recipient = "2nEFkqYm51yfqsYgfRx72w8FF9bmWqnkJu8XqY8T7psXufjYNRxf5ME"
+            .try_into()
+            .unwrap();
+        let send = |notes: Vec<(Note, SpendCondition)>, gift| {
+            TxBuilder::new(1 << 10)
+                .simple_spend(notes, recipient, gift, pkh, false)
+                .unwrap()
+                .sign(&private_key)
+                .build()
+                .to_raw_tx()
+        };
+
+        let mut ledger = Ledger::new(10, 1 << 10);
+        let note = ledger.mint(&spend_condition, 1 << 24);
+        let (coinbase, coinbase_lock) = ledger.mint_coinbase(Pkh::single(pkh), 1 << 24);
+        assert_eq!(coinbase.origin_page, 10);
+        ledger.advance();
+
+        let mut wallet = WalletState::new();
+        wallet.apply_update(ledger.balance_of(&spend_condition.first_name()));
+        assert_eq!(wallet.spendable_assets(), 1 << 24);
+
+        // Coinbase notes are locked until they are 100 blocks old.
+        let early = send(vec![(coinbase.clone(), coinbase_lock.clone())], 1000);
+        assert!(matches!(
+            ledger.apply(&early).unwrap_err()[..],
+            [VerifyError::MissingUnlocks { ref unlocks, .. }]
+                if matches!(unlocks[..], [MissingUnlocks::Tim { spendable_from: Some(110), .. }])
+        ));
+        while ledger.height() < 110 {
+            ledger.advance();
+        }
+        ledger.check(&early).unwrap();
+
+        // A transaction without signatures is not unlocked.
+        let mut builder = TxBuilder::new(1 << 10);
+        builder
+            .simple_spend(
+                vec![(note.clone(), spend_condition.clone())],
+                recipient,
+                1000,
+                pkh,
+                false,
+            )
+            .unwrap();
+        let unsigned = builder.build().to_raw_tx();
+        assert!(matches!(
+            ledger.apply(&unsigned).unwrap_err()[..],
+            [VerifyError::MissingUnlocks { .. }]
+        ));
+
+        let tx = send(vec![(note.clone(), spend_condition.clone())], 1000);
+        let outputs = ledger.apply(&tx).unwrap();
+        assert_eq!(outputs.len(), 2);
+        assert!(outputs.iter().all(|n| n.origin_page == 110));
+        assert!(ledger.get(&note.name).is_none());
+        assert!(matches!(
+            ledger.apply(&tx).unwrap_err()[..],
+            [VerifyError::NoteNotFound(_)]
+        ));
+
+        let block_id = ledger.block_id();
+        ledger.advance();
+        wallet.apply_update(ledger.balance_of(&spend_condition.first_name()));
+        assert_eq!(
+            wallet.spendable_assets(),
+            (1 << 24) - 1000 - tx.spends.0[0].1.fee
+        );
+
+        // Rolling back the blocks of a reorg restores the spent note.
+        assert_eq!(ledger.rollback(), Some(vec![]));
+        assert_eq!(ledger.block_id(), block_id);
+        assert_eq!(ledger.rollback(), Some(vec![tx.id]));
+        assert_eq!(ledger.height(), 109);
+        assert!(ledger.get(&note.name).is_some());
+        assert!(outputs.iter().all(|n| ledger.get(&n.name).is_none()));
+
+        // The new branch gets different block ids.
+        ledger.advance();
+        assert_ne!(ledger.block_id(), block_id);
+
+        while ledger.rollback().is_some() {}
+        assert_eq!(ledger.height(), 10);
+        assert_eq!(ledger.notes().len(), 2);
+    }
+
+    #[test]
+    fn test_rollback_chained_spend() {
+        let mnemonic = Mnemonic::parse("dice domain inspire horse time initial monitor nature mass impose tone benefit vibrant dash kiss mosquito rice then color ribbon agent method drop fat").unwrap();
+        let private_key = derive_master_key(&mnemonic.to_seed(""))
+            .private_key
+            .unwrap();
+        let pkh = private_key.public_key().hash();
+        let spend_condition = SpendCondition::new_pkh(Pkh::single(pkh));
+        let send = |note: Note, gift| {
+            TxBuilder::new(1 << 10)
+                .simple_spend(vec![(note, spend_condition.clone())], pkh, gift, pkh, false)
+                .unwrap()
+                .sign(&private_key)
+                .build()
+                .to_raw_tx()
+        };
+
+        let mut ledger = Ledger::new(10, 1 << 10);
+        let note = ledger.mint(&spend_condition, 1 << 24);
+        ledger.advance();
+
+        // A creates a note that B spends, in the same block.
+        let a = send(note.clone(), 1 << 20);
+        let created = ledger.apply(&a).unwrap();
+        let x = created[0].clone();
+        let b = send(x.clone(), 1000);
+        let outputs = ledger.apply(&b).unwrap();
+        assert!(ledger.get(&x.name).is_none());
+
+        assert_eq!(ledger.rollback(), Some(vec![a.id, b.id]));
+        assert!(ledger.get(&note.name).is_some());
+        assert!(ledger.get(&x.name).is_none());
+        assert!(created
+            .iter()
+            .chain(&outputs)
+            .all(|n| ledger.get(&n.name).is_none()));
+        assert_eq!(ledger.notes().len(), 1);
+    }
+}
@@ -0,0 +1,276 @@
+use alloc::collections::BTreeSet;
+use alloc::vec;
+use alloc::vec::Vec;
+use iris_ztd::Digest;
+
+use super::builder::{BuildError, MissingUnlocks};
+use super::note::Name;
+use super::tx::{NockchainTx, PkhSignature, RawTx, Spend};
+use super::verify::{check_signatures, VerifyError};
+
+/// Outcome of merging co-signer signatures into a transaction.
+#[derive(Clone, Debug, Default)]
+pub struct MergeReport {
+    /// Signature entries that were not merged, because they failed verification.
+    pub rejected: Vec<VerifyError>,
+    /// Spends that still lack signatures, with the signatures they are missing.
+    pub under_threshold: Vec<(Name, Vec<MissingUnlocks>)>,
+}
+
+impl MergeReport {
+    /// Whether every spend has enough signatures to satisfy its `Pkh` locks.
+    pub fn is_complete(&self) -> bool {
+        self.under_threshold.is_empty()
+    }
+}
+
+/// Merge the verified signatures of `others` into `spend`.
+///
+/// Signatures are only kept while some `Pkh` lock containing the signer still needs them, so that
+/// the witness does not grow past what each lock's `m` requires.
+fn merge_spend<'a>(
+    name: &Name,
+    spend: &mut Spend,
+    others: impl Iterator<Item = &'a Spend>,
+    report: &mut MergeReport,
+) {
+    let sig_hash = spend.sig_hash();
+    let spend_condition = spend.witness.lock_merkle_proof.spend_condition.clone();
+    let locks = spend_condition.pkh().collect::<Vec<_>>();
+
+    let candidates = core::mem::take(&mut spend.witness.pkh_signature.0)
+        .into_iter()
+        .chain(others.flat_map(|s| s.witness.pkh_signature.0.iter().cloned()))
+        .collect::<Vec<_>>();
+    let results = check_signatures(
+        &candidates
+            .iter()
+            .map(|entry| (name, &spend_condition, &sig_hash, entry))
+            .collect::<Vec<_>>(),
+    );
+
+    let mut seen = BTreeSet::<Digest>::new();
+    let mut counts = vec![0u64; locks.len()];
+    let mut merged = vec![];
+
+    for (entry, result) in candidates.into_iter().zip(results) {
+        let pkh = entry.0;
+        if seen.contains(&pkh) {
+            continue;
+        }
+
+        // Only a verified entry claims its pkh, so a bad entry can't shadow a later good one.
+        if let Some(err) = result {
+            report.rejected.push(err);
+            continue;
+        }
+        seen.insert(pkh);
+
+        let mut needed = false;
+        for (p, count) in locks.iter().zip(counts.iter_mut()) {
+            if p.hashes.contains(&pkh) && *count < p.m {
+                *count += 1;
+                needed = true;
+            }
+        }
+        if needed {
+            merged.push(entry);
+        }
+    }
+
+    spend.witness.pkh_signature = PkhSignature(merged);
+
+    let missing = spend_condition
+        .missing_unlocks(&spend.witness)
+        .into_iter()
+        .filter(|u| matches!(u, MissingUnlocks::Pkh { .. }))
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        report.under_threshold.push((name.clone(), missing));
+    }
+}
+
+impl RawTx {
+    /// Merge signatures collected by co-signers into this transaction.
+    ///
+    /// All transactions must contain the same spends, with the same seeds and fees. Each signature
+    /// entry is checked against the spend's `sig_hash` and spend condition before it is merged,
+    /// and the transaction id is recalculated afterwards.
+    pub fn merge_signatures(&mut self, others: &[RawTx]) -> Result<MergeReport, BuildError> {
+        for other in others {
+            for (name, spend) in &self.spends.0 {
+                let matches = other
+                    .spends
+                    .0
+                    .iter()
+                    .find(|(n, _)| n == name)
+                    .is_some_and(|(_, s)| s.sig_hash() == spend.sig_hash());
+                if !matches {
+                    return Err(BuildError::SpendMismatch(name.clone()));
+                }
+            }
+            if let Some((name, _)) = other
+                .spends
+                .0
+                .iter()
+                .find(|(n, _)| !self.spends.0.iter().any(|(m, _)| m == n))
+            {
+                return Err(BuildError::SpendMismatch(name.clone()));
+            }
+        }
+
+        let mut report = MergeReport::default();
+
+        for (name, spend) in &mut self.spends.0 {
+            let theirs = others
+                .iter()
+                .filter_map(|tx| tx.spends.0.iter().find(|(n, _)| n == name).map(|(_, s)| s));
+            merge_spend(name, spend, theirs, &mut report);
+        }
+
+        self.id = self.calc_id();
+
+        Ok(report)
+    }
+}
+
+impl NockchainTx {
+    /// Merge witnesses collected by co-signers into this transaction.
+    ///
+    /// See [`RawTx::merge_signatures`].
+    pub fn merge_witness(&mut self, others: &[NockchainTx]) -> Result<MergeReport, BuildError> {
+        let mut raw = self.to_raw_tx();
+        let others = others.iter().map(|tx| tx.to_raw_tx()).collect::<Vec<_>>();
+        let report = raw.merge_signatures(&others)?;
+
+        let (spends, witness_data) = raw.spends.split_witness();
+        self.id = raw.id;
+        self.spends = spends;
+        self.witness_data = witness_data;
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Note, NoteData, Pkh, Source, SpendCondition, TxBuilder, Version};
+    use bip39::Mnemonic;
+    use iris_crypto::derive_master_key;
+    use iris_ztd::Hashable;
+
+    #[test]
+    fn test_merge_signatures() {
+        let mnemonic = Mnemonic::parse("dice domain inspire horse time initial monitor nature mass impose tone benefit vibrant dash kiss mosquito rice then color ribbon agent method drop fat").unwrap();
+        let master = derive_master_key(&mnemonic.to_seed(""));
+        let keys = (0..4)
+            .map(|i| master.derive_child(i).private_key.unwrap())
+            .collect::<Vec<_>>();
+
+        let spend_condition = SpendCondition::new_pkh(Pkh::new(
+            2,
+            keys[..3].iter().map(|k| k.public_key().hash()).collect(),
+        ));
+        let note = Note::new(
+            Version::V1,
+            13,
+            Name::new_v1(
+                spend_condition.hash(),
+                Source {
+                    hash: "2H7WHTE9dFXiGgx4J432DsCLuMovNkokfcnCGRg7utWGM9h13PgQvsH"
+                        .try_into()
+                        .unwrap(),
+                    is_coinbase: false,
+                },
+            ),
+            NoteData::empty(),
+            1 << 32,
+        );
+        let recipient = "2nEFkqYm51yfqsYgfRx72w8FF9bmWqnkJu8XqY8T7psXufjYNRxf5ME"
+            .try_into()
+            .unwrap();
+
+        let mut builder = TxBuilder::new(1 << 10);
+        builder
+            .simple_spend(
+                vec![(note.clone(), spend_condition)],
+                recipient,
+                1234567,
+                keys[0].public_key().hash(),
+                true,
+            )
+            .unwrap();
+        let signed_by = |i: usize| builder.clone().sign(&keys[i]).build().to_raw_tx();
+
+        // A key outside the lock cannot contribute a signature.
+        let mut stray = builder.build().to_raw_tx();
+        let spend = &mut stray.spends.0[0].1;
+        let signature = keys[3].sign(&spend.sig_hash());
+        spend.add_signature(keys[3].public_key(), signature);
+
+        let mut tx = signed_by(0);
+        let report = tx.merge_signatures(&[stray]).unwrap();
+        assert!(!report.is_complete());
+        assert!(matches!(
+            report.rejected[..],
+            [VerifyError::UnexpectedSignature { .. }]
+        ));
+        assert!(matches!(
+            report.under_threshold[0].1[..],
+            [MissingUnlocks::Pkh { num_sigs: 1, .. }]
+        ));
+
+        // Signatures beyond the lock's threshold are not merged.
+        let report = tx.merge_signatures(&[signed_by(1), signed_by(2)]).unwrap();
+        assert!(report.is_complete());
+        assert!(report.rejected.is_empty());
+        assert_eq!(tx.spends.0[0].1.witness.pkh_signature.0.len(), 2);
+
+        let expected = builder
+            .clone()
+            .sign(&keys[0])
+            .sign(&keys[1])
+            .build()
+            .to_raw_tx();
+        assert_eq!(tx.id, expected.id);
+        let notes = [(note.name.clone(), note)].into_iter().collect();
+        assert!(tx.verify(&notes, 1 << 10).is_valid());
+
+        // Forged signatures are rejected.
+        let mut forged = signed_by(1);
+        forged.spends.0[0].1.witness.pkh_signature.0[0].2 =
+            tx.spends.0[0].1.witness.pkh_signature.0[0].2.clone();
+        let mut tx = signed_by(0);
+        let report = tx.merge_signatures(&[forged.clone()]).unwrap();
+        assert!(matches!(
+            report.rejected[..],
+            [VerifyError::InvalidSignature { .. }]
+        ));
+
+        // A forged entry does not block a valid signature for the same pkh that comes after it.
+        let mut tx = signed_by(0);
+        let report = tx.merge_signatures(&[forged, signed_by(1)]).unwrap();
+        assert!(report.is_complete());
+        assert!(matches!(
+            report.rejected[..],
+            [VerifyError::InvalidSignature { .. }]
+        ));
+        assert_eq!(tx.id, expected.id);
+
+        // Transactions with different spends cannot be merged.
+        let mut other = signed_by(1);
+        other.spends.0[0].1.fee += 1;
+        assert!(matches!(
+            tx.merge_signatures(&[other]),
+            Err(BuildError::SpendMismatch(_))
+        ));
+
+        let mut nock_tx = signed_by(0).to_nockchain_tx();
+        let report = nock_tx
+            .merge_witness(&[signed_by(2).to_nockchain_tx()])
+            .unwrap();
+        assert!(report.is_complete());
+        assert_eq!(nock_tx.to_raw_tx().calc_id(), nock_tx.id);
+    }
+}
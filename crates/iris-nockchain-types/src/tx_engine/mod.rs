@@ -1,7 +1,23 @@
 pub mod builder;
+pub mod bundle;
+pub mod ledger;
+pub mod merge;
 pub mod note;
+pub mod policy;
+pub mod selection;
+pub mod summary;
 pub mod tx;
+pub mod verify;
+pub mod wallet;
 
 pub use builder::*;
+pub use bundle::*;
+pub use ledger::*;
+pub use merge::*;
 pub use note::*;
+pub use policy::*;
+pub use selection::*;
+pub use summary::*;
 pub use tx::*;
+pub use verify::*;
+pub use wallet::*;
@@ -1,3 +1,4 @@
+use alloc::collections::BTreeMap;
 use alloc::string::ToString;
 use alloc::vec::Vec;
 use alloc::{string::String, vec};
@@ -120,7 +121,7 @@ impl Hashable for NoteData {
     }
 }
 
-#[derive(Debug, Clone, Hashable, Serialize, Deserialize)]
+#[derive(Debug, Clone, Hashable, NounEncode, NounDecode, Serialize, Deserialize)]
 pub struct Note {
     pub version: Version,
     pub origin_page: BlockHeight,
@@ -152,6 +153,29 @@ pub type Nicks = u64;
 #[derive(Debug, Clone)]
 pub struct Balance(pub Vec<(Name, Note)>);
 
+impl Balance {
+    /// Pair up notes with the spend conditions that unlock them.
+    ///
+    /// A note is matched to a spend condition if its first name commits to that lock. Notes
+    /// that match none of `spend_conditions` are omitted.
+    pub fn with_spend_conditions(
+        &self,
+        spend_conditions: &[SpendCondition],
+    ) -> Vec<(Note, SpendCondition)> {
+        let by_first = spend_conditions
+            .iter()
+            .map(|sc| (sc.first_name(), sc))
+            .collect::<BTreeMap<_, _>>();
+        self.0
+            .iter()
+            .filter_map(|(name, note)| {
+                let sc = by_first.get(&name.first)?;
+                Some((note.clone(), (*sc).clone()))
+            })
+            .collect()
+    }
+}
+
 pub type BlockHeight = u64;
 
 #[derive(Debug, Clone)]
@@ -261,7 +285,7 @@ pub struct Source {
 }
 
 /// Timelock range (for both absolute and relative constraints)
-#[derive(Debug, Clone, Hashable, NounEncode, NounDecode)]
+#[derive(Debug, Clone, PartialEq, Eq, Hashable, NounEncode, NounDecode)]
 pub struct TimelockRange {
     pub min: Option<BlockHeight>,
     pub max: Option<BlockHeight>,
@@ -280,4 +304,22 @@ impl TimelockRange {
             max: None,
         }
     }
+
+    /// Whether `height` lies within the range. Both bounds are inclusive.
+    pub fn contains(&self, height: BlockHeight) -> bool {
+        self.min.is_none_or(|min| height >= min) && self.max.is_none_or(|max| height <= max)
+    }
+
+    /// Narrow the range down to heights that are also within `other`.
+    pub fn intersect(&self, other: &TimelockRange) -> TimelockRange {
+        let min = match (self.min, other.min) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        let max = match (self.max, other.max) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        Self { min, max }
+    }
 }
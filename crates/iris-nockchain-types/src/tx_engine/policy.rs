@@ -0,0 +1,516 @@
+//! Text representation of spend conditions and lock trees.
+//!
+//! A policy is either a single branch, or `or(...)` of 2, 4, 8 or 16 branches forming a lock tree.
+//! A branch is a single fragment, or `and(...)` of fragments, each compiling to one lock
+//! primitive:
+//!
+//! - `pkh(m, A, B, ...)`: `m` signatures out of the given public key hashes
+//! - `hax(D, ...)`: preimages of the given hashes
+//! - `brn()`: unspendable
+//! - `after(h)`, `before(h)`: absolute timelock, both inclusive, with `h > 0`
+//! - `after_rel(n)`, `before_rel(n)`: timelock relative to the note's origin page, with `n > 0`
+//! - `tim(rel_min, rel_max, abs_min, abs_max)`: full timelock, with `*` for unbounded and at least
+//!   one bound given
+//!
+//! For example, `or(pkh(2, A, B, C), and(pkh(1, A), after(1000)))`.
+//!
+//! Policies with `or` compile to multi-branch lock trees, which are not yet verified to be
+//! spendable on chain; see [`LockTree`].
+
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+use iris_ztd::Digest;
+
+use super::note::{BlockHeight, TimelockRange};
+use super::tx::{Hax, LockPrimitive, LockTim, LockTree, SpendCondition};
+use crate::Pkh;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyError {
+    /// Malformed input at the given byte offset.
+    Syntax {
+        pos: usize,
+        expected: &'static str,
+    },
+    UnknownFragment(String),
+    InvalidArgs(String),
+    InvalidDigest(String),
+    InvalidNumber(String),
+    /// A fragment was used where it is not allowed, such as `or` inside `and`.
+    Misplaced(String),
+    InvalidBranchCount(usize),
+    /// Calls are nested deeper than [`MAX_POLICY_DEPTH`], at the given byte offset.
+    TooDeep {
+        pos: usize,
+    },
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyError::Syntax { pos, expected } => {
+                write!(f, "Syntax error at {pos}: expected {expected}")
+            }
+            PolicyError::UnknownFragment(name) => write!(f, "Unknown fragment `{name}`"),
+            PolicyError::InvalidArgs(name) => write!(f, "Invalid arguments to `{name}`"),
+            PolicyError::InvalidDigest(d) => write!(f, "Invalid hash `{d}`"),
+            PolicyError::InvalidNumber(n) => write!(f, "Invalid number `{n}`"),
+            PolicyError::Misplaced(name) => write!(f, "`{name}` is not allowed here"),
+            PolicyError::InvalidBranchCount(n) => {
+                write!(f, "Lock trees need 1, 2, 4, 8 or 16 branches (got {n})")
+            }
+            PolicyError::TooDeep { pos } => {
+                write!(
+                    f,
+                    "Policy is nested deeper than {MAX_POLICY_DEPTH} calls at {pos}"
+                )
+            }
+        }
+    }
+}
+
+/// Deepest nesting of calls the parser accepts. Valid policies nest at most three deep, as in
+/// `or(and(pkh(...)))`, and the limit keeps hostile input from exhausting the stack.
+pub const MAX_POLICY_DEPTH: usize = 8;
+
+/// Untyped syntax tree of a policy.
+enum Expr {
+    Call(String, Vec<Expr>),
+    Word(String),
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.input[self.pos..].starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn word(&mut self) -> Result<String, PolicyError> {
+        self.skip_whitespace();
+        let rest = &self.input[self.pos..];
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '*'))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(PolicyError::Syntax {
+                pos: self.pos,
+                expected: "a fragment or argument",
+            });
+        }
+        self.pos += len;
+        Ok(rest[..len].to_string())
+    }
+
+    fn expr(&mut self, depth: usize) -> Result<Expr, PolicyError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let word = self.word()?;
+        if !self.eat('(') {
+            return Ok(Expr::Word(word));
+        }
+        if depth == MAX_POLICY_DEPTH {
+            return Err(PolicyError::TooDeep { pos: start });
+        }
+
+        let mut args = vec![];
+        if !self.eat(')') {
+            loop {
+                args.push(self.expr(depth + 1)?);
+                if self.eat(')') {
+                    break;
+                }
+                if !self.eat(',') {
+                    return Err(PolicyError::Syntax {
+                        pos: self.pos,
+                        expected: "`,` or `)`",
+                    });
+                }
+            }
+        }
+        Ok(Expr::Call(word, args))
+    }
+
+    fn parse(input: &str) -> Result<Expr, PolicyError> {
+        let mut parser = Parser { input, pos: 0 };
+        let expr = parser.expr(0)?;
+        parser.skip_whitespace();
+        if parser.pos != input.len() {
+            return Err(PolicyError::Syntax {
+                pos: parser.pos,
+                expected: "end of input",
+            });
+        }
+        Ok(expr)
+    }
+}
+
+fn digest(expr: &Expr) -> Result<Digest, PolicyError> {
+    match expr {
+        Expr::Word(w) => {
+            Digest::try_from(w.as_str()).map_err(|_| PolicyError::InvalidDigest(w.clone()))
+        }
+        Expr::Call(name, _) => Err(PolicyError::Misplaced(name.clone())),
+    }
+}
+
+fn height(expr: &Expr) -> Result<Option<BlockHeight>, PolicyError> {
+    match expr {
+        Expr::Word(w) if w == "*" => Ok(None),
+        Expr::Word(w) => w
+            .parse()
+            .map(Some)
+            .map_err(|_| PolicyError::InvalidNumber(w.clone())),
+        Expr::Call(name, _) => Err(PolicyError::Misplaced(name.clone())),
+    }
+}
+
+fn primitive(expr: &Expr) -> Result<LockPrimitive, PolicyError> {
+    let (name, args) = match expr {
+        Expr::Call(name, args) => (name, args),
+        Expr::Word(w) => return Err(PolicyError::UnknownFragment(w.clone())),
+    };
+    let invalid = || PolicyError::InvalidArgs(name.clone());
+
+    // Bounds are kept as written, so that a `0` bound printed by `Display` parses back to the
+    // same lock.
+    let tim = |rel: (Option<BlockHeight>, Option<BlockHeight>), abs: (_, _)| {
+        LockPrimitive::Tim(LockTim {
+            rel: TimelockRange {
+                min: rel.0,
+                max: rel.1,
+            },
+            abs: TimelockRange {
+                min: abs.0,
+                max: abs.1,
+            },
+        })
+    };
+    let single_height = || match &args[..] {
+        [h] => match height(h)? {
+            Some(h) if h > 0 => Ok(Some(h)),
+            _ => Err(invalid()),
+        },
+        _ => Err(invalid()),
+    };
+
+    Ok(match name.as_str() {
+        "pkh" => {
+            let [m, hashes @ ..] = &args[..] else {
+                return Err(invalid());
+            };
+            let m = height(m)?.ok_or_else(invalid)?;
+            let hashes = hashes.iter().map(digest).collect::<Result<Vec<_>, _>>()?;
+            if m == 0 || m > hashes.len() as u64 {
+                return Err(invalid());
+            }
+            LockPrimitive::Pkh(Pkh::new(m, hashes))
+        }
+        "hax" => {
+            if args.is_empty() {
+                return Err(invalid());
+            }
+            LockPrimitive::Hax(Hax(args.iter().map(digest).collect::<Result<_, _>>()?))
+        }
+        "brn" => {
+            if !args.is_empty() {
+                return Err(invalid());
+            }
+            LockPrimitive::Brn
+        }
+        "after" => tim((None, None), (single_height()?, None)),
+        "before" => tim((None, None), (None, single_height()?)),
+        "after_rel" => tim((single_height()?, None), (None, None)),
+        "before_rel" => tim((None, single_height()?), (None, None)),
+        "tim" => {
+            let [rel_min, rel_max, abs_min, abs_max] = &args[..] else {
+                return Err(invalid());
+            };
+            let bounds = [
+                height(rel_min)?,
+                height(rel_max)?,
+                height(abs_min)?,
+                height(abs_max)?,
+            ];
+            if bounds.iter().all(Option::is_none) {
+                return Err(invalid());
+            }
+            tim((bounds[0], bounds[1]), (bounds[2], bounds[3]))
+        }
+        "and" | "or" => return Err(PolicyError::Misplaced(name.clone())),
+        _ => return Err(PolicyError::UnknownFragment(name.clone())),
+    })
+}
+
+fn branch(expr: &Expr) -> Result<SpendCondition, PolicyError> {
+    match expr {
+        Expr::Call(name, args) if name == "and" => {
+            if args.is_empty() {
+                return Err(PolicyError::InvalidArgs(name.clone()));
+            }
+            Ok(SpendCondition(
+                args.iter().map(primitive).collect::<Result<_, _>>()?,
+            ))
+        }
+        _ => Ok(SpendCondition(vec![primitive(expr)?])),
+    }
+}
+
+impl FromStr for SpendCondition {
+    type Err = PolicyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        branch(&Parser::parse(s)?)
+    }
+}
+
+impl FromStr for LockTree {
+    type Err = PolicyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let branches = match Parser::parse(s)? {
+            Expr::Call(name, args) if name == "or" => {
+                args.iter().map(branch).collect::<Result<Vec<_>, _>>()?
+            }
+            expr => vec![branch(&expr)?],
+        };
+        let count = branches.len();
+        LockTree::new(branches).ok_or(PolicyError::InvalidBranchCount(count))
+    }
+}
+
+fn fmt_height(f: &mut fmt::Formatter<'_>, height: Option<BlockHeight>) -> fmt::Result {
+    match height {
+        Some(h) => write!(f, "{h}"),
+        None => write!(f, "*"),
+    }
+}
+
+fn fmt_list<T: fmt::Display>(f: &mut fmt::Formatter<'_>, items: &[T]) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{item}")?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for LockPrimitive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockPrimitive::Pkh(pkh) => {
+                write!(f, "pkh({}, ", pkh.m)?;
+                fmt_list(f, &pkh.hashes)?;
+                write!(f, ")")
+            }
+            LockPrimitive::Hax(hax) => {
+                write!(f, "hax(")?;
+                fmt_list(f, &hax.0)?;
+                write!(f, ")")
+            }
+            LockPrimitive::Brn => write!(f, "brn()"),
+            LockPrimitive::Tim(LockTim { rel, abs }) => {
+                // The shorthands reject `0`, so a `0` bound is only written out in full.
+                match (rel.min, rel.max, abs.min, abs.max) {
+                    (None, None, Some(h), None) if h > 0 => write!(f, "after({h})"),
+                    (None, None, None, Some(h)) if h > 0 => write!(f, "before({h})"),
+                    (Some(h), None, None, None) if h > 0 => write!(f, "after_rel({h})"),
+                    (None, Some(h), None, None) if h > 0 => write!(f, "before_rel({h})"),
+                    _ => {
+                        write!(f, "tim(")?;
+                        fmt_height(f, rel.min)?;
+                        write!(f, ", ")?;
+                        fmt_height(f, rel.max)?;
+                        write!(f, ", ")?;
+                        fmt_height(f, abs.min)?;
+                        write!(f, ", ")?;
+                        fmt_height(f, abs.max)?;
+                        write!(f, ")")
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for SpendCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0[..] {
+            [primitive] => write!(f, "{primitive}"),
+            primitives => {
+                write!(f, "and(")?;
+                fmt_list(f, primitives)?;
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl fmt::Display for LockTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.branches() {
+            [branch] => write!(f, "{branch}"),
+            branches => {
+                write!(f, "or(")?;
+                fmt_list(f, branches)?;
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+    use iris_ztd::Hashable;
+
+    const A: &str = "2nEFkqYm51yfqsYgfRx72w8FF9bmWqnkJu8XqY8T7psXufjYNRxf5ME";
+    const B: &str = "6psXufjYNRxffRx72w8FF9b5MYg8TEmWq2nEFkqYm51yfqsnkJu8XqX";
+    const C: &str = "2H7WHTE9dFXiGgx4J432DsCLuMovNkokfcnCGRg7utWGM9h13PgQvsH";
+
+    #[test]
+    fn test_policy_compile() {
+        let a: Digest = A.try_into().unwrap();
+        let b: Digest = B.try_into().unwrap();
+        let c: Digest = C.try_into().unwrap();
+
+        let multisig = SpendCondition::new_pkh(Pkh::new(2, vec![a, b, c]));
+        let recovery = SpendCondition(vec![
+            LockPrimitive::Pkh(Pkh::single(a)),
+            LockPrimitive::Tim(LockTim {
+                rel: TimelockRange::new(Some(100), None),
+                abs: TimelockRange::none(),
+            }),
+        ]);
+        let expected = LockTree::new(vec![multisig.clone(), recovery.clone()]).unwrap();
+
+        let text = format!("or(pkh(2, {A}, {B}, {C}),\n  and(pkh(1, {A}), after_rel(100)))");
+        let tree: LockTree = text.parse().unwrap();
+        assert_eq!(tree.root(), expected.root());
+        assert_eq!(
+            tree.to_string(),
+            format!("or(pkh(2, {A}, {B}, {C}), and(pkh(1, {A}), after_rel(100)))")
+        );
+
+        let sc: SpendCondition = format!("pkh(2,{A},{B},{C})").parse().unwrap();
+        assert_eq!(sc.hash(), multisig.hash());
+        // A single branch is the same as a plain lock.
+        let single: LockTree = multisig.to_string().parse().unwrap();
+        assert_eq!(single.root(), multisig.hash());
+    }
+
+    #[test]
+    fn test_policy_roundtrip() {
+        for text in [
+            "brn()".to_string(),
+            format!("hax({A}, {B})"),
+            format!("and(pkh(1, {A}), after(10), before(20))"),
+            format!("and(pkh(1, {A}), before_rel(5), tim(1, *, *, 50))"),
+            format!("or(brn(), pkh(1, {A}), hax({C}), and(pkh(1, {B}), after(1)))"),
+        ] {
+            let tree: LockTree = text.parse().unwrap();
+            assert_eq!(tree.to_string(), text);
+            let reparsed: LockTree = tree.to_string().parse().unwrap();
+            assert_eq!(reparsed.root(), tree.root());
+        }
+    }
+
+    #[test]
+    fn test_policy_display_parses() {
+        let a: Digest = A.try_into().unwrap();
+        let b: Digest = B.try_into().unwrap();
+        let mut primitives = vec![
+            LockPrimitive::Pkh(Pkh::new(1, vec![a, b])),
+            LockPrimitive::Hax(Hax(vec![a])),
+            LockPrimitive::Brn,
+        ];
+        // Every combination of timelock bounds that has at least one bound, including zeros.
+        for value in [0, 7] {
+            for mask in 1..16u32 {
+                let bound = |bit: u32| (mask & (1 << bit) != 0).then_some(value + bit as u64);
+                primitives.push(LockPrimitive::Tim(LockTim {
+                    rel: TimelockRange {
+                        min: bound(0),
+                        max: bound(1),
+                    },
+                    abs: TimelockRange {
+                        min: bound(2),
+                        max: bound(3),
+                    },
+                }));
+            }
+        }
+
+        let conditions = primitives
+            .iter()
+            .map(|p| SpendCondition(vec![p.clone()]))
+            .chain([SpendCondition(primitives.clone())]);
+        for sc in conditions {
+            let text = sc.to_string();
+            let parsed: SpendCondition = text
+                .parse()
+                .unwrap_or_else(|e| panic!("`{text}` does not parse: {e}"));
+            assert_eq!(parsed.hash(), sc.hash(), "{text}");
+            assert_eq!(parsed.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn test_policy_errors() {
+        let err = |s: &str| s.parse::<LockTree>().unwrap_err();
+
+        assert_eq!(
+            err(&format!("or(pkh(1, {A}), brn(), brn())")),
+            PolicyError::InvalidBranchCount(3)
+        );
+        assert_eq!(
+            err(&format!("pkh(3, {A}, {B})")),
+            PolicyError::InvalidArgs("pkh".into())
+        );
+        assert_eq!(err("after(0)"), PolicyError::InvalidArgs("after".into()));
+        assert_eq!(err("and()"), PolicyError::InvalidArgs("and".into()));
+        assert_eq!(
+            err("tim(*, *, *, *)"),
+            PolicyError::InvalidArgs("tim".into())
+        );
+        assert_eq!(
+            err("and(or(brn(), brn()))"),
+            PolicyError::Misplaced("or".into())
+        );
+        assert_eq!(
+            err("pkh(1, 0OIl)"),
+            PolicyError::InvalidDigest("0OIl".into())
+        );
+        assert_eq!(err("multi()"), PolicyError::UnknownFragment("multi".into()));
+        assert_eq!(err("brn"), PolicyError::UnknownFragment("brn".into()));
+        assert!(matches!(err("brn() x"), PolicyError::Syntax { pos: 6, .. }));
+        assert!(matches!(err("and(brn()"), PolicyError::Syntax { .. }));
+        let nested = "and(".repeat(100_000);
+        assert_eq!(err(&nested), PolicyError::TooDeep { pos: 32 });
+        let nested = format!("{}brn(){}", "and(".repeat(7), ")".repeat(7));
+        assert_eq!(err(&nested), PolicyError::Misplaced("and".into()));
+        assert!(matches!(
+            "or(brn(), brn())".parse::<SpendCondition>(),
+            Err(PolicyError::Misplaced(_))
+        ));
+    }
+}
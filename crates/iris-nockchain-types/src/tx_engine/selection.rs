@@ -0,0 +1,321 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::note::Note;
+use super::tx::SpendCondition;
+use crate::Nicks;
+
+/// A note that may be selected as a transaction input.
+#[derive(Debug, Clone)]
+pub struct SelectionCandidate {
+    pub note: Note,
+    pub spend_condition: SpendCondition,
+    /// Estimated fee for including this note as an input.
+    pub fee: Nicks,
+}
+
+impl SelectionCandidate {
+    /// Value this note contributes towards the gift, after paying for itself.
+    pub fn effective_value(&self) -> Nicks {
+        self.note.assets.saturating_sub(self.fee)
+    }
+}
+
+/// What the selected inputs must pay for, on top of their own fees.
+#[derive(Debug, Clone)]
+pub struct SelectionTarget {
+    pub gift: Nicks,
+    /// Fee for the refund seed, if one is needed.
+    pub change_fee: Nicks,
+}
+
+/// Strategy for choosing which notes to spend.
+pub trait CoinSelector {
+    /// Pick candidates that cover `target`, returning their indices in spending order.
+    ///
+    /// Returns `None` if the candidates are insufficient.
+    fn select(
+        &self,
+        candidates: &[SelectionCandidate],
+        target: &SelectionTarget,
+    ) -> Option<Vec<usize>>;
+}
+
+fn select_in_order(
+    candidates: &[SelectionCandidate],
+    target: &SelectionTarget,
+    order: impl Iterator<Item = usize>,
+) -> Option<Vec<usize>> {
+    let needed = target.gift.saturating_add(target.change_fee);
+    let mut selected = vec![];
+    let mut sum: Nicks = 0;
+
+    for i in order {
+        if sum >= needed {
+            break;
+        }
+        let value = candidates[i].effective_value();
+        if value == 0 {
+            continue;
+        }
+        sum = sum.saturating_add(value);
+        selected.push(i);
+    }
+
+    // If we ran out of notes before covering the refund seed, the excess goes to fee instead.
+    if sum >= target.gift {
+        Some(selected)
+    } else {
+        None
+    }
+}
+
+/// Spend the largest notes first, minimizing the number of inputs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LargestFirst;
+
+impl CoinSelector for LargestFirst {
+    fn select(
+        &self,
+        candidates: &[SelectionCandidate],
+        target: &SelectionTarget,
+    ) -> Option<Vec<usize>> {
+        let mut order = (0..candidates.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| core::cmp::Reverse(candidates[i].effective_value()));
+        select_in_order(candidates, target, order.into_iter())
+    }
+}
+
+/// Spend the smallest notes first, consolidating dust at the cost of higher fees.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SmallestFirst;
+
+impl CoinSelector for SmallestFirst {
+    fn select(
+        &self,
+        candidates: &[SelectionCandidate],
+        target: &SelectionTarget,
+    ) -> Option<Vec<usize>> {
+        let mut order = (0..candidates.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| candidates[i].effective_value());
+        select_in_order(candidates, target, order.into_iter())
+    }
+}
+
+/// Spend the notes with the lowest `origin_page` first.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OldestFirst;
+
+impl CoinSelector for OldestFirst {
+    fn select(
+        &self,
+        candidates: &[SelectionCandidate],
+        target: &SelectionTarget,
+    ) -> Option<Vec<usize>> {
+        let mut order = (0..candidates.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| candidates[i].note.origin_page);
+        select_in_order(candidates, target, order.into_iter())
+    }
+}
+
+/// Search for a set of notes that covers the gift without needing a refund seed.
+///
+/// Any excess below `change_fee` is given up as fee, as creating the refund would cost more. If no
+/// such set is found within `max_tries` steps, selection falls back to `LargestFirst`.
+#[derive(Debug, Clone, Copy)]
+pub struct BranchAndBound {
+    pub max_tries: usize,
+}
+
+impl Default for BranchAndBound {
+    fn default() -> Self {
+        Self { max_tries: 100_000 }
+    }
+}
+
+struct BnbSearch<'a> {
+    /// Candidate indices with their effective values, largest first.
+    values: &'a [(usize, Nicks)],
+    gift: Nicks,
+    upper: Nicks,
+    tries: usize,
+    selected: Vec<usize>,
+}
+
+impl BnbSearch<'_> {
+    /// Depth-first search over include/exclude decisions, starting at `values[i]`.
+    fn go(&mut self, i: usize, sum: Nicks, remaining: Nicks) -> bool {
+        if sum > self.upper {
+            return false;
+        }
+        if sum >= self.gift {
+            return true;
+        }
+        if i == self.values.len() || sum.saturating_add(remaining) < self.gift || self.tries == 0 {
+            return false;
+        }
+        self.tries -= 1;
+
+        let (idx, value) = self.values[i];
+        let remaining = remaining - value;
+
+        self.selected.push(idx);
+        if self.go(i + 1, sum.saturating_add(value), remaining) {
+            return true;
+        }
+        self.selected.pop();
+
+        self.go(i + 1, sum, remaining)
+    }
+}
+
+impl CoinSelector for BranchAndBound {
+    fn select(
+        &self,
+        candidates: &[SelectionCandidate],
+        target: &SelectionTarget,
+    ) -> Option<Vec<usize>> {
+        let mut values = candidates
+            .iter()
+            .map(|c| c.effective_value())
+            .enumerate()
+            .filter(|v| v.1 > 0)
+            .collect::<Vec<_>>();
+        values.sort_by_key(|v| core::cmp::Reverse(v.1));
+
+        let mut search = BnbSearch {
+            values: &values,
+            gift: target.gift,
+            upper: target.gift.saturating_add(target.change_fee),
+            tries: self.max_tries,
+            selected: vec![],
+        };
+        let remaining = values.iter().map(|v| v.1).fold(0, Nicks::saturating_add);
+
+        if search.go(0, 0, remaining) {
+            Some(search.selected)
+        } else {
+            LargestFirst.select(candidates, target)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Name, NoteData, Pkh, TxBuilder, Version};
+    use alloc::vec;
+    use bip39::Mnemonic;
+    use iris_crypto::derive_master_key;
+    use iris_ztd::{Digest, Hashable};
+
+    fn candidates(values: &[(Nicks, u64)]) -> Vec<SelectionCandidate> {
+        let digest: Digest = "2H7WHTE9dFXiGgx4J432DsCLuMovNkokfcnCGRg7utWGM9h13PgQvsH"
+            .try_into()
+            .unwrap();
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, &(assets, origin_page))| SelectionCandidate {
+                note: Note::new(
+                    Version::V1,
+                    origin_page,
+                    Name::new(digest, (i as u64).hash()),
+                    NoteData::empty(),
+                    assets,
+                ),
+                spend_condition: SpendCondition::new_pkh(Pkh::single(digest)),
+                fee: 10,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_strategies() {
+        let c = candidates(&[(510, 3), (110, 1), (1010, 4), (60, 2), (5, 0)]);
+        let target = SelectionTarget {
+            gift: 540,
+            change_fee: 20,
+        };
+
+        assert_eq!(LargestFirst.select(&c, &target), Some(vec![2]));
+        assert_eq!(SmallestFirst.select(&c, &target), Some(vec![3, 1, 0]));
+        assert_eq!(OldestFirst.select(&c, &target), Some(vec![1, 3, 0]));
+        // 500 + 50 lands within the change window, so no refund is needed.
+        assert_eq!(
+            BranchAndBound::default().select(&c, &target),
+            Some(vec![0, 3])
+        );
+
+        // Nothing fits the window, so branch-and-bound falls back to largest-first.
+        let target = SelectionTarget {
+            gift: 1450,
+            change_fee: 5,
+        };
+        assert_eq!(
+            BranchAndBound::default().select(&c, &target),
+            Some(vec![2, 0])
+        );
+
+        let target = SelectionTarget {
+            gift: 1651,
+            change_fee: 0,
+        };
+        assert_eq!(LargestFirst.select(&c, &target), None);
+        assert_eq!(BranchAndBound::default().select(&c, &target), None);
+    }
+
+    #[test]
+    fn test_simple_spend_with() {
+        let mnemonic = Mnemonic::parse("dice domain inspire horse time initial monitor nature mass impose tone benefit vibrant dash kiss mosquito rice then color ribbon agent method drop fat").unwrap();
+        let private_key = derive_master_key(&mnemonic.to_seed(""))
+            .private_key
+            .unwrap();
+        let pkh = private_key.public_key().hash();
+        let spend_condition = SpendCondition::new_pkh(Pkh::single(pkh));
+
+        let notes = candidates(&[(3000, 3), (100_000, 1), (50_000, 2)])
+            .into_iter()
+            .map(|c| (c.note, spend_condition.clone()))
+            .collect::<Vec<_>>();
+        let recipient = "2nEFkqYm51yfqsYgfRx72w8FF9bmWqnkJu8XqY8T7psXufjYNRxf5ME"
+            .try_into()
+            .unwrap();
+
+        let mut builder = TxBuilder::new(8);
+        builder
+            .simple_spend_with(&OldestFirst, notes.clone(), recipient, 60_000, pkh, false)
+            .unwrap()
+            .sign(&private_key)
+            .validate()
+            .unwrap();
+        assert_eq!(builder.all_spends().len(), 1);
+        let spend = builder.all_spends().values().next().unwrap();
+        assert!(spend.cur_refund().is_some());
+
+        // Spending everything but a few nicks of the largest note leaves no refund.
+        let mut builder = TxBuilder::new(8);
+        let fee = builder
+            .simple_spend_with(&LargestFirst, notes.clone(), recipient, 60_000, pkh, false)
+            .unwrap()
+            .cur_fee();
+        let mut builder = TxBuilder::new(8);
+        builder
+            .simple_spend_with(
+                &BranchAndBound::default(),
+                notes,
+                recipient,
+                100_000 - fee,
+                pkh,
+                false,
+            )
+            .unwrap()
+            .sign(&private_key)
+            .validate()
+            .unwrap();
+        assert_eq!(builder.all_spends().len(), 1);
+        let spend = builder.all_spends().values().next().unwrap();
+        assert!(spend.cur_refund().is_none());
+        assert!(spend.is_balanced());
+    }
+}
@@ -0,0 +1,351 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+use iris_ztd::{Digest, Hashable as HashableTrait};
+
+use super::bundle::TxBundle;
+use super::note::{Name, Note};
+use super::tx::{LockRoot, NockchainTx, RawTx, SpendCondition, TransactionDisplay};
+use crate::{Nicks, Pkh};
+
+/// Locks that belong to the wallet summarizing a transaction.
+#[derive(Debug, Clone, Default)]
+pub struct OwnedLocks {
+    pub pkhs: BTreeSet<Digest>,
+    pub spend_conditions: Vec<SpendCondition>,
+}
+
+impl OwnedLocks {
+    pub fn new(pkhs: impl IntoIterator<Item = Digest>) -> Self {
+        Self {
+            pkhs: pkhs.into_iter().collect(),
+            spend_conditions: Vec::new(),
+        }
+    }
+
+    pub fn with_spend_conditions(
+        mut self,
+        spend_conditions: impl IntoIterator<Item = SpendCondition>,
+    ) -> Self {
+        self.spend_conditions.extend(spend_conditions);
+        self
+    }
+
+    /// Whether a note locked to `lock_root` can be spent by us alone.
+    ///
+    /// `spend_condition` is the lock behind `lock_root`, if it is known.
+    pub fn owns(&self, lock_root: &Digest, spend_condition: Option<&SpendCondition>) -> bool {
+        if self.spend_conditions.iter().any(|s| s.hash() == *lock_root)
+            || self
+                .pkhs
+                .iter()
+                .any(|p| SpendCondition::new_pkh(Pkh::single(*p)).hash() == *lock_root)
+        {
+            return true;
+        }
+
+        let Some(spend_condition) = spend_condition else {
+            return false;
+        };
+        !spend_condition.brn()
+            && spend_condition.hax().next().is_none()
+            && spend_condition.pkh().next().is_some()
+            && spend_condition
+                .pkh()
+                .all(|p| p.hashes.iter().filter(|h| self.pkhs.contains(h)).count() as u64 >= p.m)
+    }
+}
+
+/// Something about a transaction that a user should double check before signing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SummaryWarning {
+    /// Funds are sent to a lock that can never be spent.
+    Burn(Digest),
+    /// Funds are sent to a lock that requires a hash preimage.
+    HashLock(Digest),
+    /// Funds are sent to a lock that can only be spent within a range of heights.
+    Timelocked(Digest),
+    /// Funds are sent to a lock root whose spend condition is unknown.
+    UnknownLock(Digest),
+    /// A note being spent was not provided, so the input total is unknown.
+    UnknownInput(Name),
+    /// The notes being spent do not add up to the outputs and fee, and the transaction will be
+    /// rejected.
+    Unbalanced { inputs: Nicks, spent: Nicks },
+}
+
+/// Funds flowing to a single lock.
+#[derive(Debug, Clone)]
+pub struct OutputSummary {
+    pub lock_root: Digest,
+    pub spend_condition: Option<SpendCondition>,
+    pub amount: Nicks,
+}
+
+/// What a transaction does, from the point of view of a wallet.
+#[derive(Debug, Clone)]
+pub struct TxSummary {
+    /// Total assets of the notes being spent, if they were all provided to
+    /// [`TxSummary::with_notes`].
+    pub total_inputs: Option<Nicks>,
+    /// Total of the gifts and fees of all spends, which the notes being spent must add up to.
+    pub total_spent: Nicks,
+    /// Outputs to locks that are not ours.
+    pub outputs: Vec<OutputSummary>,
+    /// Outputs flowing back to our own locks.
+    pub change: Vec<OutputSummary>,
+    pub fee: Nicks,
+    /// Fee paid per word of seed data and witness, rounded down.
+    pub fee_per_word: Nicks,
+    pub warnings: Vec<SummaryWarning>,
+}
+
+impl TxSummary {
+    /// Summarize `tx`, using `display` to resolve lock roots of the outputs, if available.
+    pub fn new(tx: &RawTx, display: Option<&TransactionDisplay>, ours: &OwnedLocks) -> Self {
+        let mut by_lock = BTreeMap::<Digest, OutputSummary>::new();
+        let mut total_spent: Nicks = 0;
+        let mut fee: Nicks = 0;
+        let mut words = 0;
+
+        for (_, spend) in &tx.spends.0 {
+            fee = fee.saturating_add(spend.fee);
+            total_spent = total_spent.saturating_add(spend.fee);
+            let (seed_words, witness_words) = spend.calc_words();
+            words += seed_words + witness_words;
+
+            for seed in &spend.seeds.0 {
+                total_spent = total_spent.saturating_add(seed.gift);
+
+                let lock_root = seed.lock_root.hash();
+                let spend_condition = match &seed.lock_root {
+                    LockRoot::Lock(sc) => Some(sc.clone()),
+                    LockRoot::Hash(_) => display
+                        .and_then(|d| d.outputs.get(&lock_root))
+                        .map(|m| m.lock.clone()),
+                };
+                let output = by_lock.entry(lock_root).or_insert(OutputSummary {
+                    lock_root,
+                    spend_condition,
+                    amount: 0,
+                });
+                output.amount = output.amount.saturating_add(seed.gift);
+            }
+        }
+
+        let mut outputs = Vec::new();
+        let mut change = Vec::new();
+        let mut warnings = Vec::new();
+
+        for (lock_root, output) in by_lock {
+            match &output.spend_condition {
+                Some(sc) => {
+                    if sc.brn() {
+                        warnings.push(SummaryWarning::Burn(lock_root));
+                    }
+                    if sc.hax().next().is_some() {
+                        warnings.push(SummaryWarning::HashLock(lock_root));
+                    }
+                    if sc.tim().next().is_some() {
+                        warnings.push(SummaryWarning::Timelocked(lock_root));
+                    }
+                }
+                None if !ours.owns(&lock_root, None) => {
+                    warnings.push(SummaryWarning::UnknownLock(lock_root));
+                }
+                None => {}
+            }
+
+            if ours.owns(&lock_root, output.spend_condition.as_ref()) {
+                change.push(output);
+            } else {
+                outputs.push(output);
+            }
+        }
+
+        Self {
+            total_inputs: None,
+            total_spent,
+            outputs,
+            change,
+            fee,
+            fee_per_word: fee.checked_div(words).unwrap_or(0),
+            warnings,
+        }
+    }
+
+    /// Fill in [`TxSummary::total_inputs`] from the notes being spent, which `tx` does not
+    /// carry, and warn if they are missing or do not balance the transaction.
+    pub fn with_notes(mut self, tx: &RawTx, notes: &BTreeMap<Name, Note>) -> Self {
+        let mut total: Nicks = 0;
+        let mut complete = true;
+        for (name, _) in &tx.spends.0 {
+            match notes.get(name) {
+                Some(note) => total = total.saturating_add(note.assets),
+                None => {
+                    complete = false;
+                    self.warnings
+                        .push(SummaryWarning::UnknownInput(name.clone()));
+                }
+            }
+        }
+        if complete {
+            self.total_inputs = Some(total);
+            if total != self.total_spent {
+                self.warnings.push(SummaryWarning::Unbalanced {
+                    inputs: total,
+                    spent: self.total_spent,
+                });
+            }
+        }
+        self
+    }
+
+    /// Total amount sent to locks that are not ours.
+    pub fn total_outputs(&self) -> Nicks {
+        self.outputs.iter().map(|o| o.amount).sum()
+    }
+
+    /// Total amount flowing back to our own locks.
+    pub fn total_change(&self) -> Nicks {
+        self.change.iter().map(|o| o.amount).sum()
+    }
+}
+
+impl RawTx {
+    pub fn summary(&self, ours: &OwnedLocks) -> TxSummary {
+        TxSummary::new(self, None, ours)
+    }
+}
+
+impl NockchainTx {
+    pub fn summary(&self, ours: &OwnedLocks) -> TxSummary {
+        TxSummary::new(&self.to_raw_tx(), Some(&self.display), ours)
+    }
+}
+
+impl TxBundle {
+    /// Summary of the transaction, including the input total, since bundles carry their notes.
+    pub fn summary(&self, ours: &OwnedLocks) -> TxSummary {
+        let tx = RawTx::new(self.spends());
+        TxSummary::new(&tx, None, ours).with_notes(&tx, &self.notes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Hax, LockPrimitive, LockTim, Name, Note, NoteData, Payment, TxBuilder, Version};
+    use alloc::vec;
+    use bip39::Mnemonic;
+    use iris_crypto::derive_master_key;
+
+    #[test]
+    fn test_summary() {
+        let mnemonic = Mnemonic::parse("dice domain inspire horse time initial monitor nature mass impose tone benefit vibrant dash kiss mosquito rice then color ribbon agent method drop fat").unwrap();
+        let private_key = derive_master_key(&mnemonic.to_seed(""))
+            .private_key
+            .unwrap();
+        let pkh = private_key.public_key().hash();
+        let spend_condition = SpendCondition::new_pkh(Pkh::single(pkh));
+        let note = Note::new(
+            Version::V1,
+            13,
+            Name::new(
+                spend_condition.first_name(),
+                "7yMzrJjkb2Xu8uURP7YB3DFcotttR8dKDXF1tSp2wJmmXUvLM7SYzvM"
+                    .try_into()
+                    .unwrap(),
+            ),
+            NoteData::empty(),
+            100_000,
+        );
+
+        let alice: Digest = "2nEFkqYm51yfqsYgfRx72w8FF9bmWqnkJu8XqY8T7psXufjYNRxf5ME"
+            .try_into()
+            .unwrap();
+        let alice_lock = SpendCondition::new_pkh(Pkh::single(alice));
+        let burn = SpendCondition(vec![LockPrimitive::Brn]);
+        let hashlock = SpendCondition(vec![
+            LockPrimitive::Pkh(Pkh::single(alice)),
+            LockPrimitive::Hax(Hax(vec![alice])),
+        ]);
+        let timelock = SpendCondition(vec![
+            LockPrimitive::Pkh(Pkh::single(pkh)),
+            LockPrimitive::Tim(LockTim::coinbase()),
+        ]);
+
+        let mut builder = TxBuilder::new(8);
+        let tx = builder
+            .batch_spend(
+                vec![(note.clone(), spend_condition)],
+                vec![
+                    Payment::new(alice_lock.clone(), 10_000),
+                    Payment::new(burn.clone(), 1_000),
+                    Payment::new(hashlock.clone(), 2_000),
+                    Payment::new(timelock.clone(), 3_000),
+                ],
+                pkh,
+                false,
+            )
+            .unwrap()
+            .sign(&private_key)
+            .build();
+
+        let ours = OwnedLocks::new([pkh]);
+        let summary = tx.summary(&ours);
+        assert_eq!(summary.total_inputs, None);
+        assert_eq!(summary.total_spent, 100_000);
+        assert_eq!(summary.total_outputs(), 13_000);
+        // Timelocked notes to our own key are still ours.
+        assert_eq!(summary.change.len(), 2);
+        assert_eq!(
+            summary.total_spent,
+            summary.total_outputs() + summary.total_change() + summary.fee
+        );
+        assert_eq!(summary.fee, tx.spends.0[0].1.fee);
+        assert!(summary.fee_per_word >= 8);
+
+        assert_eq!(summary.warnings.len(), 3);
+        for warning in [
+            SummaryWarning::Burn(burn.hash()),
+            SummaryWarning::HashLock(hashlock.hash()),
+            SummaryWarning::Timelocked(timelock.hash()),
+        ] {
+            assert!(summary.warnings.contains(&warning));
+        }
+
+        // The input total comes from the notes, and must match what the spends pay out.
+        let raw = tx.to_raw_tx();
+        let mut notes = BTreeMap::from([(note.name.clone(), note.clone())]);
+        let summary = raw.summary(&ours).with_notes(&raw, &notes);
+        assert_eq!(summary.total_inputs, Some(100_000));
+        assert_eq!(summary.warnings.len(), 3);
+        notes.get_mut(&note.name).unwrap().assets = 150_000;
+        let summary = raw.summary(&ours).with_notes(&raw, &notes);
+        assert_eq!(summary.total_inputs, Some(150_000));
+        assert!(summary.warnings.contains(&SummaryWarning::Unbalanced {
+            inputs: 150_000,
+            spent: 100_000
+        }));
+        let summary = raw.summary(&ours).with_notes(&raw, &BTreeMap::new());
+        assert_eq!(summary.total_inputs, None);
+        assert!(summary
+            .warnings
+            .contains(&SummaryWarning::UnknownInput(note.name.clone())));
+
+        // Without the lock itself, a lock root can only be recognized if it is ours.
+        let mut raw = tx.to_raw_tx();
+        for seed in &mut raw.spends.0[0].1.seeds.0 {
+            seed.lock_root = LockRoot::Hash(seed.lock_root.hash());
+        }
+        let summary = raw.summary(&ours);
+        assert_eq!(summary.change.len(), 1);
+        assert_eq!(summary.outputs.len(), 4);
+        assert!(summary
+            .warnings
+            .contains(&SummaryWarning::UnknownLock(alice_lock.hash())));
+        assert!(!summary.warnings.contains(&SummaryWarning::UnknownLock(
+            SpendCondition::new_pkh(Pkh::single(pkh)).hash()
+        )));
+    }
+}
@@ -6,10 +6,10 @@ use iris_crypto::{PublicKey, Signature};
 use iris_ztd::{Digest, Hashable as HashableTrait, Noun, NounDecode, NounEncode, ZMap, ZSet};
 use iris_ztd_derive::{Hashable, NounDecode, NounEncode};
 
-use super::note::{Name, Note, NoteData, Source, TimelockRange, Version};
+use super::note::{BlockHeight, Name, Note, NoteData, Source, TimelockRange, Version};
 use crate::{Nicks, Pkh};
 
-fn noun_words(n: &Noun) -> u64 {
+pub(crate) fn noun_words(n: &Noun) -> u64 {
     match n {
         Noun::Atom(_) => 1,
         Noun::Cell(l, r) => noun_words(l) + noun_words(r),
@@ -305,12 +305,16 @@ pub struct Witness {
 impl Witness {
     pub fn new(spend_condition: SpendCondition) -> Self {
         let root = spend_condition.hash();
+        Self::from_proof(LockMerkleProof {
+            spend_condition,
+            axis: 1,
+            proof: MerkleProof { root, path: vec![] },
+        })
+    }
+
+    pub fn from_proof(lock_merkle_proof: LockMerkleProof) -> Self {
         Self {
-            lock_merkle_proof: LockMerkleProof {
-                spend_condition,
-                axis: 1,
-                proof: MerkleProof { root, path: vec![] },
-            },
+            lock_merkle_proof,
             pkh_signature: PkhSignature(vec![]),
             hax_map: ZMap::new(),
             tim: (),
@@ -336,6 +340,28 @@ pub struct LockMerkleProof {
     pub proof: MerkleProof,
 }
 
+impl LockMerkleProof {
+    /// Whether `spend_condition` sits at `axis` of a lock tree with the proven root.
+    pub fn verify(&self) -> bool {
+        if self.axis == 0 || self.axis.ilog2() as usize != self.proof.path.len() {
+            return false;
+        }
+
+        let mut axis = self.axis;
+        let mut digest = self.spend_condition.hash();
+        for sibling in &self.proof.path {
+            digest = if axis & 1 == 0 {
+                (&digest, sibling).hash()
+            } else {
+                (sibling, &digest).hash()
+            };
+            axis >>= 1;
+        }
+
+        digest == self.proof.root
+    }
+}
+
 impl HashableTrait for LockMerkleProof {
     fn hash(&self) -> Digest {
         // NOTE: lmao
@@ -346,6 +372,82 @@ impl HashableTrait for LockMerkleProof {
     }
 }
 
+/// Merkle tree of alternative spend conditions, any one of which unlocks a note.
+///
+/// Trees have 1, 2, 4, 8 or 16 branches. A tree with a single branch has the same root as its
+/// spend condition.
+///
+/// Spending from trees with more than one branch has not been checked against a transaction
+/// accepted on chain, so the proof encoding may still differ from what nodes expect. Do not lock
+/// funds to such trees on mainnet yet.
+#[derive(Debug, Clone)]
+pub struct LockTree(Vec<SpendCondition>);
+
+impl LockTree {
+    pub const MAX_BRANCHES: usize = 16;
+
+    pub fn new(branches: Vec<SpendCondition>) -> Option<Self> {
+        if !branches.len().is_power_of_two() || branches.len() > Self::MAX_BRANCHES {
+            return None;
+        }
+        Some(Self(branches))
+    }
+
+    pub fn branches(&self) -> &[SpendCondition] {
+        &self.0
+    }
+
+    /// Hashes of each level of the tree, from the leaves up to the root.
+    fn levels(&self) -> Vec<Vec<Digest>> {
+        let mut levels = vec![self.0.iter().map(|sc| sc.hash()).collect::<Vec<_>>()];
+        while levels[levels.len() - 1].len() > 1 {
+            let next = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| (&pair[0], &pair[1]).hash())
+                .collect();
+            levels.push(next);
+        }
+        levels
+    }
+
+    pub fn root(&self) -> Digest {
+        self.levels().pop().unwrap()[0]
+    }
+
+    pub fn first_name(&self) -> Digest {
+        (true, self.root()).hash()
+    }
+
+    /// Axis of the branch at `index`, counting from the leftmost leaf.
+    pub fn axis(&self, index: usize) -> u64 {
+        (self.0.len() + index) as u64
+    }
+
+    /// Find the branch with the given spend condition.
+    pub fn position(&self, spend_condition: &SpendCondition) -> Option<usize> {
+        let hash = spend_condition.hash();
+        self.0.iter().position(|sc| sc.hash() == hash)
+    }
+
+    /// Prove that the branch at `index` belongs to this tree.
+    pub fn prove(&self, index: usize) -> Option<LockMerkleProof> {
+        let spend_condition = self.0.get(index)?.clone();
+        let mut levels = self.levels();
+        let root = levels.pop().unwrap()[0];
+        let path = levels
+            .iter()
+            .enumerate()
+            .map(|(depth, level)| level[(index >> depth) ^ 1])
+            .collect();
+
+        Some(LockMerkleProof {
+            spend_condition,
+            axis: self.axis(index),
+            proof: MerkleProof { root, path },
+        })
+    }
+}
+
 #[derive(Debug, Clone, NounEncode, NounDecode, Hashable)]
 pub struct MerkleProof {
     pub root: Digest,
@@ -397,6 +499,13 @@ impl SpendCondition {
     pub fn brn(&self) -> bool {
         self.0.iter().any(|v| matches!(v, LockPrimitive::Brn))
     }
+
+    /// Range of heights at which a note created at `origin_page` satisfies all timelocks.
+    pub fn timelock_range(&self, origin_page: BlockHeight) -> TimelockRange {
+        self.tim().fold(TimelockRange::none(), |acc, t| {
+            acc.intersect(&t.range(origin_page))
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -458,6 +567,15 @@ impl LockTim {
             abs: TimelockRange::none(),
         }
     }
+
+    /// Absolute range of heights at which a note created at `origin_page` may be spent.
+    pub fn range(&self, origin_page: BlockHeight) -> TimelockRange {
+        let rel = TimelockRange {
+            min: self.rel.min.map(|v| origin_page.saturating_add(v)),
+            max: self.rel.max.map(|v| origin_page.saturating_add(v)),
+        };
+        rel.intersect(&self.abs)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -916,4 +1034,71 @@ mod tests {
             "3j4vkn72mcpVtQrTgNnYyoF3rDuYax3aebT5axu3Qe16jm9x2wLtepW",
         );
     }
+
+    #[test]
+    fn test_lock_merkle_proof_hash_vector() {
+        // Proof of the second branch (axis 3) of a two-branch tree. The digest only pins our own
+        // encoding, and was not taken from a chain transaction.
+        // TODO: replace with a proof from a mined multi-branch spend, then drop the warning on
+        // `LockTree`.
+        let branches = (0..2u64)
+            .map(|i| SpendCondition::new_pkh(Pkh::single(i.hash())))
+            .collect::<Vec<_>>();
+        let proof = LockTree::new(branches).unwrap().prove(1).unwrap();
+        assert_eq!(proof.axis, 3);
+        check_hash(
+            "lock merkle proof",
+            &proof,
+            "Ch74hE2Jr5t4o1TBjDrEQ81LxsCtkSc6oAB3rw6QuuwJEKYFYVh6PGv",
+        );
+    }
+
+    #[test]
+    fn test_lock_tree() {
+        let branches = (0..8u64)
+            .map(|i| SpendCondition::new_pkh(Pkh::single(i.hash())))
+            .collect::<Vec<_>>();
+        assert!(LockTree::new(branches[..3].to_vec()).is_none());
+        assert!(LockTree::new(vec![]).is_none());
+
+        // A single branch tree is the same as an ordinary lock.
+        let single = LockTree::new(branches[..1].to_vec()).unwrap();
+        assert_eq!(single.root(), branches[0].hash());
+        let proof = single.prove(0).unwrap();
+        assert_eq!(
+            proof.hash(),
+            Witness::new(branches[0].clone()).lock_merkle_proof.hash()
+        );
+
+        let tree = LockTree::new(branches.clone()).unwrap();
+        let left = (&branches[0].hash(), &branches[1].hash()).hash();
+        let right = (&branches[2].hash(), &branches[3].hash()).hash();
+        assert_eq!(
+            tree.prove(0).unwrap().proof.path[..2],
+            [branches[1].hash(), right]
+        );
+        assert_eq!(
+            LockTree::new(branches[..4].to_vec()).unwrap().root(),
+            (&left, &right).hash()
+        );
+
+        for i in 0..8 {
+            let proof = tree.prove(i).unwrap();
+            assert_eq!(proof.axis, 8 + i as u64);
+            assert_eq!(proof.proof.root, tree.root());
+            assert!(proof.verify());
+
+            let mut wrong_axis = proof.clone();
+            wrong_axis.axis ^= 1;
+            assert!(!wrong_axis.verify());
+            // The hash commits to the axis mold, not to the axis itself.
+            assert_eq!(wrong_axis.hash(), proof.hash());
+
+            let mut wrong_branch = proof;
+            wrong_branch.spend_condition = branches[(i + 1) % 8].clone();
+            assert!(!wrong_branch.verify());
+        }
+        assert!(tree.prove(8).is_none());
+        assert_eq!(tree.position(&branches[5]), Some(5));
+    }
 }
@@ -0,0 +1,641 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use iris_crypto::{verify_batch, PublicKey, Signature};
+use iris_ztd::{Digest, Hashable as HashableTrait, Noun};
+
+use super::builder::MissingUnlocks;
+use super::note::{BlockHeight, Name, Note, Version};
+use super::tx::{NockchainTx, RawTx, Spend, SpendCondition, TxId};
+use crate::Nicks;
+
+/// A single problem found while verifying a transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerifyError {
+    InvalidVersion,
+    IdMismatch {
+        expected: TxId,
+        got: TxId,
+    },
+    NoteNotFound(Name),
+    InvalidLockProof(Name),
+    LockMismatch(Name),
+    ParentHashMismatch(Name),
+    PkhMismatch {
+        name: Name,
+        pkh: Digest,
+    },
+    InvalidSignature {
+        name: Name,
+        pkh: Digest,
+    },
+    UnexpectedSignature {
+        name: Name,
+        pkh: Digest,
+    },
+    InvalidPreimage {
+        name: Name,
+        digest: Digest,
+    },
+    UnexpectedPreimage {
+        name: Name,
+        digest: Digest,
+    },
+    Unbalanced {
+        name: Name,
+        assets: Nicks,
+        spent: Nicks,
+    },
+    MissingUnlocks {
+        name: Name,
+        unlocks: Vec<MissingUnlocks>,
+    },
+    InvalidFee(Nicks, Nicks),
+}
+
+impl core::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            VerifyError::InvalidVersion => write!(f, "Invalid RawTx version"),
+            VerifyError::IdMismatch { expected, got } => {
+                write!(
+                    f,
+                    "Transaction id mismatch (expected: {expected}, got: {got})"
+                )
+            }
+            VerifyError::NoteNotFound(name) => {
+                write!(f, "Unable to find note [{} {}]", name.first, name.last)
+            }
+            VerifyError::InvalidLockProof(name) => write!(
+                f,
+                "Lock merkle proof does not match spend condition of [{} {}]",
+                name.first, name.last
+            ),
+            VerifyError::LockMismatch(name) => write!(
+                f,
+                "Lock root does not match the name of note [{} {}]",
+                name.first, name.last
+            ),
+            VerifyError::ParentHashMismatch(name) => write!(
+                f,
+                "Seed parent hash does not match note [{} {}]",
+                name.first, name.last
+            ),
+            VerifyError::PkhMismatch { name, pkh } => write!(
+                f,
+                "Public key does not hash to {pkh} in spend of [{} {}]",
+                name.first, name.last
+            ),
+            VerifyError::InvalidSignature { name, pkh } => write!(
+                f,
+                "Invalid signature by {pkh} in spend of [{} {}]",
+                name.first, name.last
+            ),
+            VerifyError::UnexpectedSignature { name, pkh } => write!(
+                f,
+                "Signature by {pkh} is not required by spend of [{} {}]",
+                name.first, name.last
+            ),
+            VerifyError::InvalidPreimage { name, digest } => write!(
+                f,
+                "Preimage does not hash to {digest} in spend of [{} {}]",
+                name.first, name.last
+            ),
+            VerifyError::UnexpectedPreimage { name, digest } => write!(
+                f,
+                "Preimage for {digest} is not required by spend of [{} {}]",
+                name.first, name.last
+            ),
+            VerifyError::Unbalanced {
+                name,
+                assets,
+                spent,
+            } => write!(
+                f,
+                "Spend of [{} {}] is not balanced (assets: {assets}, gifts + fee: {spent})",
+                name.first, name.last
+            ),
+            VerifyError::MissingUnlocks { name, unlocks } => {
+                write!(
+                    f,
+                    "Spend of [{} {}] is not fully unlocked. The following unlocks are missing:",
+                    name.first, name.last
+                )?;
+                for u in unlocks {
+                    write!(f, "{u:?}")?;
+                }
+                Ok(())
+            }
+            VerifyError::InvalidFee(expected, got) => {
+                write!(
+                    f,
+                    "Insufficient fee for transaction (needed: {expected}, got: {got})"
+                )
+            }
+        }
+    }
+}
+
+/// Result of verifying a transaction against the notes it spends.
+///
+/// Verification does not stop at the first problem, so that a co-signer can see everything that
+/// is wrong with a transaction at once.
+#[derive(Clone, Debug, Default)]
+pub struct VerifyReport {
+    pub errors: Vec<VerifyError>,
+}
+
+impl VerifyReport {
+    /// Whether the transaction is fully valid and ready to be submitted.
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Whether the only remaining problems are missing unlocks.
+    ///
+    /// This is the state of a correct, but partially signed transaction.
+    pub fn is_well_formed(&self) -> bool {
+        self.errors
+            .iter()
+            .all(|e| matches!(e, VerifyError::MissingUnlocks { .. }))
+    }
+
+    /// Missing unlocks, per spend.
+    pub fn missing_unlocks(&self) -> impl Iterator<Item = (&Name, &[MissingUnlocks])> + '_ {
+        self.errors.iter().filter_map(|e| match e {
+            VerifyError::MissingUnlocks { name, unlocks } => Some((name, unlocks.as_slice())),
+            _ => None,
+        })
+    }
+
+    pub fn into_result(self) -> Result<(), Vec<VerifyError>> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
+}
+
+/// Atoms larger than a belt cannot be hashed, and must not reach `Noun::hash`.
+pub(super) fn noun_hashable(noun: &Noun) -> bool {
+    match noun {
+        Noun::Atom(a) => u64::try_from(a).is_ok(),
+        Noun::Cell(l, r) => noun_hashable(l) && noun_hashable(r),
+    }
+}
+
+/// Check that a signature entry's key matches its pkh, and that the spend condition wants it.
+fn check_signer(
+    name: &Name,
+    spend_condition: &SpendCondition,
+    (pkh, pk, _): &(Digest, PublicKey, Signature),
+) -> Option<VerifyError> {
+    if pk.hash() != *pkh {
+        Some(VerifyError::PkhMismatch {
+            name: name.clone(),
+            pkh: *pkh,
+        })
+    } else if !spend_condition.pkh().any(|p| p.hashes.contains(pkh)) {
+        Some(VerifyError::UnexpectedSignature {
+            name: name.clone(),
+            pkh: *pkh,
+        })
+    } else {
+        None
+    }
+}
+
+/// A signature entry of the spend `name`, with the spend condition and `sig_hash` it is checked
+/// against.
+pub(crate) type SignatureEntry<'a> = (
+    &'a Name,
+    &'a SpendCondition,
+    &'a Digest,
+    &'a (Digest, PublicKey, Signature),
+);
+
+/// Check signature entries against their spend conditions, returning the problem with each entry.
+///
+/// The signatures are verified together with [`verify_batch`], which is much cheaper than
+/// verifying them one at a time.
+pub(crate) fn check_signatures(entries: &[SignatureEntry<'_>]) -> Vec<Option<VerifyError>> {
+    let mut results = entries
+        .iter()
+        .map(|(name, spend_condition, _, entry)| check_signer(name, spend_condition, entry))
+        .collect::<Vec<_>>();
+
+    let pending = (0..entries.len())
+        .filter(|&i| results[i].is_none())
+        .collect::<Vec<_>>();
+    let items = pending
+        .iter()
+        .map(|&i| {
+            let (_, _, sig_hash, (_, pk, sig)) = entries[i];
+            (pk.clone(), *sig_hash, sig.clone())
+        })
+        .collect::<Vec<_>>();
+    if let Err(invalid) = verify_batch(&items) {
+        for j in invalid {
+            let (name, _, _, (pkh, _, _)) = entries[pending[j]];
+            results[pending[j]] = Some(VerifyError::InvalidSignature {
+                name: name.clone(),
+                pkh: *pkh,
+            });
+        }
+    }
+    results
+}
+
+/// Verify `spend` of `note`, given the results of [`check_signatures`] for its signature entries.
+fn verify_spend(
+    name: &Name,
+    spend: &Spend,
+    note: &Note,
+    sig_errors: Vec<Option<VerifyError>>,
+    height: Option<BlockHeight>,
+    errors: &mut Vec<VerifyError>,
+) {
+    let lmp = &spend.witness.lock_merkle_proof;
+    let spend_condition = &lmp.spend_condition;
+
+    if !lmp.verify() {
+        errors.push(VerifyError::InvalidLockProof(name.clone()));
+    }
+
+    if (true, lmp.proof.root).hash() != note.name.first {
+        errors.push(VerifyError::LockMismatch(name.clone()));
+    }
+
+    let note_hash = note.hash();
+    if spend.seeds.0.iter().any(|s| s.parent_hash != note_hash) {
+        errors.push(VerifyError::ParentHashMismatch(name.clone()));
+    }
+
+    let spent = spend
+        .seeds
+        .0
+        .iter()
+        .fold(spend.fee, |acc, s| acc.saturating_add(s.gift));
+    if spent != note.assets {
+        errors.push(VerifyError::Unbalanced {
+            name: name.clone(),
+            assets: note.assets,
+            spent,
+        });
+    }
+
+    // Only signatures that pass verification may count towards the pkh thresholds.
+    let mut witness = spend.witness.clone();
+    let mut sig_errors = sig_errors.into_iter();
+    witness.pkh_signature.0.retain(|_| {
+        let err = sig_errors.next().flatten();
+        let valid = err.is_none();
+        errors.extend(err);
+        valid
+    });
+
+    for (digest, preimage) in spend.witness.hax_map.clone() {
+        if !spend_condition.hax().any(|h| h.0.contains(&digest)) {
+            errors.push(VerifyError::UnexpectedPreimage {
+                name: name.clone(),
+                digest,
+            });
+        } else if !noun_hashable(&preimage) || preimage.hash() != digest {
+            errors.push(VerifyError::InvalidPreimage {
+                name: name.clone(),
+                digest,
+            });
+        }
+    }
+    witness.hax_map = spend
+        .witness
+        .hax_map
+        .clone()
+        .into_iter()
+        .filter(|(digest, preimage)| noun_hashable(preimage) && preimage.hash() == *digest)
+        .collect();
+
+    let mut unlocks = spend_condition.missing_unlocks(&witness);
+    if let Some(height) = height {
+        unlocks.extend(spend_condition.missing_timelock(note.origin_page, height));
+    }
+    if !unlocks.is_empty() {
+        errors.push(VerifyError::MissingUnlocks {
+            name: name.clone(),
+            unlocks,
+        });
+    }
+}
+
+impl RawTx {
+    /// Verify the transaction against the notes it spends, without a node.
+    ///
+    /// Checks the transaction id, lock proofs, signatures, hax preimages, per-spend balance and
+    /// the total fee, given the `fee_per_word` of the chain. Timelocks are not evaluated, see
+    /// [`RawTx::verify_at`].
+    pub fn verify(&self, notes: &BTreeMap<Name, Note>, fee_per_word: Nicks) -> VerifyReport {
+        self.verify_inner(notes, fee_per_word, None)
+    }
+
+    /// Like [`RawTx::verify`], but also checks that the timelocks allow spending at `height`.
+    ///
+    /// A timelock that is not yet (or no longer) satisfied is reported among the spend's
+    /// [`VerifyError::MissingUnlocks`], as [`MissingUnlocks::Tim`].
+    pub fn verify_at(
+        &self,
+        notes: &BTreeMap<Name, Note>,
+        fee_per_word: Nicks,
+        height: BlockHeight,
+    ) -> VerifyReport {
+        self.verify_inner(notes, fee_per_word, Some(height))
+    }
+
+    fn verify_inner(
+        &self,
+        notes: &BTreeMap<Name, Note>,
+        fee_per_word: Nicks,
+        height: Option<BlockHeight>,
+    ) -> VerifyReport {
+        let mut errors = vec![];
+
+        if self.version != Version::V1 {
+            errors.push(VerifyError::InvalidVersion);
+        }
+
+        let calc_id = self.calc_id();
+        if calc_id != self.id {
+            errors.push(VerifyError::IdMismatch {
+                expected: calc_id,
+                got: self.id,
+            });
+        }
+
+        // The signatures of all spends are verified in a single batch.
+        let sig_hashes = self
+            .spends
+            .0
+            .iter()
+            .map(|(_, spend)| spend.sig_hash())
+            .collect::<Vec<_>>();
+        let entries = self
+            .spends
+            .0
+            .iter()
+            .zip(&sig_hashes)
+            .flat_map(|((name, spend), sig_hash)| {
+                let spend_condition = &spend.witness.lock_merkle_proof.spend_condition;
+                spend
+                    .witness
+                    .pkh_signature
+                    .0
+                    .iter()
+                    .map(move |entry| (name, spend_condition, sig_hash, entry))
+            })
+            .collect::<Vec<_>>();
+        let mut sig_errors = check_signatures(&entries).into_iter();
+
+        for (name, spend) in &self.spends.0 {
+            let spend_sig_errors = sig_errors
+                .by_ref()
+                .take(spend.witness.pkh_signature.0.len())
+                .collect();
+            match notes.get(name) {
+                Some(note) => {
+                    verify_spend(name, spend, note, spend_sig_errors, height, &mut errors)
+                }
+                None => errors.push(VerifyError::NoteNotFound(name.clone())),
+            }
+        }
+
+        let needed_fee = self.spends.fee(fee_per_word);
+        let cur_fee = self
+            .spends
+            .0
+            .iter()
+            .fold(0u64, |acc, (_, s)| acc.saturating_add(s.fee));
+        if cur_fee < needed_fee {
+            errors.push(VerifyError::InvalidFee(needed_fee, cur_fee));
+        }
+
+        VerifyReport { errors }
+    }
+}
+
+impl NockchainTx {
+    /// See [`RawTx::verify`].
+    pub fn verify(&self, notes: &BTreeMap<Name, Note>, fee_per_word: Nicks) -> VerifyReport {
+        let mut tx = self.to_raw_tx();
+        tx.version = self.version.clone();
+        tx.verify(notes, fee_per_word)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Pkh, Source, SpendCondition, TxBuilder};
+    use alloc::vec;
+    use bip39::Mnemonic;
+    use iris_crypto::{derive_master_key, PrivateKey};
+
+    fn keys() -> (PrivateKey, PrivateKey) {
+        let mnemonic = Mnemonic::parse("dice domain inspire horse time initial monitor nature mass impose tone benefit vibrant dash kiss mosquito rice then color ribbon agent method drop fat").unwrap();
+        let ek = derive_master_key(&mnemonic.to_seed(""));
+        let child = ek.derive_child(0);
+        (ek.private_key.unwrap(), child.private_key.unwrap())
+    }
+
+    fn note_for(spend_condition: &SpendCondition, assets: Nicks) -> Note {
+        let source = Source {
+            hash: "2H7WHTE9dFXiGgx4J432DsCLuMovNkokfcnCGRg7utWGM9h13PgQvsH"
+                .try_into()
+                .unwrap(),
+            is_coinbase: false,
+        };
+        Note::new(
+            Version::V1,
+            13,
+            Name::new_v1(spend_condition.hash(), source),
+            crate::NoteData::empty(),
+            assets,
+        )
+    }
+
+    #[test]
+    fn test_verify_signed_tx() {
+        let (key_a, key_b) = keys();
+        let spend_condition = SpendCondition::new_pkh(Pkh::new(
+            2,
+            vec![key_a.public_key().hash(), key_b.public_key().hash()],
+        ));
+        let note = note_for(&spend_condition, 1 << 32);
+        let notes = BTreeMap::from([(note.name.clone(), note.clone())]);
+        let recipient = "2nEFkqYm51yfqsYgfRx72w8FF9bmWqnkJu8XqY8T7psXufjYNRxf5ME"
+            .try_into()
+            .unwrap();
+
+        let fee_per_word = 1 << 10;
+        let mut builder = TxBuilder::new(fee_per_word);
+        builder
+            .simple_spend(
+                vec![(note, spend_condition)],
+                recipient,
+                1234567,
+                key_a.public_key().hash(),
+                false,
+            )
+            .unwrap();
+
+        // Partially signed: well formed, but one signature short.
+        builder.sign(&key_a);
+        let report = builder.build().verify(&notes, fee_per_word);
+        assert!(!report.is_valid());
+        assert!(report.is_well_formed());
+        let (_, unlocks) = report.missing_unlocks().next().unwrap();
+        assert!(matches!(unlocks, [MissingUnlocks::Pkh { num_sigs: 1, .. }]));
+
+        builder.sign(&key_b);
+        let tx = builder.build();
+        assert!(tx.verify(&notes, fee_per_word).is_valid());
+
+        // A higher fee rate than the one the tx was built with must be rejected.
+        let report = tx.verify(&notes, fee_per_word * 2);
+        assert!(matches!(report.errors[..], [VerifyError::InvalidFee(..)]));
+
+        // Tampering with the fee breaks balance, signatures and the id.
+        let mut raw = tx.to_raw_tx();
+        raw.spends.0[0].1.fee += 1;
+        let report = raw.verify(&notes, fee_per_word);
+        assert!(report
+            .errors
+            .iter()
+            .any(|e| matches!(e, VerifyError::IdMismatch { .. })));
+        assert!(report
+            .errors
+            .iter()
+            .any(|e| matches!(e, VerifyError::Unbalanced { .. })));
+        assert_eq!(
+            report
+                .errors
+                .iter()
+                .filter(|e| matches!(e, VerifyError::InvalidSignature { .. }))
+                .count(),
+            2
+        );
+        assert!(!report.is_well_formed());
+
+        assert_eq!(
+            tx.verify(&BTreeMap::new(), fee_per_word).errors,
+            vec![VerifyError::NoteNotFound(
+                notes.keys().next().unwrap().clone()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_verify_signature_batch() {
+        let (key_a, _) = keys();
+        let pkh = key_a.public_key().hash();
+        let spend_condition = SpendCondition::new_pkh(Pkh::single(pkh));
+        let first = note_for(&spend_condition, 1 << 32);
+        let mut second = first.clone();
+        second.name = Name::new_v1(
+            spend_condition.hash(),
+            Source {
+                hash: 7u64.hash(),
+                is_coinbase: false,
+            },
+        );
+        let notes = BTreeMap::from([
+            (first.name.clone(), first.clone()),
+            (second.name.clone(), second.clone()),
+        ]);
+
+        let mut builder = TxBuilder::new(1);
+        builder
+            .simple_spend(
+                vec![(first, spend_condition.clone()), (second, spend_condition)],
+                pkh,
+                (1 << 32) + 1000,
+                pkh,
+                false,
+            )
+            .unwrap();
+        builder.sign(&key_a);
+        let tx = builder.build().to_raw_tx();
+        assert_eq!(tx.spends.0.len(), 2);
+        assert!(tx.verify(&notes, 1).is_valid());
+
+        // A bad signature in one spend is attributed to that spend only.
+        let mut raw = tx.clone();
+        raw.spends.0[1].1.witness.pkh_signature.0[0].2 =
+            raw.spends.0[0].1.witness.pkh_signature.0[0].2.clone();
+        raw.id = raw.calc_id();
+        let bad = raw.spends.0[1].0.clone();
+        let report = raw.verify(&notes, 1);
+        assert_eq!(
+            report.errors[0],
+            VerifyError::InvalidSignature {
+                name: bad.clone(),
+                pkh
+            }
+        );
+        assert!(matches!(
+            &report.errors[1..],
+            [VerifyError::MissingUnlocks { name, .. }] if *name == bad
+        ));
+    }
+
+    #[test]
+    fn test_verify_hax() {
+        let (key_a, _) = keys();
+        let preimage = Noun::Atom(42u64.into());
+        let spend_condition = SpendCondition(vec![
+            crate::LockPrimitive::Pkh(Pkh::single(key_a.public_key().hash())),
+            crate::LockPrimitive::Hax(crate::Hax(vec![preimage.hash()])),
+        ]);
+        let note = note_for(&spend_condition, 1 << 32);
+        let notes = BTreeMap::from([(note.name.clone(), note.clone())]);
+
+        let mut builder = TxBuilder::new(1);
+        builder
+            .simple_spend(
+                vec![(note, spend_condition)],
+                key_a.public_key().hash(),
+                1000,
+                key_a.public_key().hash(),
+                false,
+            )
+            .unwrap();
+        builder.sign(&key_a);
+
+        let report = builder.build().verify(&notes, 1);
+        assert!(report.is_well_formed());
+        assert!(matches!(
+            report.missing_unlocks().next().unwrap().1,
+            [MissingUnlocks::Hax { .. }]
+        ));
+
+        // Preimages are not covered by the signature, so they can be added after signing.
+        builder.add_preimage(preimage.clone());
+        let tx = builder.build();
+        assert!(tx.verify(&notes, 1).is_valid());
+
+        let mut raw = tx.to_raw_tx();
+        let witness = &mut raw.spends.0[0].1.witness;
+        witness.hax_map = iris_ztd::ZMap::new();
+        witness
+            .hax_map
+            .insert(preimage.hash(), Noun::Atom(43u64.into()));
+        raw.id = raw.calc_id();
+        let report = raw.verify(&notes, 1);
+        assert!(matches!(
+            report.errors[..],
+            [
+                VerifyError::InvalidPreimage { .. },
+                VerifyError::MissingUnlocks { .. }
+            ]
+        ));
+    }
+}
@@ -0,0 +1,310 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+use iris_ztd::Digest;
+
+use super::builder::BuildError;
+use super::note::{Balance, BalanceUpdate, BlockHeight, Name, Note};
+use super::tx::{NockchainTx, TxId};
+use crate::Nicks;
+
+/// Changes between two successive balance snapshots.
+#[derive(Debug, Clone, Default)]
+pub struct BalanceDiff {
+    pub received: Vec<Note>,
+    pub spent: Vec<Note>,
+    /// Pending transactions whose inputs are all gone, most likely because they were mined.
+    ///
+    /// A conflicting transaction that spent exactly the same notes looks the same from the
+    /// balance alone.
+    pub confirmed: Vec<TxId>,
+    /// Pending transactions that can no longer be mined, because some of their inputs were
+    /// spent elsewhere while others are still unspent.
+    pub dropped: Vec<TxId>,
+}
+
+impl BalanceDiff {
+    pub fn is_empty(&self) -> bool {
+        self.received.is_empty()
+            && self.spent.is_empty()
+            && self.confirmed.is_empty()
+            && self.dropped.is_empty()
+    }
+}
+
+/// A transaction that was built, but has not been seen on chain yet.
+#[derive(Debug, Clone)]
+pub struct PendingTx {
+    pub id: TxId,
+    pub inputs: Vec<Name>,
+    /// Notes the transaction is predicted to create.
+    pub outputs: Vec<Note>,
+}
+
+/// Tracks notes across balance snapshots, along with notes reserved by pending transactions.
+#[derive(Debug, Clone, Default)]
+pub struct WalletState {
+    height: Option<BlockHeight>,
+    block_id: Option<Digest>,
+    notes: BTreeMap<Name, Note>,
+    pending: BTreeMap<TxId, PendingTx>,
+    reserved: BTreeMap<Name, TxId>,
+}
+
+impl WalletState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn height(&self) -> Option<BlockHeight> {
+        self.height
+    }
+
+    pub fn block_id(&self) -> Option<Digest> {
+        self.block_id
+    }
+
+    /// Replace the confirmed notes with `update`, and return what changed.
+    ///
+    /// Pending transactions whose inputs are no longer in the balance have either been included
+    /// in a block, or conflict with one that was. Either way, their reservations are released,
+    /// and they are reported in [`BalanceDiff::confirmed`] or [`BalanceDiff::dropped`].
+    pub fn apply_update(&mut self, update: BalanceUpdate) -> BalanceDiff {
+        let notes = update.notes.0.into_iter().collect::<BTreeMap<_, _>>();
+        let mut diff = BalanceDiff::default();
+
+        for (name, note) in &self.notes {
+            if !notes.contains_key(name) {
+                diff.spent.push(note.clone());
+            }
+        }
+        for (name, note) in &notes {
+            if !self.notes.contains_key(name) {
+                diff.received.push(note.clone());
+            }
+        }
+
+        self.height = Some(update.height);
+        self.block_id = Some(update.block_id);
+        self.notes = notes;
+
+        let gone = |n: &Name| !self.notes.contains_key(n) && !self.is_unconfirmed(n);
+        let settled = self
+            .pending
+            .values()
+            .filter(|tx| tx.inputs.iter().any(gone))
+            .map(|tx| (tx.id, tx.inputs.iter().all(gone)))
+            .collect::<Vec<_>>();
+        for (id, confirmed) in settled {
+            self.release(&id);
+            if confirmed {
+                diff.confirmed.push(id);
+            } else {
+                diff.dropped.push(id);
+            }
+        }
+
+        diff
+    }
+
+    /// Reserve the inputs of `tx`, and record its outputs as unconfirmed notes.
+    ///
+    /// Inputs may be confirmed notes, or unconfirmed outputs of other pending transactions. Fails
+    /// if an input is unknown, or already reserved by another transaction.
+    pub fn reserve(&mut self, tx: &NockchainTx) -> Result<&PendingTx, BuildError> {
+        let inputs = tx
+            .spends
+            .0
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+
+        for name in &inputs {
+            match self.reserved.get(name) {
+                Some(id) if *id != tx.id => return Err(BuildError::NoteReserved(name.clone())),
+                _ => {}
+            }
+            if !self.notes.contains_key(name) && !self.is_unconfirmed(name) {
+                return Err(BuildError::NoteNotFound(name.clone()));
+            }
+        }
+
+        for name in &inputs {
+            self.reserved.insert(name.clone(), tx.id);
+        }
+        let pending = PendingTx {
+            id: tx.id,
+            inputs,
+            outputs: tx.outputs(),
+        };
+        Ok(self.pending.entry(tx.id).or_insert(pending))
+    }
+
+    /// Drop a pending transaction, making its inputs spendable again.
+    pub fn release(&mut self, id: &TxId) -> Option<PendingTx> {
+        let pending = self.pending.remove(id)?;
+        for name in &pending.inputs {
+            if self.reserved.get(name) == Some(id) {
+                self.reserved.remove(name);
+            }
+        }
+        Some(pending)
+    }
+
+    pub fn pending(&self) -> impl Iterator<Item = &PendingTx> + '_ {
+        self.pending.values()
+    }
+
+    pub fn is_reserved(&self, name: &Name) -> bool {
+        self.reserved.contains_key(name)
+    }
+
+    fn is_unconfirmed(&self, name: &Name) -> bool {
+        self.pending
+            .values()
+            .any(|tx| tx.outputs.iter().any(|n| n.name == *name))
+    }
+
+    /// All confirmed notes, including reserved ones.
+    pub fn confirmed(&self) -> Balance {
+        Balance(
+            self.notes
+                .iter()
+                .map(|(name, note)| (name.clone(), note.clone()))
+                .collect(),
+        )
+    }
+
+    /// Confirmed notes that are not reserved by a pending transaction.
+    pub fn spendable(&self) -> Balance {
+        Balance(
+            self.notes
+                .iter()
+                .filter(|(name, _)| !self.is_reserved(name))
+                .map(|(name, note)| (name.clone(), note.clone()))
+                .collect(),
+        )
+    }
+
+    /// Predicted outputs of pending transactions that are not yet confirmed, nor reserved.
+    ///
+    /// This includes outputs to other wallets. Use [`Balance::with_spend_conditions`] to find
+    /// the ones that are ours.
+    pub fn unconfirmed(&self) -> Balance {
+        let mut seen = BTreeSet::new();
+        Balance(
+            self.pending
+                .values()
+                .flat_map(|tx| tx.outputs.iter())
+                .filter(|n| !self.notes.contains_key(&n.name) && !self.is_reserved(&n.name))
+                .filter(|n| seen.insert(n.name.clone()))
+                .map(|n| (n.name.clone(), n.clone()))
+                .collect(),
+        )
+    }
+
+    /// Total assets of spendable notes.
+    pub fn spendable_assets(&self) -> Nicks {
+        self.spendable().0.iter().map(|(_, n)| n.assets).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NoteData, Pkh, SpendCondition, TxBuilder, Version};
+    use alloc::vec;
+    use bip39::Mnemonic;
+    use iris_crypto::derive_master_key;
+    use iris_ztd::Hashable;
+
+    #[test]
+    fn test_wallet_state() {
+        let mnemonic = Mnemonic::parse("dice domain inspire horse time initial monitor nature mass impose tone benefit vibrant dash kiss mosquito rice then color ribbon agent method drop fat").unwrap();
+        let private_key = derive_master_key(&mnemonic.to_seed(""))
+            .private_key
+            .unwrap();
+        let pkh = private_key.public_key().hash();
+        let spend_condition = SpendCondition::new_pkh(Pkh::single(pkh));
+        let ours = [spend_condition.clone()];
+        let note = |i: u64, assets| {
+            Note::new(
+                Version::V1,
+                13,
+                Name::new(spend_condition.first_name(), i.hash()),
+                NoteData::empty(),
+                assets,
+            )
+        };
+        let update = |height, notes: Vec<Note>| BalanceUpdate {
+            height,
+            block_id: height.hash(),
+            notes: Balance(notes.into_iter().map(|n| (n.name.clone(), n)).collect()),
+        };
+        let recipient = "2nEFkqYm51yfqsYgfRx72w8FF9bmWqnkJu8XqY8T7psXufjYNRxf5ME"
+            .try_into()
+            .unwrap();
+
+        let mut state = WalletState::new();
+        let diff = state.apply_update(update(1, vec![note(0, 50_000), note(1, 20_000)]));
+        assert_eq!(diff.received.len(), 2);
+        assert!(diff.spent.is_empty());
+        assert_eq!(state.spendable_assets(), 70_000);
+
+        let send = |state: &WalletState, gift| {
+            let notes = state.spendable().with_spend_conditions(&ours);
+            let mut builder = TxBuilder::new(1);
+            builder
+                .simple_spend(notes, recipient, gift, pkh, false)
+                .unwrap()
+                .sign(&private_key)
+                .build()
+        };
+
+        let tx = send(&state, 30_000);
+        let pending = state.reserve(&tx).unwrap();
+        assert_eq!(pending.inputs, vec![note(0, 50_000).name]);
+        assert!(state.is_reserved(&note(0, 50_000).name));
+        assert_eq!(state.spendable_assets(), 20_000);
+        // The refund back to us is known, but not yet confirmed.
+        let change = state.unconfirmed().with_spend_conditions(&ours);
+        assert_eq!(change.len(), 1);
+
+        // A second send cannot pick the reserved note.
+        let tx2 = send(&state, 10_000);
+        state.reserve(&tx2).unwrap();
+        assert_eq!(state.spendable_assets(), 0);
+
+        // Dropping the second transaction frees its note.
+        state.release(&tx2.id).unwrap();
+        assert_eq!(state.spendable_assets(), 20_000);
+
+        // Spending a note reserved by another transaction fails.
+        let mut state2 = state.clone();
+        state2.release(&tx.id);
+        let conflicting = send(&state2, 40_000);
+        assert!(matches!(
+            state.reserve(&conflicting),
+            Err(BuildError::NoteReserved(_))
+        ));
+
+        // Once the transaction is mined, its input is gone and its change is confirmed.
+        let mut notes = vec![note(1, 20_000)];
+        notes.extend(change.into_iter().map(|(n, _)| n));
+        let diff = state.apply_update(update(2, notes));
+        assert_eq!(diff.spent.len(), 1);
+        assert_eq!(diff.received.len(), 1);
+        assert_eq!(diff.confirmed, vec![tx.id]);
+        assert!(diff.dropped.is_empty());
+        assert_eq!(state.pending().count(), 0);
+        assert!(state.unconfirmed().0.is_empty());
+        assert_eq!(state.height(), Some(2));
+
+        // A transaction is dropped once one of its inputs is spent elsewhere.
+        let tx3 = send(&state, 30_000);
+        assert_eq!(state.reserve(&tx3).unwrap().inputs.len(), 2);
+        let diff = state.apply_update(update(3, vec![note(1, 20_000)]));
+        assert!(diff.confirmed.is_empty());
+        assert_eq!(diff.dropped, vec![tx3.id]);
+        assert_eq!(state.spendable_assets(), 20_000);
+    }
+}
@@ -2,8 +2,15 @@ use ibig::UBig;
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
+use std::collections::BTreeMap;
+
 use iris_crypto::cheetah::{PrivateKey, PublicKey, Signature};
-use iris_crypto::slip10::{derive_master_key as derive_master_key_internal, ExtendedKey};
+use iris_crypto::keygen::{gen_master_key_with_params, gen_master_key_with_progress, KeygenParams};
+use iris_crypto::keystore::{KdfParams, Keystore, KeystoreContents, KeystoreError};
+use iris_crypto::slip10::{
+    derive_master_key as derive_master_key_internal, DerivationPath, DerivationPathError,
+    ExtendedKey,
+};
 
 #[wasm_bindgen(js_name = Signature)]
 #[derive(Clone, Serialize, Deserialize)]
@@ -47,7 +54,7 @@ impl WasmSignature {
 }
 
 #[wasm_bindgen(js_name = ExtendedKey)]
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct WasmExtendedKey {
     #[wasm_bindgen(skip)]
     pub private_key: Option<Vec<u8>>,
@@ -55,6 +62,12 @@ pub struct WasmExtendedKey {
     pub public_key: Vec<u8>,
     #[wasm_bindgen(skip)]
     pub chain_code: Vec<u8>,
+    #[wasm_bindgen(skip)]
+    pub depth: u8,
+    #[wasm_bindgen(skip)]
+    pub parent_fingerprint: [u8; 4],
+    #[wasm_bindgen(skip)]
+    pub child_number: u32,
 }
 
 #[wasm_bindgen(js_class = ExtendedKey)]
@@ -74,12 +87,79 @@ impl WasmExtendedKey {
         self.chain_code.clone()
     }
 
+    #[wasm_bindgen(getter)]
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    #[wasm_bindgen(getter, js_name = parentFingerprint)]
+    pub fn parent_fingerprint(&self) -> Vec<u8> {
+        self.parent_fingerprint.to_vec()
+    }
+
+    #[wasm_bindgen(getter, js_name = childNumber)]
+    pub fn child_number(&self) -> u32 {
+        self.child_number
+    }
+
+    /// Parse a base58check extended public or private key
+    #[wasm_bindgen(js_name = fromBase58)]
+    pub fn from_base58(s: &str) -> Result<WasmExtendedKey, JsValue> {
+        let key = ExtendedKey::from_base58(s).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(WasmExtendedKey::from_internal(&key))
+    }
+
+    /// Base58check extended public key, for watch-only wallets
+    #[wasm_bindgen(js_name = toXpub)]
+    pub fn to_xpub(&self) -> Result<String, JsValue> {
+        let extended_key = self.to_internal().map_err(|e| JsValue::from_str(&e))?;
+        Ok(extended_key.to_xpub())
+    }
+
+    /// Base58check extended private key, or undefined for public-only keys
+    #[wasm_bindgen(js_name = toXprv)]
+    pub fn to_xprv(&self) -> Result<Option<String>, JsValue> {
+        let extended_key = self.to_internal().map_err(|e| JsValue::from_str(&e))?;
+        Ok(extended_key.to_xprv())
+    }
+
+    /// The same key without its private key
+    #[wasm_bindgen(js_name = toPublic)]
+    pub fn to_public(&self) -> WasmExtendedKey {
+        WasmExtendedKey {
+            private_key: None,
+            ..self.clone()
+        }
+    }
+
+    /// Fingerprint of this key, as recorded in its children
+    pub fn fingerprint(&self) -> Result<Vec<u8>, JsValue> {
+        let extended_key = self.to_internal().map_err(|e| JsValue::from_str(&e))?;
+        Ok(extended_key.fingerprint().to_vec())
+    }
+
+    /// Derive the key at a path like "m/44'/0'/0/5", relative to this key
+    #[wasm_bindgen(js_name = derivePath)]
+    pub fn derive_path(&self, path: &str) -> Result<WasmExtendedKey, JsValue> {
+        let path: DerivationPath = path
+            .parse()
+            .map_err(|e: DerivationPathError| JsValue::from_str(&e.to_string()))?;
+        let extended_key = self.to_internal().map_err(|e| JsValue::from_str(&e))?;
+
+        let child = extended_key
+            .try_derive_path(&path)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(WasmExtendedKey::from_internal(&child))
+    }
+
     /// Derive a child key at the given index
     #[wasm_bindgen(js_name = deriveChild)]
     pub fn derive_child(&self, index: u32) -> Result<WasmExtendedKey, JsValue> {
         let extended_key = self.to_internal().map_err(|e| JsValue::from_str(&e))?;
 
-        let child = extended_key.derive_child(index);
+        let child = extended_key
+            .try_derive_child(index)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
         Ok(WasmExtendedKey::from_internal(&child))
     }
 
@@ -88,7 +168,10 @@ impl WasmExtendedKey {
             if pk_bytes.len() != 32 {
                 return Err("Private key must be 32 bytes".to_string());
             }
-            Some(PrivateKey(UBig::from_be_bytes(pk_bytes)))
+            Some(
+                PrivateKey::from_be_bytes(pk_bytes)
+                    .ok_or_else(|| "Private key is out of range".to_string())?,
+            )
         } else {
             None
         };
@@ -110,6 +193,9 @@ impl WasmExtendedKey {
             private_key,
             public_key,
             chain_code,
+            depth: self.depth,
+            parent_fingerprint: self.parent_fingerprint,
+            child_number: self.child_number,
         })
     }
 
@@ -118,8 +204,123 @@ impl WasmExtendedKey {
             private_key: key.private_key.as_ref().map(|pk| pk.to_be_bytes().to_vec()),
             public_key: key.public_key.to_be_bytes().to_vec(),
             chain_code: key.chain_code.to_vec(),
+            depth: key.depth,
+            parent_fingerprint: key.parent_fingerprint,
+            child_number: key.child_number,
+        }
+    }
+}
+
+#[wasm_bindgen(js_name = KeygenParams)]
+#[derive(Clone, Copy)]
+pub struct WasmKeygenParams {
+    inner: KeygenParams,
+}
+
+#[wasm_bindgen(js_class = KeygenParams)]
+impl WasmKeygenParams {
+    /// Argon2d parameters: memory in KiB, passes and lanes
+    #[wasm_bindgen(constructor)]
+    pub fn new(m_cost: u32, t_cost: u32, p_cost: u32) -> Self {
+        Self {
+            inner: KeygenParams::new(m_cost, t_cost, p_cost),
+        }
+    }
+
+    /// 768 MiB, 6 passes, 4 lanes, compatible with `nockchain-wallet keygen`
+    pub fn standard() -> Self {
+        Self {
+            inner: KeygenParams::STANDARD,
+        }
+    }
+
+    /// 128 MiB, 4 passes, 1 lane
+    pub fn browser() -> Self {
+        Self {
+            inner: KeygenParams::BROWSER,
+        }
+    }
+
+    /// Fast and insecure, for tests only
+    #[wasm_bindgen(js_name = insecureTesting)]
+    pub fn insecure_testing() -> Self {
+        Self {
+            inner: KeygenParams::INSECURE_TESTING,
         }
     }
+
+    #[wasm_bindgen(getter, js_name = mCost)]
+    pub fn m_cost(&self) -> u32 {
+        self.inner.m_cost
+    }
+
+    #[wasm_bindgen(getter, js_name = tCost)]
+    pub fn t_cost(&self) -> u32 {
+        self.inner.t_cost
+    }
+
+    #[wasm_bindgen(getter, js_name = pCost)]
+    pub fn p_cost(&self) -> u32 {
+        self.inner.p_cost
+    }
+}
+
+#[wasm_bindgen(js_name = GeneratedKey)]
+pub struct WasmGeneratedKey {
+    mnemonic: String,
+    master_key: WasmExtendedKey,
+}
+
+#[wasm_bindgen(js_class = GeneratedKey)]
+impl WasmGeneratedKey {
+    #[wasm_bindgen(getter)]
+    pub fn mnemonic(&self) -> String {
+        self.mnemonic.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = masterKey)]
+    pub fn master_key(&self) -> WasmExtendedKey {
+        self.master_key.clone()
+    }
+}
+
+/// Generate a mnemonic and master key from entropy and salt, using Argon2d
+///
+/// `onProgress(completed, total)` is called as the hash advances; returning `false` cancels key
+/// generation. Run this in a Web Worker to keep the page responsive.
+#[wasm_bindgen(js_name = genMasterKey)]
+pub fn gen_master_key(
+    entropy: &[u8],
+    salt: &[u8],
+    params: Option<WasmKeygenParams>,
+    on_progress: Option<js_sys::Function>,
+) -> Result<WasmGeneratedKey, JsValue> {
+    let params = params.map(|p| p.inner).unwrap_or_default();
+    let mut callback_error = None;
+    let result = match on_progress {
+        None => gen_master_key_with_params(entropy, salt, &params),
+        Some(on_progress) => gen_master_key_with_progress(entropy, salt, &params, |progress| {
+            match on_progress.call2(
+                &JsValue::NULL,
+                &progress.completed.into(),
+                &progress.total.into(),
+            ) {
+                Ok(ret) => ret.as_bool() != Some(false),
+                Err(e) => {
+                    callback_error = Some(e);
+                    false
+                }
+            }
+        }),
+    };
+    match result {
+        Ok((mnemonic, key)) => Ok(WasmGeneratedKey {
+            mnemonic,
+            master_key: WasmExtendedKey::from_internal(&key),
+        }),
+        // An exception thrown by the callback is rethrown as is.
+        Err(e) => Err(callback_error.unwrap_or_else(|| JsValue::from_str(&e.to_string()))),
+    }
 }
 
 /// Derive master key from seed bytes
@@ -185,7 +386,8 @@ pub fn sign_message(private_key_bytes: &[u8], message: &str) -> Result<WasmSigna
     if private_key_bytes.len() != 32 {
         return Err(JsValue::from_str("Private key must be 32 bytes"));
     }
-    let private_key = PrivateKey(UBig::from_be_bytes(private_key_bytes));
+    let private_key = PrivateKey::from_be_bytes(private_key_bytes)
+        .ok_or_else(|| JsValue::from_str("Private key is out of range"))?;
     let digest = Belt::from_bytes(message.as_bytes()).to_noun().hash();
     Ok(WasmSignature::from_internal(&private_key.sign(&digest)))
 }
@@ -207,3 +409,161 @@ pub fn verify_signature(
     let digest = Belt::from_bytes(message.as_bytes()).to_noun().hash();
     Ok(public_key.verify(&digest, &signature.to_internal()))
 }
+
+fn keystore_error(e: KeystoreError) -> JsValue {
+    JsValue::from_str(&e.to_string())
+}
+
+#[wasm_bindgen(js_name = KdfParams)]
+#[derive(Clone, Copy)]
+pub struct WasmKdfParams {
+    inner: KdfParams,
+}
+
+#[wasm_bindgen(js_class = KdfParams)]
+impl WasmKdfParams {
+    /// Argon2id parameters: memory in KiB, passes and lanes
+    #[wasm_bindgen(constructor)]
+    pub fn new(m_cost: u32, t_cost: u32, p_cost: u32) -> Self {
+        Self {
+            inner: KdfParams::new(m_cost, t_cost, p_cost),
+        }
+    }
+
+    #[wasm_bindgen(js_name = default)]
+    pub fn default_params() -> Self {
+        Self {
+            inner: KdfParams::default(),
+        }
+    }
+
+    #[wasm_bindgen(getter, js_name = mCost)]
+    pub fn m_cost(&self) -> u32 {
+        self.inner.m_cost
+    }
+
+    #[wasm_bindgen(getter, js_name = tCost)]
+    pub fn t_cost(&self) -> u32 {
+        self.inner.t_cost
+    }
+
+    #[wasm_bindgen(getter, js_name = pCost)]
+    pub fn p_cost(&self) -> u32 {
+        self.inner.p_cost
+    }
+}
+
+/// Password-encrypted keystore, in the same JSON format as native wallets
+#[wasm_bindgen(js_name = Keystore)]
+pub struct WasmKeystore {
+    inner: Keystore,
+}
+
+#[wasm_bindgen(js_class = Keystore)]
+impl WasmKeystore {
+    /// Encrypt a BIP39 mnemonic, with optional string labels
+    #[wasm_bindgen(js_name = encryptMnemonic)]
+    pub fn encrypt_mnemonic(
+        mnemonic: &str,
+        password: &str,
+        labels: JsValue,
+        kdf: Option<WasmKdfParams>,
+    ) -> Result<WasmKeystore, JsValue> {
+        let contents = KeystoreContents::from_mnemonic(mnemonic).map_err(keystore_error)?;
+        Self::encrypt(contents, password, labels, kdf)
+    }
+
+    /// Encrypt BIP39 entropy, with optional string labels
+    #[wasm_bindgen(js_name = encryptEntropy)]
+    pub fn encrypt_entropy(
+        entropy: &[u8],
+        password: &str,
+        labels: JsValue,
+        kdf: Option<WasmKdfParams>,
+    ) -> Result<WasmKeystore, JsValue> {
+        let contents = KeystoreContents::from_entropy(entropy).map_err(keystore_error)?;
+        Self::encrypt(contents, password, labels, kdf)
+    }
+
+    fn encrypt(
+        mut contents: KeystoreContents,
+        password: &str,
+        labels: JsValue,
+        kdf: Option<WasmKdfParams>,
+    ) -> Result<WasmKeystore, JsValue> {
+        if !labels.is_undefined() && !labels.is_null() {
+            contents.labels = serde_wasm_bindgen::from_value(labels)?;
+        }
+        let kdf = kdf.map(|k| k.inner).unwrap_or_default();
+        Ok(Self {
+            inner: Keystore::encrypt(&contents, password, kdf).map_err(keystore_error)?,
+        })
+    }
+
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json(json: &str) -> Result<WasmKeystore, JsValue> {
+        Ok(Self {
+            inner: Keystore::from_json(json).map_err(keystore_error)?,
+        })
+    }
+
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> String {
+        self.inner.to_json()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn version(&self) -> u32 {
+        self.inner.version
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn kdf(&self) -> WasmKdfParams {
+        WasmKdfParams {
+            inner: self.inner.kdf,
+        }
+    }
+
+    /// Decrypt the labels stored alongside the secret
+    pub fn labels(&self, password: &str) -> Result<JsValue, JsValue> {
+        let labels: BTreeMap<String, String> =
+            self.inner.decrypt(password).map_err(keystore_error)?.labels;
+        Ok(serde_wasm_bindgen::to_value(&labels)?)
+    }
+
+    #[wasm_bindgen(js_name = exportMnemonic)]
+    pub fn export_mnemonic(&self, password: &str) -> Result<String, JsValue> {
+        self.inner.export_mnemonic(password).map_err(keystore_error)
+    }
+
+    /// Decrypt the keystore and derive its master key
+    #[wasm_bindgen(js_name = deriveMasterKey)]
+    pub fn derive_master_key(
+        &self,
+        password: &str,
+        passphrase: Option<String>,
+    ) -> Result<WasmExtendedKey, JsValue> {
+        let key = self
+            .inner
+            .decrypt(password)
+            .and_then(|c| c.master_key(passphrase.as_deref().unwrap_or("")))
+            .map_err(keystore_error)?;
+        Ok(WasmExtendedKey::from_internal(&key))
+    }
+
+    /// Re-encrypt under a new password, optionally with new KDF parameters
+    #[wasm_bindgen(js_name = changePassword)]
+    pub fn change_password(
+        &self,
+        old_password: &str,
+        new_password: &str,
+        kdf: Option<WasmKdfParams>,
+    ) -> Result<WasmKeystore, JsValue> {
+        Ok(Self {
+            inner: self
+                .inner
+                .change_password(old_password, new_password, kdf.map(|k| k.inner))
+                .map_err(keystore_error)?,
+        })
+    }
+}
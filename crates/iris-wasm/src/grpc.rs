@@ -1,96 +1,134 @@
 use iris_grpc_proto::pb::common::v1::{Base58Hash, Base58Pubkey, PageRequest};
 use iris_grpc_proto::pb::common::v2 as pb_common_v2;
+use iris_grpc_proto::pb::public::v2::nockchain_service_client::NockchainServiceClient;
 use iris_grpc_proto::pb::public::v2::*;
+use iris_nockchain_types::{BalanceUpdate, Nicks};
 use tonic_web_wasm_client::Client;
 use wasm_bindgen::prelude::*;
 
+use crate::{WasmDigest, WasmName, WasmNote};
+
+/// Times to start over when the snapshot changes while paging through a balance
+const SNAPSHOT_RETRIES: u32 = 3;
+
+/// Delay before the first retry, growing linearly with each one, as in the native client
+const SNAPSHOT_RETRY_DELAY_MS: u32 = 500;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(handler: &js_sys::Function, timeout: i32) -> JsValue;
+}
+
+/// Resolve after `ms` milliseconds, using the global `setTimeout` of browsers, workers and Node
+async fn sleep(ms: u32) {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        set_timeout(&resolve, ms as i32);
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+/// Balance snapshot, with the notes of every page
+#[wasm_bindgen(js_name = Balance)]
+pub struct WasmBalance {
+    #[wasm_bindgen(skip)]
+    pub(crate) internal: BalanceUpdate,
+}
+
+#[wasm_bindgen(js_class = Balance)]
+impl WasmBalance {
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> u64 {
+        self.internal.height
+    }
+
+    #[wasm_bindgen(getter, js_name = blockId)]
+    pub fn block_id(&self) -> WasmDigest {
+        WasmDigest::from_internal(&self.internal.block_id)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn notes(&self) -> Vec<WasmNote> {
+        self.internal
+            .notes
+            .0
+            .iter()
+            .map(|(_, note)| WasmNote::from_internal(note.clone()))
+            .collect()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn names(&self) -> Vec<WasmName> {
+        self.internal
+            .notes
+            .0
+            .iter()
+            .map(|(name, _)| WasmName::from_internal(name))
+            .collect()
+    }
+
+    /// Total assets of all notes
+    #[wasm_bindgen(getter, js_name = totalAssets)]
+    pub fn total_assets(&self) -> Nicks {
+        self.internal.notes.0.iter().map(|(_, n)| n.assets).sum()
+    }
+}
+
+enum BalanceError {
+    SnapshotChanged,
+    Other(JsValue),
+}
+
+impl From<BalanceError> for JsValue {
+    fn from(err: BalanceError) -> Self {
+        match err {
+            BalanceError::SnapshotChanged => {
+                JsValue::from_str("Snapshot changed during pagination; retry")
+            }
+            BalanceError::Other(e) => e,
+        }
+    }
+}
+
 #[wasm_bindgen]
 pub struct GrpcClient {
-    endpoint: String,
+    client: NockchainServiceClient<Client>,
 }
 
 #[wasm_bindgen]
 impl GrpcClient {
     #[wasm_bindgen(constructor)]
     pub fn new(endpoint: String) -> Self {
-        Self { endpoint }
+        Self {
+            client: NockchainServiceClient::new(Client::new(endpoint)),
+        }
     }
 
     /// Get balance for a wallet address
     #[wasm_bindgen(js_name = getBalanceByAddress)]
-    pub async fn get_balance_by_address(&self, address: String) -> Result<JsValue, JsValue> {
-        let client = Client::new(self.endpoint.clone());
-        let mut grpc_client = nockchain_service_client::NockchainServiceClient::new(client);
-
-        let request = WalletGetBalanceRequest {
-            selector: Some(wallet_get_balance_request::Selector::Address(
-                Base58Pubkey { key: address },
-            )),
-            page: Some(PageRequest {
-                client_page_items_limit: 0,
-                page_token: String::new(),
-                max_bytes: 0,
-            }),
-        };
-
-        let response = grpc_client
-            .wallet_get_balance(request)
-            .await
-            .map_err(|e| JsValue::from_str(&format!("gRPC error: {}", e)))?
-            .into_inner();
-
-        match response.result {
-            Some(wallet_get_balance_response::Result::Balance(balance)) => {
-                serde_wasm_bindgen::to_value(&balance)
-                    .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
-            }
-            Some(wallet_get_balance_response::Result::Error(e)) => {
-                Err(JsValue::from_str(&format!("Server error: {}", e.message)))
-            }
-            None => Err(JsValue::from_str("Empty response from server")),
-        }
+    pub async fn get_balance_by_address(&self, address: String) -> Result<WasmBalance, JsValue> {
+        self.get_balance(wallet_get_balance_request::Selector::Address(
+            Base58Pubkey { key: address },
+        ))
+        .await
     }
 
     /// Get balance for a first name
     #[wasm_bindgen(js_name = getBalanceByFirstName)]
-    pub async fn get_balance_by_first_name(&self, first_name: String) -> Result<JsValue, JsValue> {
-        let client = Client::new(self.endpoint.clone());
-        let mut grpc_client = nockchain_service_client::NockchainServiceClient::new(client);
-
-        let request = WalletGetBalanceRequest {
-            selector: Some(wallet_get_balance_request::Selector::FirstName(
-                Base58Hash { hash: first_name },
-            )),
-            page: Some(PageRequest {
-                client_page_items_limit: 0,
-                page_token: String::new(),
-                max_bytes: 0,
-            }),
-        };
-
-        let response = grpc_client
-            .wallet_get_balance(request)
-            .await
-            .map_err(|e| JsValue::from_str(&format!("gRPC error: {}", e)))?
-            .into_inner();
-
-        match response.result {
-            Some(wallet_get_balance_response::Result::Balance(balance)) => {
-                serde_wasm_bindgen::to_value(&balance)
-                    .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
-            }
-            Some(wallet_get_balance_response::Result::Error(e)) => {
-                Err(JsValue::from_str(&format!("Server error: {}", e.message)))
-            }
-            None => Err(JsValue::from_str("Empty response from server")),
-        }
+    pub async fn get_balance_by_first_name(
+        &self,
+        first_name: String,
+    ) -> Result<WasmBalance, JsValue> {
+        self.get_balance(wallet_get_balance_request::Selector::FirstName(
+            Base58Hash { hash: first_name },
+        ))
+        .await
     }
 
     /// Send a transaction
     #[wasm_bindgen(js_name = sendTransaction)]
     pub async fn send_transaction(&self, raw_tx: JsValue) -> Result<JsValue, JsValue> {
-        let client = Client::new(self.endpoint.clone());
-        let mut grpc_client = nockchain_service_client::NockchainServiceClient::new(client);
+        let mut grpc_client = self.client.clone();
 
         let pb_raw_tx: pb_common_v2::RawTransaction = serde_wasm_bindgen::from_value(raw_tx)
             .map_err(|e| JsValue::from_str(&format!("Deserialization error: {}", e)))?;
@@ -123,8 +161,7 @@ impl GrpcClient {
     /// Check if a transaction was accepted
     #[wasm_bindgen(js_name = transactionAccepted)]
     pub async fn transaction_accepted(&self, tx_id: String) -> Result<bool, JsValue> {
-        let client = Client::new(self.endpoint.clone());
-        let mut grpc_client = nockchain_service_client::NockchainServiceClient::new(client);
+        let mut grpc_client = self.client.clone();
 
         let request = TransactionAcceptedRequest {
             tx_id: Some(Base58Hash { hash: tx_id }),
@@ -145,3 +182,87 @@ impl GrpcClient {
         }
     }
 }
+
+impl GrpcClient {
+    /// Fetch every page of the balance, starting over if the snapshot changes between pages.
+    async fn get_balance(
+        &self,
+        selector: wallet_get_balance_request::Selector,
+    ) -> Result<WasmBalance, JsValue> {
+        let mut retries = 0;
+        loop {
+            match self.collect_balance(&selector).await {
+                Err(BalanceError::SnapshotChanged) if retries < SNAPSHOT_RETRIES => {
+                    retries += 1;
+                    sleep(SNAPSHOT_RETRY_DELAY_MS * retries).await;
+                }
+                result => return Ok(WasmBalance { internal: result? }),
+            }
+        }
+    }
+
+    async fn collect_balance(
+        &self,
+        selector: &wallet_get_balance_request::Selector,
+    ) -> Result<BalanceUpdate, BalanceError> {
+        let mut grpc_client = self.client.clone();
+        let mut page_token = String::new();
+        let mut notes = Vec::new();
+        let mut snapshot = None;
+
+        loop {
+            let request = WalletGetBalanceRequest {
+                selector: Some(selector.clone()),
+                page: Some(PageRequest {
+                    client_page_items_limit: 0,
+                    page_token: page_token.clone(),
+                    max_bytes: 0,
+                }),
+            };
+
+            let response = grpc_client
+                .wallet_get_balance(request)
+                .await
+                .map_err(|e| BalanceError::Other(JsValue::from_str(&format!("gRPC error: {}", e))))?
+                .into_inner();
+
+            let balance = match response.result {
+                Some(wallet_get_balance_response::Result::Balance(balance)) => balance,
+                Some(wallet_get_balance_response::Result::Error(e)) => {
+                    return Err(BalanceError::Other(JsValue::from_str(&format!(
+                        "Server error: {}",
+                        e.message
+                    ))))
+                }
+                None => {
+                    return Err(BalanceError::Other(JsValue::from_str(
+                        "Empty response from server",
+                    )))
+                }
+            };
+
+            let page_snapshot = (balance.height, balance.block_id);
+            match &snapshot {
+                None => snapshot = Some(page_snapshot),
+                Some(s) if *s != page_snapshot => return Err(BalanceError::SnapshotChanged),
+                Some(_) => {}
+            }
+
+            notes.extend(balance.notes);
+            page_token = balance.page.map(|p| p.next_page_token).unwrap_or_default();
+            if page_token.is_empty() {
+                break;
+            }
+        }
+
+        let (height, block_id) = snapshot.unwrap_or_default();
+        let balance = pb_common_v2::Balance {
+            notes,
+            height,
+            block_id,
+            page: None,
+        };
+        BalanceUpdate::try_from(balance)
+            .map_err(|e| BalanceError::Other(JsValue::from_str(&format!("{}", e))))
+    }
+}
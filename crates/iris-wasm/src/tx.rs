@@ -3,17 +3,19 @@ use std::collections::{BTreeMap, BTreeSet};
 use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
-use ibig::UBig;
 use iris_crypto::PrivateKey;
 use iris_grpc_proto::pb::common::v1 as pb_v1;
 use iris_grpc_proto::pb::common::v2 as pb;
 use iris_nockchain_types::{
     builder::TxBuilder,
-    note::{Name, Note, NoteData, NoteDataEntry, Pkh, TimelockRange, Version},
-    tx::{LockPrimitive, LockRoot, NockchainTx, RawTx, Seed, SpendCondition},
+    note::{BlockHeight, Name, Note, NoteData, NoteDataEntry, Pkh, TimelockRange, Version},
+    tx::{LockPrimitive, LockRoot, LockTree, NockchainTx, RawTx, Seed, SpendCondition},
     Nicks,
 };
-use iris_nockchain_types::{Hax, LockTim, MissingUnlocks, Source, SpendBuilder};
+use iris_nockchain_types::{
+    Hax, LockTim, MissingUnlocks, OutputSummary, OwnedLocks, Payment, PolicyError, Source,
+    SpendBuilder, SummaryWarning, TxSummary,
+};
 use iris_ztd::{cue, jam, Digest, Hashable as HashableTrait, NounDecode, NounEncode};
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
@@ -46,7 +48,7 @@ impl WasmDigest {
         self.value.as_str().try_into()
     }
 
-    fn from_internal(digest: &Digest) -> Self {
+    pub(crate) fn from_internal(digest: &Digest) -> Self {
         Self {
             value: digest.to_string(),
         }
@@ -140,10 +142,7 @@ impl WasmName {
         Name::new(self.first, self.last)
     }
 
-    #[allow(dead_code)]
-    fn from_internal(name: &Name) -> Self {
-        // We need to access Name fields via hash since they are private
-        // For now, we'll only support construction, not reading back
+    pub(crate) fn from_internal(name: &Name) -> Self {
         Self {
             first: name.first,
             last: name.last,
@@ -465,7 +464,7 @@ impl WasmNote {
         ))
     }
 
-    fn from_internal(internal: Note) -> Self {
+    pub(crate) fn from_internal(internal: Note) -> Self {
         Self {
             version: WasmVersion::from_internal(&internal.version),
             origin_page: internal.origin_page,
@@ -829,6 +828,50 @@ impl WasmSpendCondition {
     }
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct WasmCompiledPolicy {
+    pub lock_root: String,
+    pub first_name: String,
+    pub branches: Vec<WasmSpendCondition>,
+}
+
+/// Compile a policy, such as `or(pkh(2, A, B, C), and(pkh(1, A), after(1000)))`, into the spend
+/// conditions of a lock tree.
+///
+/// Experimental for policies with `or`: spends from multi-branch trees have not been checked
+/// against the chain yet, so do not send mainnet funds to them.
+#[wasm_bindgen(js_name = compilePolicy)]
+pub fn compile_policy(policy: &str) -> Result<JsValue, JsValue> {
+    let tree: LockTree = policy
+        .parse()
+        .map_err(|e: PolicyError| JsValue::from_str(&e.to_string()))?;
+    let compiled = WasmCompiledPolicy {
+        lock_root: tree.root().to_string(),
+        first_name: tree.first_name().to_string(),
+        branches: tree
+            .branches()
+            .iter()
+            .cloned()
+            .map(WasmSpendCondition::from_internal)
+            .collect(),
+    };
+    serde_wasm_bindgen::to_value(&compiled).map_err(|e| e.into())
+}
+
+/// Turn the branches of a lock tree back into a policy.
+#[wasm_bindgen(js_name = decompilePolicy)]
+pub fn decompile_policy(branches: Vec<WasmSpendCondition>) -> Result<String, JsValue> {
+    let branches = branches
+        .iter()
+        .map(|sc| sc.to_internal())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| JsValue::from_str(&e))?;
+    let count = branches.len();
+    let tree = LockTree::new(branches)
+        .ok_or_else(|| JsValue::from_str(&PolicyError::InvalidBranchCount(count).to_string()))?;
+    Ok(tree.to_string())
+}
+
 #[wasm_bindgen(js_name = LockRoot)]
 #[derive(Clone, Debug)]
 pub struct WasmLockRoot {
@@ -1023,6 +1066,54 @@ impl WasmSeed {
     }
 }
 
+#[wasm_bindgen(js_name = Payment)]
+#[derive(Clone)]
+pub struct WasmPayment {
+    #[wasm_bindgen(skip)]
+    pub lock: WasmSpendCondition,
+    #[wasm_bindgen(skip)]
+    pub amount: Nicks,
+    #[wasm_bindgen(skip)]
+    pub note_data: Option<WasmNoteData>,
+}
+
+#[wasm_bindgen(js_class = Payment)]
+impl WasmPayment {
+    #[wasm_bindgen(constructor)]
+    pub fn new(lock: WasmSpendCondition, amount: Nicks, note_data: Option<WasmNoteData>) -> Self {
+        Self {
+            lock,
+            amount,
+            note_data,
+        }
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn lock(&self) -> WasmSpendCondition {
+        self.lock.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn amount(&self) -> Nicks {
+        self.amount
+    }
+
+    #[wasm_bindgen(getter, js_name = noteData)]
+    pub fn note_data(&self) -> Option<WasmNoteData> {
+        self.note_data.clone()
+    }
+
+    fn to_internal(&self) -> Result<Payment, String> {
+        let mut payment = Payment::new(self.lock.to_internal()?, self.amount);
+        payment.note_data = self
+            .note_data
+            .as_ref()
+            .map(WasmNoteData::to_internal)
+            .transpose()?;
+        Ok(payment)
+    }
+}
+
 // ============================================================================
 // Wasm Transaction Builder
 // ============================================================================
@@ -1150,11 +1241,74 @@ impl WasmTxBuilder {
         Ok(())
     }
 
+    /// Pay out to multiple recipients in one transaction.
+    ///
+    /// Works like `simpleSpend`, but each payment has its own lock, amount, and optional note
+    /// data. Payments are filled in order, and may be split across several notes. If a payment
+    /// has no note data, `include_lock_data` decides whether its lock is included.
+    #[allow(clippy::too_many_arguments)]
+    #[wasm_bindgen(js_name = batchSpend)]
+    pub fn batch_spend(
+        &mut self,
+        notes: Vec<WasmNote>,
+        spend_conditions: Vec<WasmSpendCondition>,
+        payments: Vec<WasmPayment>,
+        fee_override: Option<Nicks>,
+        refund_pkh: WasmDigest,
+        include_lock_data: bool,
+    ) -> Result<(), JsValue> {
+        if notes.len() != spend_conditions.len() {
+            return Err(JsValue::from_str(
+                "notes and spend_conditions must have the same length",
+            ));
+        }
+
+        let internal_notes: Result<Vec<(Note, SpendCondition)>, String> = notes
+            .iter()
+            .zip(spend_conditions.iter())
+            .map(|(n, sc)| Ok((n.to_internal()?, sc.to_internal()?)))
+            .collect();
+        let internal_notes = internal_notes.map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let payments = payments
+            .iter()
+            .map(WasmPayment::to_internal)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| JsValue::from_str(&e))?;
+
+        self.builder
+            .batch_spend_base(
+                internal_notes,
+                payments,
+                refund_pkh.to_internal()?,
+                include_lock_data,
+            )
+            .map_err(|e| JsValue::from_str(&format!("{}", e)))?;
+
+        if let Some(fee) = fee_override {
+            self.builder
+                .set_fee_and_balance_refund(fee, false, include_lock_data)
+        } else {
+            self.builder.recalc_and_set_fee(include_lock_data)
+        }
+        .map_err(|e| JsValue::from_str(&format!("{}", e)))?;
+
+        Ok(())
+    }
+
     /// Append a `SpendBuilder` to this transaction
     pub fn spend(&mut self, spend: WasmSpendBuilder) -> Option<WasmSpendBuilder> {
         self.builder.spend(spend.into()).map(|v| v.into())
     }
 
+    /// Set the block height the transaction is expected to be included at.
+    ///
+    /// Once set, `simpleSpend` skips notes whose timelocks are not satisfied at this height, and
+    /// `validate` fails on such spends.
+    #[wasm_bindgen(js_name = setHeight)]
+    pub fn set_height(&mut self, height: BlockHeight) {
+        self.builder.set_height(height);
+    }
+
     /// Distributes `fee` across builder's spends, and balances refunds out
     ///
     /// `adjust_fee` parameter allows the fee to be slightly tweaked, whenever notes are added or
@@ -1202,7 +1356,8 @@ impl WasmTxBuilder {
         if signing_key_bytes.len() != 32 {
             return Err(JsValue::from_str("Private key must be 32 bytes"));
         }
-        let signing_key = PrivateKey(UBig::from_be_bytes(signing_key_bytes));
+        let signing_key = PrivateKey::from_be_bytes(signing_key_bytes)
+            .ok_or_else(|| JsValue::from_str("Private key is out of range"))?;
 
         self.builder.sign(&signing_key);
 
@@ -1398,7 +1553,8 @@ impl WasmSpendBuilder {
         if signing_key_bytes.len() != 32 {
             return Err(JsValue::from_str("Private key must be 32 bytes"));
         }
-        let signing_key = PrivateKey(UBig::from_be_bytes(signing_key_bytes));
+        let signing_key = PrivateKey::from_be_bytes(signing_key_bytes)
+            .ok_or_else(|| JsValue::from_str("Private key is out of range"))?;
         Ok(self.builder.sign(&signing_key))
     }
 
@@ -1436,6 +1592,10 @@ pub enum WasmMissingUnlocks {
         preimages_for: BTreeSet<String>,
     },
     Brn,
+    Tim {
+        spendable_from: Option<BlockHeight>,
+        spendable_until: Option<BlockHeight>,
+    },
 }
 
 impl WasmMissingUnlocks {
@@ -1455,6 +1615,13 @@ impl WasmMissingUnlocks {
                     .collect(),
             },
             MissingUnlocks::Brn => Self::Brn,
+            MissingUnlocks::Tim {
+                spendable_from,
+                spendable_until,
+            } => Self::Tim {
+                spendable_from: *spendable_from,
+                spendable_until: *spendable_until,
+            },
         }
     }
 }
@@ -1544,105 +1711,158 @@ impl WasmRawTx {
         WasmDigest::from_internal(&computed_id)
     }
 
+    /// Summarize what this transaction does, for displaying before signing.
+    ///
+    /// Outputs to `pkhs` or `spend_conditions` are reported as change.
+    pub fn summary(
+        &self,
+        pkhs: Vec<String>,
+        spend_conditions: Vec<WasmSpendCondition>,
+    ) -> Result<JsValue, JsValue> {
+        let ours = owned_locks(pkhs, spend_conditions)?;
+        let summary = WasmTxSummary::from_internal(&self.internal.summary(&ours));
+        serde_wasm_bindgen::to_value(&summary).map_err(|e| e.into())
+    }
+
+    /// Like `summary`, but also totals the assets of the `notes` being spent, and warns if they
+    /// are missing or do not balance the transaction.
+    #[wasm_bindgen(js_name = summaryWithNotes)]
+    pub fn summary_with_notes(
+        &self,
+        pkhs: Vec<String>,
+        spend_conditions: Vec<WasmSpendCondition>,
+        notes: Vec<WasmNote>,
+    ) -> Result<JsValue, JsValue> {
+        let ours = owned_locks(pkhs, spend_conditions)?;
+        let summary = self
+            .internal
+            .summary(&ours)
+            .with_notes(&self.internal, &notes_by_name(notes)?);
+        serde_wasm_bindgen::to_value(&WasmTxSummary::from_internal(&summary)).map_err(|e| e.into())
+    }
+
     #[wasm_bindgen(js_name = toNockchainTx)]
     pub fn to_nockchain_tx(&self) -> WasmNockchainTx {
         WasmNockchainTx::from_internal(&self.internal.to_nockchain_tx())
     }
-    
+
     /// Merge signatures from multiple signed transactions into one.
+    ///
+    /// Kept for compatibility: `threshold` must be at least 1, but is otherwise unused, as the
+    /// number of signatures kept now follows each `Pkh` lock. Use `mergeSignaturesWithReport` to
+    /// also learn which spends are still under threshold and which signatures were rejected.
     #[wasm_bindgen(js_name = mergeSignatures)]
-    pub fn merge_signatures(signed_txs: js_sys::Array, threshold: u32) -> Result<WasmRawTx, JsValue> {
-        use std::collections::HashSet;
-
-        if signed_txs.length() == 0 {
-            return Err(JsValue::from_str("At least one signed transaction is required"));
-        }
-
+    pub fn merge_signatures(
+        signed_txs: js_sys::Array,
+        threshold: u32,
+    ) -> Result<WasmRawTx, JsValue> {
         if threshold == 0 {
             return Err(JsValue::from_str("Threshold must be at least 1"));
         }
+        let result = Self::merge_signatures_with_report(signed_txs)?;
+        Ok(WasmRawTx::from_internal(&result.tx))
+    }
 
-        // Parse all signed transactions
+    /// Merge signatures from multiple signed transactions into one, reporting what is missing.
+    ///
+    /// The first transaction is used as the base. Each signature is verified before it is merged,
+    /// and only as many signatures as each `Pkh` lock requires are kept.
+    #[wasm_bindgen(js_name = mergeSignaturesWithReport)]
+    pub fn merge_signatures_with_report(
+        signed_txs: js_sys::Array,
+    ) -> Result<WasmMergeResult, JsValue> {
         let mut transactions: Vec<RawTx> = Vec::new();
         for i in 0..signed_txs.length() {
-            let tx_val = signed_txs.get(i);
-            let pb: pb::RawTransaction = serde_wasm_bindgen::from_value(tx_val)
-                .map_err(|e| JsValue::from_str(&format!("Invalid transaction at index {}: {}", i, e)))?;
-            let tx: RawTx = pb.try_into()
-                .map_err(|e| JsValue::from_str(&format!("Failed to parse transaction at index {}: {}", i, e)))?;
+            let pb: pb::RawTransaction = serde_wasm_bindgen::from_value(signed_txs.get(i))
+                .map_err(|e| {
+                    JsValue::from_str(&format!("Invalid transaction at index {}: {}", i, e))
+                })?;
+            let tx: RawTx = pb.try_into().map_err(|e| {
+                JsValue::from_str(&format!(
+                    "Failed to parse transaction at index {}: {}",
+                    i, e
+                ))
+            })?;
             transactions.push(tx);
         }
 
-        // Use the first transaction as base and verify all have the same ID
-        let base_tx = transactions.remove(0);
-        let expected_id = &base_tx.id;
-
-        for (i, tx) in transactions.iter().enumerate() {
-            if &tx.id != expected_id {
-                return Err(JsValue::from_str(&format!(
-                    "Transaction ID mismatch at index {}: expected {}, got {}",
-                    i + 1,
-                    expected_id,
-                    tx.id
-                )));
-            }
+        if transactions.is_empty() {
+            return Err(JsValue::from_str(
+                "At least one signed transaction is required",
+            ));
         }
 
-        // Merge signatures for each spend, keeping only up to `threshold` unique signatures
-        let mut merged_spends = Vec::new();
+        let mut tx = transactions.remove(0);
+        let report = tx
+            .merge_signatures(&transactions)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-        for (name, base_spend) in base_tx.spends.0 {
-            let mut seen_pkhs: HashSet<String> = HashSet::new();
-            let mut all_signatures = Vec::new();
+        Ok(WasmMergeResult {
+            tx,
+            under_threshold: report
+                .under_threshold
+                .into_iter()
+                .map(|(name, unlocks)| WasmUnderThreshold {
+                    name: WasmName {
+                        first: name.first,
+                        last: name.last,
+                    },
+                    unlocks: unlocks
+                        .iter()
+                        .map(WasmMissingUnlocks::from_internal)
+                        .collect(),
+                })
+                .collect(),
+            rejected: report.rejected.iter().map(|e| e.to_string()).collect(),
+        })
+    }
+}
 
-            // Collect signatures from base transaction
-            for (pkh, pubkey, sig) in &base_spend.witness.pkh_signature.0 {
-                let pkh_str = pkh.to_string();
-                if !seen_pkhs.contains(&pkh_str) && all_signatures.len() < threshold as usize {
-                    seen_pkhs.insert(pkh_str);
-                    all_signatures.push((pkh.clone(), pubkey.clone(), sig.clone()));
-                }
-            }
+#[derive(Serialize, Deserialize)]
+pub struct WasmUnderThreshold {
+    pub name: WasmName,
+    pub unlocks: Vec<WasmMissingUnlocks>,
+}
 
-            // Collect signatures from other transactions (up to threshold)
-            for other_tx in &transactions {
-                if all_signatures.len() >= threshold as usize {
-                    break; // We have enough signatures
-                }
+/// Result of `RawTx.mergeSignaturesWithReport`.
+#[wasm_bindgen(js_name = MergeResult)]
+pub struct WasmMergeResult {
+    #[wasm_bindgen(skip)]
+    pub(crate) tx: RawTx,
+    #[wasm_bindgen(skip)]
+    pub(crate) under_threshold: Vec<WasmUnderThreshold>,
+    #[wasm_bindgen(skip)]
+    pub(crate) rejected: Vec<String>,
+}
 
-                if let Some((_, other_spend)) = other_tx.spends.0.iter().find(|(n, _)| n == &name) {
-                    for (pkh, pubkey, sig) in &other_spend.witness.pkh_signature.0 {
-                        if all_signatures.len() >= threshold as usize {
-                            break;
-                        }
-                        let pkh_str = pkh.to_string();
-                        if !seen_pkhs.contains(&pkh_str) {
-                            seen_pkhs.insert(pkh_str);
-                            all_signatures.push((pkh.clone(), pubkey.clone(), sig.clone()));
-                        }
-                    }
-                }
-            }
+#[wasm_bindgen(js_class = MergeResult)]
+impl WasmMergeResult {
+    /// The merged transaction
+    #[wasm_bindgen(getter)]
+    pub fn tx(&self) -> WasmRawTx {
+        WasmRawTx::from_internal(&self.tx)
+    }
 
-            // Create merged spend with combined signatures
-            let mut merged_spend = base_spend.clone();
-            merged_spend.witness.pkh_signature = iris_nockchain_types::tx::PkhSignature(all_signatures);
-            merged_spends.push((name, merged_spend));
-        }
+    /// Spends that still need signatures, along with the missing unlocks of each
+    #[wasm_bindgen(getter, js_name = underThreshold)]
+    pub fn under_threshold(&self) -> Result<Vec<JsValue>, JsValue> {
+        self.under_threshold
+            .iter()
+            .map(serde_wasm_bindgen::to_value)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.into())
+    }
 
-        // Create merged transaction with combined signatures
-        let merged_spends = iris_nockchain_types::tx::Spends(merged_spends);
-        // The witness data has changed (new signatures added), so the transaction hash
-        // must be recomputed: ID = hash(version, spends), and spends include witness data.
-        let tx_id = (&base_tx.version, &merged_spends).hash();
-        
-        let merged_tx = RawTx {
-            version: base_tx.version,
-            id: tx_id,
-            spends: merged_spends,
-        };
+    /// Signatures that failed verification, and were not merged
+    #[wasm_bindgen(getter)]
+    pub fn rejected(&self) -> Vec<String> {
+        self.rejected.clone()
+    }
 
-        Ok(WasmRawTx::from_internal(&merged_tx))
+    /// Whether every spend has enough signatures
+    #[wasm_bindgen(getter, js_name = isComplete)]
+    pub fn is_complete(&self) -> bool {
+        self.under_threshold.is_empty()
     }
 }
 
@@ -1699,8 +1919,155 @@ impl WasmNockchainTx {
             .collect()
     }
 
+    /// Summarize what this transaction does, for displaying before signing.
+    ///
+    /// Outputs to `pkhs` or `spend_conditions` are reported as change.
+    pub fn summary(
+        &self,
+        pkhs: Vec<String>,
+        spend_conditions: Vec<WasmSpendCondition>,
+    ) -> Result<JsValue, JsValue> {
+        let ours = owned_locks(pkhs, spend_conditions)?;
+        let summary = WasmTxSummary::from_internal(&self.internal.summary(&ours));
+        serde_wasm_bindgen::to_value(&summary).map_err(|e| e.into())
+    }
+
+    /// Like `summary`, but also totals the assets of the `notes` being spent, and warns if they
+    /// are missing or do not balance the transaction.
+    #[wasm_bindgen(js_name = summaryWithNotes)]
+    pub fn summary_with_notes(
+        &self,
+        pkhs: Vec<String>,
+        spend_conditions: Vec<WasmSpendCondition>,
+        notes: Vec<WasmNote>,
+    ) -> Result<JsValue, JsValue> {
+        let ours = owned_locks(pkhs, spend_conditions)?;
+        let summary = self
+            .internal
+            .summary(&ours)
+            .with_notes(&self.internal.to_raw_tx(), &notes_by_name(notes)?);
+        serde_wasm_bindgen::to_value(&WasmTxSummary::from_internal(&summary)).map_err(|e| e.into())
+    }
+
     #[wasm_bindgen(js_name = toRawTx)]
     pub fn to_raw_tx(&self) -> WasmRawTx {
         WasmRawTx::from_internal(&self.internal.to_raw_tx())
     }
 }
+
+fn owned_locks(
+    pkhs: Vec<String>,
+    spend_conditions: Vec<WasmSpendCondition>,
+) -> Result<OwnedLocks, JsValue> {
+    let pkhs = pkhs
+        .iter()
+        .map(|p| Digest::try_from(p.as_str()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let spend_conditions = spend_conditions
+        .iter()
+        .map(|sc| sc.to_internal())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| JsValue::from_str(&e))?;
+    Ok(OwnedLocks::new(pkhs).with_spend_conditions(spend_conditions))
+}
+
+fn notes_by_name(notes: Vec<WasmNote>) -> Result<BTreeMap<Name, Note>, JsValue> {
+    notes
+        .iter()
+        .map(|n| n.to_internal().map(|n| (n.name.clone(), n)))
+        .collect::<Result<_, _>>()
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WasmOutputSummary {
+    pub lock_root: String,
+    pub spend_condition: Option<WasmSpendCondition>,
+    pub amount: Nicks,
+}
+
+impl WasmOutputSummary {
+    fn from_internal(internal: &OutputSummary) -> Self {
+        Self {
+            lock_root: internal.lock_root.to_string(),
+            spend_condition: internal
+                .spend_condition
+                .clone()
+                .map(WasmSpendCondition::from_internal),
+            amount: internal.amount,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum WasmSummaryWarning {
+    Burn { lock_root: String },
+    HashLock { lock_root: String },
+    Timelocked { lock_root: String },
+    UnknownLock { lock_root: String },
+    UnknownInput { name: WasmName },
+    Unbalanced { inputs: Nicks, spent: Nicks },
+}
+
+impl WasmSummaryWarning {
+    fn from_internal(internal: &SummaryWarning) -> Self {
+        match internal {
+            SummaryWarning::Burn(d) => Self::Burn {
+                lock_root: d.to_string(),
+            },
+            SummaryWarning::HashLock(d) => Self::HashLock {
+                lock_root: d.to_string(),
+            },
+            SummaryWarning::Timelocked(d) => Self::Timelocked {
+                lock_root: d.to_string(),
+            },
+            SummaryWarning::UnknownLock(d) => Self::UnknownLock {
+                lock_root: d.to_string(),
+            },
+            SummaryWarning::UnknownInput(name) => Self::UnknownInput {
+                name: WasmName::from_internal(name),
+            },
+            SummaryWarning::Unbalanced { inputs, spent } => Self::Unbalanced {
+                inputs: *inputs,
+                spent: *spent,
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WasmTxSummary {
+    pub total_inputs: Option<Nicks>,
+    pub total_spent: Nicks,
+    pub outputs: Vec<WasmOutputSummary>,
+    pub change: Vec<WasmOutputSummary>,
+    pub fee: Nicks,
+    pub fee_per_word: Nicks,
+    pub warnings: Vec<WasmSummaryWarning>,
+}
+
+impl WasmTxSummary {
+    fn from_internal(internal: &TxSummary) -> Self {
+        Self {
+            total_inputs: internal.total_inputs,
+            total_spent: internal.total_spent,
+            outputs: internal
+                .outputs
+                .iter()
+                .map(WasmOutputSummary::from_internal)
+                .collect(),
+            change: internal
+                .change
+                .iter()
+                .map(WasmOutputSummary::from_internal)
+                .collect(),
+            fee: internal.fee,
+            fee_per_word: internal.fee_per_word,
+            warnings: internal
+                .warnings
+                .iter()
+                .map(WasmSummaryWarning::from_internal)
+                .collect(),
+        }
+    }
+}
@@ -3,6 +3,8 @@ use core::ops::{Add, Div, Mul, Neg, Sub};
 use num_traits::Pow;
 use serde::{Deserialize, Serialize};
 
+use crate::crypto::scalar::mask;
+
 pub mod bpoly;
 pub mod poly;
 
@@ -303,11 +305,10 @@ pub fn badd(a: u64, b: u64) -> u64 {
 #[inline(always)]
 pub fn bneg(a: u64) -> u64 {
     based!(a);
-    if a != 0 {
-        PRIME - a
-    } else {
-        0
-    }
+    // Masked rather than branched on, like `badd` and `bsub`, so that the constant-time curve
+    // arithmetic can use it.
+    let nonzero = mask((a | a.wrapping_neg()) >> 63);
+    PRIME.wrapping_sub(a) & nonzero
 }
 
 #[inline(always)]
@@ -346,6 +347,25 @@ pub fn reduce_159(low: u64, mid: u32, high: u64) -> u64 {
     result
 }
 
+/// [`reduce_159`] with its corrections applied through masks instead of branches, so that it
+/// takes the same time for every input.
+#[inline(always)]
+pub fn reduce_159_ct(low: u64, mid: u32, high: u64) -> u64 {
+    let (low2, carry) = low.overflowing_sub(high);
+    let low2 = low2.wrapping_add(PRIME & mask(carry as u64));
+
+    let mut product = (mid as u64) << 32;
+    product -= product >> 32;
+
+    let (result, carry) = product.overflowing_add(low2);
+    let result = result.wrapping_sub(PRIME & mask(carry as u64));
+
+    // `result - PRIME` borrows exactly when `result < PRIME`.
+    let (reduced, borrow) = result.overflowing_sub(PRIME);
+    let keep = mask(borrow as u64);
+    (result & keep) | (reduced & !keep)
+}
+
 #[inline(always)]
 pub fn bmul(a: u64, b: u64) -> u64 {
     based!(a);
@@ -353,6 +373,15 @@ pub fn bmul(a: u64, b: u64) -> u64 {
     reduce((a as u128) * (b as u128))
 }
 
+/// [`bmul`] without data-dependent branches, for arithmetic on secret values.
+#[inline(always)]
+pub fn bmul_ct(a: u64, b: u64) -> u64 {
+    based!(a);
+    based!(b);
+    let n = (a as u128) * (b as u128);
+    reduce_159_ct(n as u64, (n >> 64) as u32, (n >> 96) as u64)
+}
+
 #[inline(always)]
 pub fn binv(a: u64) -> u64 {
     based!(a);
@@ -5,9 +5,13 @@ use bs58;
 use ibig::UBig;
 use once_cell::sync::Lazy;
 
-use crate::belt::{bneg, Belt, PRIME};
+use core::ops::{Add, Mul, Sub};
+
+use crate::belt::{bmul_ct, bneg, Belt, PRIME};
 use crate::belt::{bpegcd, bpscal};
 
+use super::scalar::{eq_mask, Scalar};
+
 pub static G_ORDER: Lazy<UBig> = Lazy::new(|| {
     UBig::from_str_radix(
         "7af2599b3b3f22d0563fbf0f990a37b5327aa72330157722d443623eaed4accf",
@@ -128,7 +132,10 @@ pub fn f6_div(f1: &F6lt, f2: &F6lt) -> Result<F6lt, CheetahError> {
 }
 
 #[inline(always)]
-fn karat3(a: &[Belt; 3], b: &[Belt; 3]) -> [Belt; 5] {
+fn karat3<T>(a: &[T; 3], b: &[T; 3]) -> [T; 5]
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<Output = T>,
+{
     let m = [a[0] * b[0], a[1] * b[1], a[2] * b[2]];
     [
         m[0],
@@ -140,13 +147,16 @@ fn karat3(a: &[Belt; 3], b: &[Belt; 3]) -> [Belt; 5] {
 }
 
 #[inline(always)]
-pub fn f6_mul(f: &F6lt, g: &F6lt) -> F6lt {
-    let f0g0 = karat3(&[f.0[0], f.0[1], f.0[2]], &[g.0[0], g.0[1], g.0[2]]);
-    let f1g1 = karat3(&[f.0[3], f.0[4], f.0[5]], &[g.0[3], g.0[4], g.0[5]]);
+fn f6_mul_limbs<T>(f: &[T; 6], g: &[T; 6]) -> [T; 6]
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<Output = T> + From<Belt>,
+{
+    let f0g0 = karat3(&[f[0], f[1], f[2]], &[g[0], g[1], g[2]]);
+    let f1g1 = karat3(&[f[3], f[4], f[5]], &[g[3], g[4], g[5]]);
 
     let foil = karat3(
-        &[f.0[0] + f.0[3], f.0[1] + f.0[4], f.0[2] + f.0[5]],
-        &[g.0[0] + g.0[3], g.0[1] + g.0[4], g.0[2] + g.0[5]],
+        &[f[0] + f[3], f[1] + f[4], f[2] + f[5]],
+        &[g[0] + g[3], g[1] + g[4], g[2] + g[5]],
     );
 
     let cross = [
@@ -156,14 +166,64 @@ pub fn f6_mul(f: &F6lt, g: &F6lt) -> F6lt {
         foil[3] - (f0g0[3] + f1g1[3]),
         foil[4] - (f0g0[4] + f1g1[4]),
     ];
-    F6lt([
-        f0g0[0] + Belt(7) * (cross[3] + f1g1[0]),
-        f0g0[1] + Belt(7) * (cross[4] + f1g1[1]),
-        f0g0[2] + Belt(7) * f1g1[2],
-        f0g0[3] + cross[0] + Belt(7) * f1g1[3],
-        f0g0[4] + cross[1] + Belt(7) * f1g1[4],
+    let seven = T::from(Belt(7));
+    [
+        f0g0[0] + seven * (cross[3] + f1g1[0]),
+        f0g0[1] + seven * (cross[4] + f1g1[1]),
+        f0g0[2] + seven * f1g1[2],
+        f0g0[3] + cross[0] + seven * f1g1[3],
+        f0g0[4] + cross[1] + seven * f1g1[4],
         cross[2],
-    ])
+    ]
+}
+
+#[inline(always)]
+pub fn f6_mul(f: &F6lt, g: &F6lt) -> F6lt {
+    F6lt(f6_mul_limbs(&f.0, &g.0))
+}
+
+/// A [`Belt`] whose multiplication reduces without branching. Addition, subtraction and negation
+/// of [`Belt`] are already branch-free.
+#[derive(Clone, Copy)]
+struct CtBelt(Belt);
+
+impl From<Belt> for CtBelt {
+    fn from(b: Belt) -> Self {
+        CtBelt(b)
+    }
+}
+
+impl Add for CtBelt {
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self {
+        CtBelt(self.0 + rhs.0)
+    }
+}
+
+impl Sub for CtBelt {
+    type Output = Self;
+
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self {
+        CtBelt(self.0 - rhs.0)
+    }
+}
+
+impl Mul for CtBelt {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, rhs: Self) -> Self {
+        CtBelt(Belt(bmul_ct(self.0 .0, rhs.0 .0)))
+    }
+}
+
+/// [`f6_mul`] in constant time, for the curve arithmetic on secret scalars.
+#[inline(always)]
+fn f6_mul_ct(f: &F6lt, g: &F6lt) -> F6lt {
+    F6lt(f6_mul_limbs(&f.0.map(CtBelt), &g.0.map(CtBelt)).map(|b| b.0))
 }
 
 #[inline(always)]
@@ -320,24 +380,444 @@ pub fn ch_scal(n: u64, p: &CheetahPoint) -> Result<CheetahPoint, CheetahError> {
     Ok(acc)
 }
 
-#[inline(always)]
+/// Compute `n * p`: with the precomputed table if `p` is [`A_GEN`], and with [`ch_scal_wnaf`]
+/// otherwise.
 pub fn ch_scal_big(n: &UBig, p: &CheetahPoint) -> Result<CheetahPoint, CheetahError> {
-    let mut n_copy = n.clone();
-    let zero = UBig::from(0u64);
-    let mut p_copy = *p;
-    let mut acc = A_ID;
+    if *p == A_GEN {
+        ch_scal_gen(n)
+    } else {
+        ch_scal_wnaf(n, p)
+    }
+}
 
-    while n_copy > zero {
-        // Check if least significant bit is set
-        if n_copy.bit(0) {
-            acc = ch_add(&acc, &p_copy)?;
+/// Bits per window of [`GEN_TABLE`].
+const GEN_WINDOW: usize = 4;
+
+/// `GEN_TABLE[i][d - 1] = d * 16^i * A_GEN` for every 4-bit window `i` of a scalar below
+/// `G_ORDER`, and every nonzero digit `d`.
+static GEN_TABLE: Lazy<Vec<[CheetahPoint; 15]>> = Lazy::new(|| {
+    let windows = G_ORDER.bit_len().div_ceil(GEN_WINDOW);
+    let mut table = Vec::with_capacity(windows);
+    let mut base = A_GEN;
+    for _ in 0..windows {
+        let mut row = [base; 15];
+        for d in 1..15 {
+            row[d] = ch_add(&row[d - 1], &base).unwrap();
         }
-        p_copy = ch_double(p_copy)?;
-        n_copy >>= 1; // Right shift by 1 bit
+        base = ch_add(&row[14], &base).unwrap();
+        table.push(row);
+    }
+    table
+});
+
+/// Compute `n * A_GEN` with one addition per nonzero 4-bit window of `n` and no doublings.
+///
+/// The additions are done in projective coordinates, with a single inversion at the end.
+pub fn ch_scal_gen(n: &UBig) -> Result<CheetahPoint, CheetahError> {
+    let n = n % &*G_ORDER;
+    let mut acc = ProjectivePoint::IDENTITY;
+    for (i, byte) in n.to_le_bytes().into_iter().enumerate() {
+        for (j, d) in [byte & 0xf, byte >> 4].into_iter().enumerate() {
+            if d != 0 {
+                acc = acc.add_vartime(&GEN_TABLE_CT[2 * i + j][d as usize]);
+            }
+        }
+    }
+    acc.to_affine_vartime()
+}
+
+/// Width of the non-adjacent forms used for variable bases.
+const WNAF_WIDTH: u32 = 5;
+
+/// The width-`w` non-adjacent form of `n`, least significant digit first. Every nonzero digit is
+/// odd and below `2^(w-1)` in absolute value, and is followed by at least `w - 1` zeros.
+fn wnaf(n: &UBig, w: u32) -> Vec<i8> {
+    let modulus = 1i64 << w;
+    let mask = UBig::from(modulus as u64 - 1);
+    let mut k = n.clone();
+    let mut digits = Vec::with_capacity(k.bit_len() + 1);
+    while k != UBig::from(0u64) {
+        let mut d = 0;
+        if k.bit(0) {
+            d = u64::try_from(&k & &mask).unwrap() as i64;
+            if d >= modulus / 2 {
+                d -= modulus;
+                k += UBig::from(d.unsigned_abs());
+            } else {
+                k -= UBig::from(d as u64);
+            }
+        }
+        digits.push(d as i8);
+        k >>= 1;
+    }
+    digits
+}
+
+/// `[p, 3p, 5p, ..., (2^(w-1) - 1)p]`, the odd multiples a width-`w` NAF digit can select.
+fn odd_multiples(p: &CheetahPoint, w: u32) -> Vec<ProjectivePoint> {
+    let p = ProjectivePoint::from_affine(p);
+    let double = p.add_vartime(&p);
+    let mut table = Vec::with_capacity(1 << (w - 2));
+    table.push(p);
+    for i in 1..1 << (w - 2) {
+        table.push(table[i - 1].add_vartime(&double));
+    }
+    table
+}
+
+fn add_digit(acc: &ProjectivePoint, d: i8, table: &[ProjectivePoint]) -> ProjectivePoint {
+    let p = &table[d.unsigned_abs() as usize / 2];
+    if d > 0 {
+        acc.add_vartime(p)
+    } else {
+        acc.add_vartime(&p.neg())
+    }
+}
+
+/// Compute `n * p` for any point `p`, using its width-5 NAF: about one addition per six bits
+/// rather than one per two.
+///
+/// Points are kept in projective coordinates, so that only the result needs an inversion.
+pub fn ch_scal_wnaf(n: &UBig, p: &CheetahPoint) -> Result<CheetahPoint, CheetahError> {
+    let table = odd_multiples(p, WNAF_WIDTH);
+    let mut acc = ProjectivePoint::IDENTITY;
+    for &d in wnaf(n, WNAF_WIDTH).iter().rev() {
+        acc = acc.add_vartime(&acc);
+        if d != 0 {
+            acc = add_digit(&acc, d, &table);
+        }
+    }
+    acc.to_affine_vartime()
+}
+
+/// Compute `a * p + b * q` with a single chain of doublings shared by both scalars, as in the
+/// verification equation `s * G - c * P`.
+pub fn ch_double_scal(
+    a: &UBig,
+    p: &CheetahPoint,
+    b: &UBig,
+    q: &CheetahPoint,
+) -> Result<CheetahPoint, CheetahError> {
+    let (p_table, q_table) = (odd_multiples(p, WNAF_WIDTH), odd_multiples(q, WNAF_WIDTH));
+    let (a_digits, b_digits) = (wnaf(a, WNAF_WIDTH), wnaf(b, WNAF_WIDTH));
+    let mut acc = ProjectivePoint::IDENTITY;
+    for i in (0..a_digits.len().max(b_digits.len())).rev() {
+        acc = acc.add_vartime(&acc);
+        if let Some(&d) = a_digits.get(i).filter(|d| **d != 0) {
+            acc = add_digit(&acc, d, &p_table);
+        }
+        if let Some(&d) = b_digits.get(i).filter(|d| **d != 0) {
+            acc = add_digit(&acc, d, &q_table);
+        }
+    }
+    acc.to_affine_vartime()
+}
+
+/// Invert every element of `fs` in place, sharing a single field inversion between them.
+pub fn f6_batch_inv(fs: &mut [F6lt]) -> Result<(), CheetahError> {
+    let mut prefix = Vec::with_capacity(fs.len());
+    let mut acc = F6_ONE;
+    for f in fs.iter() {
+        prefix.push(acc);
+        acc = f6_mul(&acc, f);
+    }
+    let mut inv = f6_inv(&acc)?;
+    for (f, pre) in fs.iter_mut().zip(prefix).rev() {
+        let next = f6_mul(&inv, f);
+        *f = f6_mul(&inv, &pre);
+        inv = next;
+    }
+    Ok(())
+}
+
+/// Set `ps[i] = ps[i] + qs[i]` for every `i`, sharing a single field inversion between them.
+pub fn ch_add_batch(ps: &mut [CheetahPoint], qs: &[CheetahPoint]) -> Result<(), CheetahError> {
+    let mut generic = Vec::with_capacity(ps.len());
+    let mut denoms = Vec::with_capacity(ps.len());
+    for (i, (p, q)) in ps.iter_mut().zip(qs).enumerate() {
+        if p.inf || q.inf || p.x == q.x {
+            *p = ch_add(p, q)?;
+        } else {
+            generic.push(i);
+            denoms.push(f6_sub(&p.x, &q.x));
+        }
+    }
+    f6_batch_inv(&mut denoms)?;
+    for (i, inv) in generic.into_iter().zip(denoms) {
+        let (p, q) = (ps[i], qs[i]);
+        let slope = f6_mul(&f6_sub(&p.y, &q.y), &inv);
+        let x = f6_sub(&f6_square(&slope), &f6_add(&p.x, &q.x));
+        let y = f6_sub(&f6_mul(&slope, &f6_sub(&p.x, &x)), &p.y);
+        ps[i] = CheetahPoint { x, y, inf: false };
+    }
+    Ok(())
+}
+
+/// Double every point of `ps` in place, sharing a single field inversion between them.
+pub fn ch_double_batch(ps: &mut [CheetahPoint]) -> Result<(), CheetahError> {
+    let mut generic = Vec::with_capacity(ps.len());
+    let mut denoms = Vec::with_capacity(ps.len());
+    for (i, p) in ps.iter_mut().enumerate() {
+        if p.inf || p.y == F6_ZERO {
+            *p = A_ID;
+        } else {
+            generic.push(i);
+            denoms.push(f6_scal(Belt(2), &p.y));
+        }
+    }
+    f6_batch_inv(&mut denoms)?;
+    for (i, inv) in generic.into_iter().zip(denoms) {
+        let p = ps[i];
+        let slope = f6_mul(&f6_add(&f6_scal(Belt(3), &f6_square(&p.x)), &F6_ONE), &inv);
+        let x = f6_sub(&f6_square(&slope), &f6_scal(Belt(2), &p.x));
+        let y = f6_sub(&f6_mul(&slope, &f6_sub(&p.x, &x)), &p.y);
+        ps[i] = CheetahPoint { x, y, inf: false };
+    }
+    Ok(())
+}
+
+/// Compute `a * p + b * q` for every `(a, p, b, q)` in `terms`.
+///
+/// All terms share one double-and-add over the bits of both scalars (Straus' method), with the
+/// doublings and additions of every term done in a batch, so that each step costs a single field
+/// inversion for the whole of `terms`.
+pub fn ch_double_scal_batch(
+    terms: &[(&UBig, &CheetahPoint, &UBig, &CheetahPoint)],
+) -> Result<Vec<CheetahPoint>, CheetahError> {
+    let ps = terms.iter().map(|t| *t.1).collect::<Vec<_>>();
+    let qs = terms.iter().map(|t| *t.3).collect::<Vec<_>>();
+    let mut pqs = ps.clone();
+    ch_add_batch(&mut pqs, &qs)?;
+
+    let bits = terms
+        .iter()
+        .map(|t| t.0.bit_len().max(t.2.bit_len()))
+        .max()
+        .unwrap_or(0);
+    let mut acc = alloc::vec![A_ID; terms.len()];
+    let mut addends = acc.clone();
+    for bit in (0..bits).rev() {
+        ch_double_batch(&mut acc)?;
+        for (i, (a, _, b, _)) in terms.iter().enumerate() {
+            addends[i] = match (a.bit(bit), b.bit(bit)) {
+                (false, false) => A_ID,
+                (true, false) => ps[i],
+                (false, true) => qs[i],
+                (true, true) => pqs[i],
+            };
+        }
+        ch_add_batch(&mut acc, &addends)?;
     }
     Ok(acc)
 }
 
+/// `3b`, for the curve `y^2 = x^3 + x + b`.
+static B3: Lazy<F6lt> = Lazy::new(|| {
+    let (x, y) = (&A_GEN.x, &A_GEN.y);
+    let b = f6_sub(&f6_sub(&f6_square(y), &f6_mul(x, &f6_square(x))), x);
+    f6_scal(Belt(3), &b)
+});
+
+/// `p^6 - 2`, the exponent that inverts a nonzero element of the sextic extension.
+static F6_INV_EXPONENT: Lazy<UBig> = Lazy::new(|| UBig::from(PRIME).pow(6) - UBig::from(2u64));
+
+/// Invert `f` by exponentiation, which unlike [`f6_inv`] takes the same time for every input.
+/// Maps zero to zero.
+fn f6_inv_ct(f: &F6lt) -> F6lt {
+    let mut acc = F6_ONE;
+    // The branch is on the bits of the public exponent, not on `f`.
+    for i in (0..F6_INV_EXPONENT.bit_len()).rev() {
+        acc = f6_mul_ct(&acc, &acc);
+        if F6_INV_EXPONENT.bit(i) {
+            acc = f6_mul_ct(&acc, f);
+        }
+    }
+    acc
+}
+
+/// All ones if `f` is zero, zero otherwise.
+fn f6_zero_mask(f: &F6lt) -> u64 {
+    eq_mask(f.0.iter().fold(0, |acc, b| acc | b.0), 0)
+}
+
+/// `b` where `mask` is all ones, `a` where it is zero.
+fn f6_select(a: &F6lt, b: &F6lt, mask: u64) -> F6lt {
+    F6lt(core::array::from_fn(|i| {
+        Belt((a.0[i].0 & !mask) | (b.0[i].0 & mask))
+    }))
+}
+
+/// A point in projective coordinates `(X : Y : Z)`, standing for `(X/Z, Y/Z)`.
+///
+/// Addition uses the complete formulas of Renes, Costello and Batina (2016), which have no
+/// special cases: the same sequence of field operations adds distinct points, doubles a point,
+/// and handles the identity `(0 : 1 : 0)`.
+#[derive(Clone, Copy)]
+struct ProjectivePoint {
+    x: F6lt,
+    y: F6lt,
+    z: F6lt,
+}
+
+impl ProjectivePoint {
+    const IDENTITY: Self = Self {
+        x: F6_ZERO,
+        y: F6_ONE,
+        z: F6_ZERO,
+    };
+
+    fn from_affine(p: &CheetahPoint) -> Self {
+        if p.inf {
+            return Self::IDENTITY;
+        }
+        Self {
+            x: p.x,
+            y: p.y,
+            z: F6_ONE,
+        }
+    }
+
+    fn to_affine(self) -> CheetahPoint {
+        let z_inv = f6_inv_ct(&self.z);
+        let id = f6_zero_mask(&self.z);
+        CheetahPoint {
+            x: f6_select(&f6_mul_ct(&self.x, &z_inv), &F6_ZERO, id),
+            y: f6_select(&f6_mul_ct(&self.y, &z_inv), &F6_ONE, id),
+            inf: id != 0,
+        }
+    }
+
+    /// Algorithm 1 of the paper, with `a = 1`. Only branch-free field operations are used.
+    fn add(&self, other: &Self) -> Self {
+        self.add_with(other, f6_mul_ct)
+    }
+
+    /// [`ProjectivePoint::add`] with the faster, variable-time [`f6_mul`], for public points.
+    fn add_vartime(&self, other: &Self) -> Self {
+        self.add_with(other, f6_mul)
+    }
+
+    /// The formulas shared by both additions, with `mul` as the field multiplication.
+    #[inline(always)]
+    fn add_with(&self, other: &Self, mul: fn(&F6lt, &F6lt) -> F6lt) -> Self {
+        let (x1, y1, z1) = (&self.x, &self.y, &self.z);
+        let (x2, y2, z2) = (&other.x, &other.y, &other.z);
+        let b3 = &*B3;
+
+        let t0 = mul(x1, x2);
+        let t1 = mul(y1, y2);
+        let t2 = mul(z1, z2);
+        let t3 = f6_sub(&mul(&f6_add(x1, y1), &f6_add(x2, y2)), &f6_add(&t0, &t1));
+        let t4 = f6_sub(&mul(&f6_add(x1, z1), &f6_add(x2, z2)), &f6_add(&t0, &t2));
+        let t5 = f6_sub(&mul(&f6_add(y1, z1), &f6_add(y2, z2)), &f6_add(&t1, &t2));
+        let z3 = f6_add(&mul(b3, &t2), &t4);
+        let x3 = f6_sub(&t1, &z3);
+        let z3 = f6_add(&t1, &z3);
+        let y3 = mul(&x3, &z3);
+        let t1 = f6_add(&f6_add(&f6_add(&t0, &t0), &t0), &t2);
+        let t4 = f6_add(&mul(b3, &t4), &f6_sub(&t0, &t2));
+        let y3 = f6_add(&y3, &mul(&t1, &t4));
+        let x3 = f6_sub(&mul(&t3, &x3), &mul(&t5, &t4));
+        let z3 = f6_add(&mul(&t5, &z3), &mul(&t3, &t1));
+        Self {
+            x: x3,
+            y: y3,
+            z: z3,
+        }
+    }
+
+    fn neg(&self) -> Self {
+        Self {
+            x: self.x,
+            y: f6_neg(&self.y),
+            z: self.z,
+        }
+    }
+
+    /// [`ProjectivePoint::to_affine`] with a variable-time inversion, for public points.
+    fn to_affine_vartime(self) -> Result<CheetahPoint, CheetahError> {
+        if self.z == F6_ZERO {
+            return Ok(A_ID);
+        }
+        let z_inv = f6_inv(&self.z)?;
+        Ok(CheetahPoint {
+            x: f6_mul(&self.x, &z_inv),
+            y: f6_mul(&self.y, &z_inv),
+            inf: false,
+        })
+    }
+
+    fn select(&self, other: &Self, mask: u64) -> Self {
+        Self {
+            x: f6_select(&self.x, &other.x, mask),
+            y: f6_select(&self.y, &other.y, mask),
+            z: f6_select(&self.z, &other.z, mask),
+        }
+    }
+
+    /// `table[digit]`, reading every entry so that the access pattern does not depend on `digit`.
+    fn lookup(table: &[Self; 16], digit: u64) -> Self {
+        table
+            .iter()
+            .enumerate()
+            .fold(Self::IDENTITY, |acc, (i, p)| {
+                acc.select(p, eq_mask(i as u64, digit))
+            })
+    }
+}
+
+/// [`GEN_TABLE`] in projective coordinates, with the identity for digit 0.
+static GEN_TABLE_CT: Lazy<Vec<[ProjectivePoint; 16]>> = Lazy::new(|| {
+    GEN_TABLE
+        .iter()
+        .map(|row| {
+            core::array::from_fn(|d| match d {
+                0 => ProjectivePoint::IDENTITY,
+                d => ProjectivePoint::from_affine(&row[d - 1]),
+            })
+        })
+        .collect()
+});
+
+/// Compute `k * A_GEN` in constant time, for secret `k`.
+pub fn ch_scal_gen_ct(k: &Scalar) -> CheetahPoint {
+    GEN_TABLE_CT
+        .iter()
+        .enumerate()
+        .fold(ProjectivePoint::IDENTITY, |acc, (i, row)| {
+            acc.add(&ProjectivePoint::lookup(row, k.nibble(i)))
+        })
+        .to_affine()
+}
+
+/// Compute `k * p` in constant time, for secret `k`, with fixed 4-bit windows.
+pub fn ch_scal_ct(k: &Scalar, p: &CheetahPoint) -> CheetahPoint {
+    let p = ProjectivePoint::from_affine(p);
+    let mut table = [ProjectivePoint::IDENTITY; 16];
+    for d in 1..16 {
+        table[d] = table[d - 1].add(&p);
+    }
+    let mut acc = ProjectivePoint::IDENTITY;
+    for i in (0..64).rev() {
+        for _ in 0..4 {
+            acc = acc.add(&acc);
+        }
+        acc = acc.add(&ProjectivePoint::lookup(&table, k.nibble(i)));
+    }
+    acc.to_affine()
+}
+
+/// [`trunc_g_order`] with [`Scalar`] arithmetic, for hashes of secret values.
+pub fn trunc_g_order_ct(a: &[u64]) -> Scalar {
+    let p = Scalar::from_u64(PRIME);
+    let p2 = p * p;
+    let p3 = p2 * p;
+    Scalar::from_u64(a[0])
+        + Scalar::from_u64(a[1]) * p
+        + Scalar::from_u64(a[2]) * p2
+        + Scalar::from_u64(a[3]) * p3
+}
+
 pub fn trunc_g_order(a: &[u64]) -> UBig {
     let mut result = UBig::from(a[0]);
     result += &*P_BIG * UBig::from(a[1]);
@@ -1 +1,2 @@
 pub mod cheetah;
+pub mod scalar;
@@ -0,0 +1,249 @@
+use core::fmt;
+use core::hint::black_box;
+use core::ops::{Add, Mul, Neg, Sub};
+
+use ibig::UBig;
+use once_cell::sync::Lazy;
+
+use super::cheetah::G_ORDER;
+
+/// Limbs of [`G_ORDER`], least significant first.
+const N: [u64; 4] = [
+    0xd443_623e_aed4_accf,
+    0x327a_a723_3015_7722,
+    0x563f_bf0f_990a_37b5,
+    0x7af2_599b_3b3f_22d0,
+];
+
+/// `-N^-1 mod 2^64`, for Montgomery reduction.
+const N_PRIME: u64 = {
+    // Newton's iteration doubles the number of correct low bits each step.
+    let mut inv = 1u64;
+    let mut i = 0;
+    while i < 6 {
+        inv = inv.wrapping_mul(2u64.wrapping_sub(N[0].wrapping_mul(inv)));
+        i += 1;
+    }
+    inv.wrapping_neg()
+};
+
+/// `2^512 mod N`, to move products out of the Montgomery domain.
+static R2: Lazy<Scalar> = Lazy::new(|| {
+    let r2 = (UBig::from(1u64) << 512) % &*G_ORDER;
+    Scalar::from_ubig(&r2)
+});
+
+/// All ones if `bit` is 1, zero if it is 0.
+#[inline(always)]
+pub(crate) fn mask(bit: u64) -> u64 {
+    black_box(0u64.wrapping_sub(bit))
+}
+
+/// All ones if `a == b`, zero otherwise.
+#[inline(always)]
+pub(crate) fn eq_mask(a: u64, b: u64) -> u64 {
+    let x = a ^ b;
+    // The top bit of `x | -x` is set iff `x` is nonzero.
+    mask(1 ^ ((x | x.wrapping_neg()) >> 63))
+}
+
+/// An integer modulo [`G_ORDER`] held in four 64-bit limbs, whose arithmetic takes the same time
+/// whatever the values.
+///
+/// Use it for private keys, nonces and anything else derived from them. `ibig::UBig` arithmetic,
+/// by contrast, depends on the magnitude of its operands.
+#[derive(Clone, Copy)]
+pub struct Scalar([u64; 4]);
+
+impl Scalar {
+    pub const ZERO: Self = Self([0; 4]);
+    pub const ONE: Self = Self([1, 0, 0, 0]);
+
+    pub const fn from_u64(n: u64) -> Self {
+        Self([n, 0, 0, 0])
+    }
+
+    /// Reduce `n` modulo [`G_ORDER`].
+    ///
+    /// `UBig` stores and operates on only as many limbs as its value needs, so its timing reveals
+    /// the magnitude of the value. Use this for public values such as challenges, and keep secrets
+    /// in `Scalar` from the start, with [`Scalar::from_be_bytes`].
+    pub fn from_ubig(n: &UBig) -> Self {
+        if n.bit_len() > 256 {
+            return Self::from_ubig(&(n % &*G_ORDER));
+        }
+        let bytes = n.to_le_bytes();
+        let mut padded = [0u8; 32];
+        padded[..bytes.len()].copy_from_slice(&bytes);
+        // 2^256 < 3N, so two conditional subtractions are enough.
+        let limbs = Self::load_le(&padded);
+        Self(reduce_once(reduce_once(limbs, 0), 0))
+    }
+
+    /// Parse a 32-byte big-endian integer, returning `None` unless it is below [`G_ORDER`].
+    pub fn from_be_bytes(bytes: &[u8; 32]) -> Option<Self> {
+        let mut le = *bytes;
+        le.reverse();
+        let limbs = Self::load_le(&le);
+        let (_, borrow) = sub_limbs(&limbs, &N);
+        (borrow == 1).then_some(Self(limbs))
+    }
+
+    fn load_le(bytes: &[u8; 32]) -> [u64; 4] {
+        let mut limbs = [0u64; 4];
+        for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks_exact(8)) {
+            *limb = u64::from_le_bytes(chunk.try_into().unwrap());
+        }
+        limbs
+    }
+
+    pub fn to_le_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (chunk, limb) in bytes.chunks_exact_mut(8).zip(self.0) {
+            chunk.copy_from_slice(&limb.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn to_be_bytes(&self) -> [u8; 32] {
+        let mut bytes = self.to_le_bytes();
+        bytes.reverse();
+        bytes
+    }
+
+    /// The value as a `UBig`, which is only suitable for public values. See [`Scalar::from_ubig`].
+    pub fn to_ubig(&self) -> UBig {
+        UBig::from_le_bytes(&self.to_le_bytes())
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.ct_eq(&Self::ZERO)
+    }
+
+    /// Equality without an early exit on the first differing limb.
+    pub fn ct_eq(&self, other: &Self) -> bool {
+        let diff = self
+            .0
+            .iter()
+            .zip(other.0)
+            .fold(0, |acc, (a, b)| acc | (a ^ b));
+        eq_mask(diff, 0) != 0
+    }
+
+    /// The 4-bit window `i` of the scalar, counting from the least significant.
+    pub(crate) fn nibble(&self, i: usize) -> u64 {
+        (self.0[i / 16] >> (4 * (i % 16))) & 0xf
+    }
+
+    /// `a * b * 2^-256 mod N`, by coarsely integrated operand scanning.
+    fn mont_mul(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+        let mut t = [0u64; 6];
+        for &ai in a {
+            let mut carry = 0u128;
+            for j in 0..4 {
+                let v = t[j] as u128 + ai as u128 * b[j] as u128 + carry;
+                t[j] = v as u64;
+                carry = v >> 64;
+            }
+            let v = t[4] as u128 + carry;
+            t[4] = v as u64;
+            t[5] = (v >> 64) as u64;
+
+            let m = t[0].wrapping_mul(N_PRIME);
+            let mut carry = (t[0] as u128 + m as u128 * N[0] as u128) >> 64;
+            for j in 1..4 {
+                let v = t[j] as u128 + m as u128 * N[j] as u128 + carry;
+                t[j - 1] = v as u64;
+                carry = v >> 64;
+            }
+            let v = t[4] as u128 + carry;
+            t[3] = v as u64;
+            t[4] = t[5] + (v >> 64) as u64;
+        }
+        reduce_once([t[0], t[1], t[2], t[3]], t[4])
+    }
+}
+
+/// `a - b` and the final borrow.
+#[inline(always)]
+fn sub_limbs(a: &[u64; 4], b: &[u64; 4]) -> ([u64; 4], u64) {
+    let mut out = [0u64; 4];
+    let mut borrow = 0u64;
+    for i in 0..4 {
+        let (d, b1) = a[i].overflowing_sub(b[i]);
+        let (d, b2) = d.overflowing_sub(borrow);
+        out[i] = d;
+        borrow = (b1 | b2) as u64;
+    }
+    (out, borrow)
+}
+
+/// Subtract `N` from the 257-bit value `high * 2^256 + limbs` if it is at least `N`.
+#[inline(always)]
+fn reduce_once(limbs: [u64; 4], high: u64) -> [u64; 4] {
+    let (diff, borrow) = sub_limbs(&limbs, &N);
+    // Keep the difference unless it went negative without a high bit to borrow from.
+    let keep = mask(borrow & (high ^ 1));
+    let mut out = [0u64; 4];
+    for i in 0..4 {
+        out[i] = (limbs[i] & keep) | (diff[i] & !keep);
+    }
+    out
+}
+
+impl Add for Scalar {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        let mut sum = [0u64; 4];
+        let mut carry = 0u64;
+        for (i, limb) in sum.iter_mut().enumerate() {
+            let v = self.0[i] as u128 + rhs.0[i] as u128 + carry as u128;
+            *limb = v as u64;
+            carry = (v >> 64) as u64;
+        }
+        Self(reduce_once(sum, carry))
+    }
+}
+
+impl Neg for Scalar {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        let (diff, _) = sub_limbs(&N, &self.0);
+        // N - 0 is N itself, which must become 0.
+        let zero = mask(self.is_zero() as u64);
+        Self(diff.map(|d| d & !zero))
+    }
+}
+
+impl Sub for Scalar {
+    type Output = Self;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn sub(self, rhs: Self) -> Self {
+        self + -rhs
+    }
+}
+
+impl Mul for Scalar {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self(Self::mont_mul(&Self::mont_mul(&self.0, &rhs.0), &R2.0))
+    }
+}
+
+impl PartialEq for Scalar {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other)
+    }
+}
+
+impl Eq for Scalar {}
+
+impl fmt::Debug for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Scalar(..)")
+    }
+}
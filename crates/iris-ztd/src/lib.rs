@@ -10,7 +10,7 @@ mod hash;
 mod noun;
 mod zmap;
 mod zset;
-pub use belt::Belt;
+pub use belt::{Belt, PRIME};
 pub use hash::*;
 pub use noun::*;
 pub use zmap::*;
@@ -649,7 +649,7 @@ pub fn cue_bitslice(buffer: &BitSlice<u8, Lsb0>) -> Option<Noun> {
             // Need an indirect atom
             let wordsize = (size + 63) >> 6;
             let mut bytes = vec![0u8; wordsize * 8];
-            BitSlice::from_slice_mut(&mut bytes).copy_from_bitslice(bits);
+            BitSlice::from_slice_mut(&mut bytes)[0..bits.len()].copy_from_bitslice(bits);
             Some(UBig::from_le_bytes(&bytes))
         }
     }
@@ -712,3 +712,22 @@ pub fn cue_bitslice(buffer: &BitSlice<u8, Lsb0>) -> Option<Noun> {
 
     Some(result)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cue_indirect_atom() {
+        // Atoms of 64 bits or more are decoded into whole words, which the atom's bits only
+        // partly fill unless its size is a multiple of 64.
+        for bits in [64, 65, 100, 128, 200] {
+            let atom = (UBig::from(1u64) << (bits - 1)) + UBig::from(12345u64);
+            let noun = Noun::Cell(
+                Box::new(Noun::Atom(atom)),
+                Box::new(Noun::Atom(UBig::from(7u64))),
+            );
+            assert_eq!(cue(&jam(noun.clone())), Some(noun));
+        }
+    }
+}